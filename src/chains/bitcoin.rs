@@ -0,0 +1,804 @@
+use async_trait::async_trait;
+use bitcoin::{
+    Address, Amount, CompressedPublicKey, Network, OutPoint, Psbt, ScriptBuf, Sequence,
+    Transaction as BitcoinTransaction, TxIn, TxOut, Txid, Witness,
+    absolute::LockTime,
+    consensus::encode::{deserialize, serialize, serialize_hex},
+    ecdsa,
+    hashes::Hash,
+    secp256k1::ecdsa::Signature as BitcoinSignature,
+    sighash::{EcdsaSighashType, SighashCache},
+    transaction::Version,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env, str::FromStr, sync::Arc, time::Duration};
+
+use crate::errors::{
+    amount_errors::AmountError, bitcoin_errors::BitcoinError, chain_errors::ChainError,
+};
+use crate::{
+    chains::features::{
+        ChainFeatures, ChainPublicKey, ChainSigner, KeyScheme, NativeTransfer, SignedTransaction,
+        UnsignedTransaction,
+    },
+    models::user_wallet_model::ChainInfo,
+    services::{
+        chains_services::{TXChain, generate_bitcoin_chain_data},
+        derivation_services::{
+            BITCOIN_PATH_COIN_TYPE, BITCOIN_PATH_PURPOSE, BITCOIN_TESTNET_PATH_COIN_TYPE,
+            DerivationPath,
+        },
+        signing_services::ThresholdSigner,
+    },
+};
+
+// Outputs below this are non-standard for P2WPKH and get folded into the fee.
+pub const DUST_LIMIT: u64 = 294;
+// Confirmation targets, in blocks, of the slow, normal and fast fee presets.
+const FEE_TARGET_BLOCKS: [u16; 3] = [12, 6, 2];
+
+#[derive(Debug, Clone)]
+pub struct BitcoinConfig {
+    pub network: Network,
+    pub esplora_url: String,
+}
+
+impl BitcoinConfig {
+    // BITCOIN_NETWORK is one of bitcoin, testnet, signet or regtest.
+    pub fn from_env() -> Self {
+        let network = env::var("BITCOIN_NETWORK")
+            .ok()
+            .and_then(|value| Network::from_str(&value).ok())
+            .unwrap_or(Network::Bitcoin);
+        let esplora_url = env::var("BITCOIN_ESPLORA_URL")
+            .unwrap_or_else(|_| String::from("https://blockstream.info/api"));
+        BitcoinConfig {
+            network,
+            esplora_url,
+        }
+    }
+}
+
+// Virtual sizes of a P2WPKH only transaction: 10.5 vB of overhead, 68 vB per
+// input and 31 vB per output, rounded up.
+fn estimate_vsize(inputs: usize, outputs: usize) -> u64 {
+    (42 + 272 * inputs as u64 + 124 * outputs as u64).div_ceil(4)
+}
+
+pub fn p2wpkh_address(
+    public_key: &secp256k1::PublicKey,
+    network: Network,
+) -> Result<Address, BitcoinError> {
+    let public_key = CompressedPublicKey::from_slice(&public_key.serialize())
+        .map_err(|e| BitcoinError::InvalidAddress(e.to_string()))?;
+    Ok(Address::p2wpkh(&public_key, network))
+}
+
+// The network an address was encoded for. Testnet and signet share a prefix, so
+// either one is fine for checking the recipient.
+pub fn address_network(address: &str) -> Result<Network, BitcoinError> {
+    let address = Address::from_str(address)
+        .map_err(|_| BitcoinError::InvalidAddress(address.to_string()))?;
+    [Network::Bitcoin, Network::Testnet, Network::Regtest]
+        .into_iter()
+        .find(|network| address.is_valid_for_network(*network))
+        .ok_or_else(|| BitcoinError::InvalidAddress(address.assume_checked().to_string()))
+}
+
+fn parse_address(address: &str, network: Network) -> Result<Address, BitcoinError> {
+    Address::from_str(address)
+        .and_then(|address| address.require_network(network))
+        .map_err(|_| BitcoinError::InvalidAddress(address.to_string()))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Utxo {
+    pub txid: String,
+    pub vout: u32,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoinSelection {
+    pub inputs: Vec<Utxo>,
+    pub fee: u64,
+    pub change: u64,
+}
+
+// Largest first selection. A change output is only added when what is left
+// over after its own fee is above the dust limit, otherwise it goes to fees.
+// Totals that overflow a u64 can never be funded.
+pub fn select_coins(
+    utxos: &[Utxo],
+    amount: u64,
+    fee_rate: u64,
+) -> Result<CoinSelection, BitcoinError> {
+    if amount < DUST_LIMIT {
+        return Err(BitcoinError::AmountBelowDust(amount));
+    }
+    let mut candidates = utxos.to_vec();
+    candidates.sort_by_key(|utxo| std::cmp::Reverse(utxo.value));
+
+    let fee = |inputs: usize, outputs: usize| estimate_vsize(inputs, outputs).checked_mul(fee_rate);
+    let mut inputs = Vec::new();
+    let mut total = 0u64;
+    for utxo in candidates {
+        total = total.saturating_add(utxo.value);
+        inputs.push(utxo);
+
+        let needed = fee(inputs.len(), 1).and_then(|fee| amount.checked_add(fee));
+        if needed.is_none_or(|needed| total < needed) {
+            continue;
+        }
+        let with_change = fee(inputs.len(), 2).and_then(|fee| {
+            let needed = amount.checked_add(fee)?.checked_add(DUST_LIMIT)?;
+            Some((fee, needed))
+        });
+        if let Some((fee_with_change, needed)) = with_change
+            && total >= needed
+        {
+            return Ok(CoinSelection {
+                inputs,
+                fee: fee_with_change,
+                change: total - amount - fee_with_change,
+            });
+        }
+        return Ok(CoinSelection {
+            inputs,
+            fee: total - amount,
+            change: 0,
+        });
+    }
+
+    let needed = fee(inputs.len().max(1), 1)
+        .and_then(|fee| amount.checked_add(fee))
+        .unwrap_or(u64::MAX);
+    Err(BitcoinError::InsufficientFunds(needed, total))
+}
+
+// Where UTXOs and fee estimates come from and where signed transactions go.
+#[async_trait]
+pub trait BitcoinBackend: Send + Sync {
+    async fn utxos(&self, address: &Address) -> Result<Vec<Utxo>, BitcoinError>;
+    // Fee rate in sat/vB expected to confirm within `target_blocks`.
+    async fn fee_rate(&self, target_blocks: u16) -> Result<f64, BitcoinError>;
+    async fn broadcast(&self, transaction: &BitcoinTransaction) -> Result<Txid, BitcoinError>;
+    // `None` while the backend has not seen the transaction.
+    async fn transaction(&self, txid: &Txid) -> Result<Option<BitcoinReceipt>, BitcoinError>;
+}
+
+pub struct EsploraBackend {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl EsploraBackend {
+    pub fn new(base_url: &str) -> Self {
+        EsploraBackend {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl BitcoinBackend for EsploraBackend {
+    async fn utxos(&self, address: &Address) -> Result<Vec<Utxo>, BitcoinError> {
+        self.client
+            .get(format!("{}/address/{}/utxo", self.base_url, address))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))
+    }
+
+    async fn fee_rate(&self, target_blocks: u16) -> Result<f64, BitcoinError> {
+        let estimates: HashMap<String, f64> = self
+            .client
+            .get(format!("{}/fee-estimates", self.base_url))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?;
+        // Use the closest target at or below the requested one.
+        Ok(estimates
+            .into_iter()
+            .filter_map(|(target, rate)| Some((target.parse::<u16>().ok()?, rate)))
+            .filter(|(target, _)| *target <= target_blocks)
+            .max_by_key(|(target, _)| *target)
+            .map(|(_, rate)| rate)
+            .unwrap_or(1.0))
+    }
+
+    async fn broadcast(&self, transaction: &BitcoinTransaction) -> Result<Txid, BitcoinError> {
+        let txid = self
+            .client
+            .post(format!("{}/tx", self.base_url))
+            .body(serialize_hex(transaction))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?;
+        Txid::from_str(txid.trim()).map_err(|e| BitcoinError::BackendError(e.to_string()))
+    }
+
+    async fn transaction(&self, txid: &Txid) -> Result<Option<BitcoinReceipt>, BitcoinError> {
+        let response = self
+            .client
+            .get(format!("{}/tx/{}", self.base_url, txid))
+            .send()
+            .await
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let transaction: EsploraTransaction = response
+            .error_for_status()
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| BitcoinError::BackendError(e.to_string()))?;
+        Ok(Some(BitcoinReceipt {
+            txid: transaction.txid,
+            fee: transaction.fee,
+            confirmed: transaction.status.confirmed,
+            block_height: transaction.status.block_height,
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraTransaction {
+    txid: String,
+    fee: u64,
+    status: EsploraStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+// The wallet key is P2WPKH on `network`. UTXOs, fee rates and broadcasting go
+// through `backend`, or an Esplora instance at the chain's rpc_url when unset.
+#[derive(Clone)]
+pub struct Bitcoin {
+    network: Network,
+    backend: Option<Arc<dyn BitcoinBackend>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BitcoinReceipt {
+    pub txid: String,
+    pub fee: u64,
+    pub confirmed: bool,
+    pub block_height: Option<u64>,
+}
+
+#[async_trait]
+impl ChainFeatures for Bitcoin {
+    fn key_scheme(&self) -> KeyScheme {
+        KeyScheme::Secp256k1
+    }
+
+    fn native_decimals(&self) -> u32 {
+        8
+    }
+
+    fn derive_address(&self, public_key: &ChainPublicKey) -> Result<(String, String), ChainError> {
+        match public_key {
+            ChainPublicKey::Secp256k1(public_key) => {
+                Ok(generate_bitcoin_chain_data(public_key, self.network)?)
+            }
+            ChainPublicKey::Ed25519(_) => Err(ChainError::UnsupportedKey),
+        }
+    }
+
+    // m/84/<coin>/<account>/0/<index>, all unhardened, so not BIP84 compatible.
+    fn derivation_path(&self, account: u32, index: u32) -> Result<DerivationPath, ChainError> {
+        let coin_type = match self.network {
+            Network::Bitcoin => BITCOIN_PATH_COIN_TYPE,
+            _ => BITCOIN_TESTNET_PATH_COIN_TYPE,
+        };
+        Ok(DerivationPath::new(
+            BITCOIN_PATH_PURPOSE,
+            coin_type,
+            account,
+            index,
+        ))
+    }
+
+    async fn balance(&self, chain_data: &ChainInfo, address: &str) -> Result<String, ChainError> {
+        let address = parse_address(address, self.network)?;
+        let utxos = self.backend(chain_data).utxos(&address).await?;
+        Ok(utxos.iter().map(|utxo| utxo.value).sum::<u64>().to_string())
+    }
+
+    async fn estimate_fee(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<String, ChainError> {
+        let (selection, _, _, _) = self.select(chain_data, transfer).await?;
+        Ok(selection.fee.to_string())
+    }
+
+    // Sends `transfer.amount` sats from `transfer.from` to `transfer.to`, returning
+    // change to the sending address.
+    async fn build_transaction(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<UnsignedTransaction, ChainError> {
+        let (selection, from, to, amount) = self.select(chain_data, transfer).await?;
+        let psbt = Self::build_psbt(&selection, &from, &to, amount)?;
+        Ok(UnsignedTransaction(psbt.serialize()))
+    }
+
+    async fn sign_transaction(
+        &self,
+        transaction: UnsignedTransaction,
+        signer: &ChainSigner,
+    ) -> Result<SignedTransaction, ChainError> {
+        let mut psbt = Psbt::deserialize(&transaction.0)
+            .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))?;
+        let signer = signer.ecdsa()?.clone();
+        let transaction = tokio::task::spawn_blocking(move || {
+            Self::sign_psbt(&mut psbt, &signer)?;
+            Self::finalize_psbt(psbt)
+        })
+        .await
+        .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))??;
+        Ok(SignedTransaction(serialize(&transaction)))
+    }
+
+    async fn broadcast(
+        &self,
+        chain_data: &ChainInfo,
+        transaction: &SignedTransaction,
+    ) -> Result<String, ChainError> {
+        let transaction: BitcoinTransaction = deserialize(&transaction.0)
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))?;
+        let txid = self.backend(chain_data).broadcast(&transaction).await?;
+        Ok(txid.to_string())
+    }
+
+    // Bitcoin blocks are too slow to wait on, so a transaction counts as accepted
+    // once the backend has it in its mempool.
+    async fn receipt(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<Option<TXChain>, ChainError> {
+        let txid = Txid::from_str(transaction_id)
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))?;
+        Ok(self
+            .backend(chain_data)
+            .transaction(&txid)
+            .await?
+            .map(TXChain::BITCOIN))
+    }
+
+    async fn is_known(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<bool, ChainError> {
+        Ok(self.receipt(chain_data, transaction_id).await?.is_some())
+    }
+
+    fn receipt_poll_interval(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn receipt_timeout(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+}
+
+impl Bitcoin {
+    pub fn new(network: Network) -> Self {
+        Bitcoin {
+            network,
+            backend: None,
+        }
+    }
+
+    pub fn with_backend(network: Network, backend: Arc<dyn BitcoinBackend>) -> Self {
+        Bitcoin {
+            network,
+            backend: Some(backend),
+        }
+    }
+
+    fn backend(&self, chain_data: &ChainInfo) -> Arc<dyn BitcoinBackend> {
+        match &self.backend {
+            Some(backend) => backend.clone(),
+            None => Arc::new(EsploraBackend::new(&chain_data.rpc_url)),
+        }
+    }
+
+    async fn select(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<(CoinSelection, Address, Address, u64), ChainError> {
+        let amount = u64::try_from(transfer.amount).map_err(|_| AmountError::Overflow)?;
+        if amount > Amount::MAX_MONEY.to_sat() {
+            return Err(AmountError::InvalidAmount(format!(
+                "{} sats is more than the {} sat supply",
+                amount,
+                Amount::MAX_MONEY.to_sat()
+            ))
+            .into());
+        }
+        let from = parse_address(&transfer.from, self.network)?;
+        let to = parse_address(&transfer.to, self.network)?;
+
+        let backend = self.backend(chain_data);
+        let utxos = backend.utxos(&from).await?;
+        let fee_rate = backend
+            .fee_rate(FEE_TARGET_BLOCKS[transfer.fees.speed as usize])
+            .await?
+            .ceil()
+            .max(1.0) as u64;
+        let selection = select_coins(&utxos, amount, fee_rate)?;
+        Ok((selection, from, to, amount))
+    }
+
+    pub fn build_psbt(
+        selection: &CoinSelection,
+        from: &Address,
+        to: &Address,
+        amount: u64,
+    ) -> Result<Psbt, BitcoinError> {
+        let input = selection
+            .inputs
+            .iter()
+            .map(|utxo| {
+                let txid = Txid::from_str(&utxo.txid)
+                    .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))?;
+                Ok(TxIn {
+                    previous_output: OutPoint::new(txid, utxo.vout),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                    witness: Witness::new(),
+                })
+            })
+            .collect::<Result<Vec<_>, BitcoinError>>()?;
+
+        let mut output = vec![TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey: to.script_pubkey(),
+        }];
+        if selection.change > 0 {
+            output.push(TxOut {
+                value: Amount::from_sat(selection.change),
+                script_pubkey: from.script_pubkey(),
+            });
+        }
+
+        let mut psbt = Psbt::from_unsigned_tx(BitcoinTransaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input,
+            output,
+        })
+        .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))?;
+        for (psbt_input, utxo) in psbt.inputs.iter_mut().zip(&selection.inputs) {
+            psbt_input.witness_utxo = Some(TxOut {
+                value: Amount::from_sat(utxo.value),
+                script_pubkey: from.script_pubkey(),
+            });
+        }
+        Ok(psbt)
+    }
+
+    // Adds a threshold signature for every input paying to the wallet key.
+    pub fn sign_psbt(psbt: &mut Psbt, signer: &ThresholdSigner) -> Result<(), BitcoinError> {
+        let public_key = CompressedPublicKey::from_slice(&signer.public_key().serialize())
+            .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))?;
+        let script_pubkey = ScriptBuf::new_p2wpkh(&public_key.wpubkey_hash());
+
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let mut signatures = Vec::with_capacity(psbt.inputs.len());
+        for (index, input) in psbt.inputs.iter().enumerate() {
+            let Some(utxo) = input
+                .witness_utxo
+                .as_ref()
+                .filter(|utxo| utxo.script_pubkey == script_pubkey)
+            else {
+                return Err(BitcoinError::ForeignInput(index));
+            };
+            let sighash = cache
+                .p2wpkh_signature_hash(index, &script_pubkey, utxo.value, EcdsaSighashType::All)
+                .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))?;
+
+            let signature = signer.sign_hash(sighash.to_byte_array())?;
+            let mut compact = [0u8; 64];
+            compact[..32].copy_from_slice(&signature.r);
+            compact[32..].copy_from_slice(&signature.s);
+            signatures.push(ecdsa::Signature {
+                signature: BitcoinSignature::from_compact(&compact)
+                    .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))?,
+                sighash_type: EcdsaSighashType::All,
+            });
+        }
+
+        for (input, signature) in psbt.inputs.iter_mut().zip(signatures) {
+            input.partial_sigs.insert(public_key.into(), signature);
+        }
+        Ok(())
+    }
+
+    pub fn finalize_psbt(mut psbt: Psbt) -> Result<BitcoinTransaction, BitcoinError> {
+        for (index, input) in psbt.inputs.iter_mut().enumerate() {
+            let Some((public_key, signature)) = input.partial_sigs.pop_first() else {
+                return Err(BitcoinError::InvalidPsbt(format!(
+                    "input {} is unsigned",
+                    index
+                )));
+            };
+            input.final_script_witness = Some(Witness::p2wpkh(&signature, &public_key.inner));
+        }
+        psbt.extract_tx()
+            .map_err(|e| BitcoinError::InvalidPsbt(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::features::FeeStrategy;
+    use crate::models::user_wallet_model::{ChainType, KeyShare};
+    use crate::services::{
+        dkg_services,
+        key_services::{Secp256k1Group, SharePolicy},
+    };
+    use bitcoin::secp256k1::{Message, Secp256k1};
+    use ethers::types::U256;
+    use std::sync::Mutex;
+
+    fn utxo(byte: u8, value: u64) -> Utxo {
+        Utxo {
+            txid: hex::encode([byte; 32]),
+            vout: 0,
+            value,
+        }
+    }
+
+    // A 2-of-3 wallet key, its signer and its regtest address.
+    fn wallet() -> (ThresholdSigner, Address) {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let joint_key = dkg_services::run_in_process::<Secp256k1Group>(policy).unwrap();
+        let address = p2wpkh_address(&joint_key.public_key, Network::Regtest).unwrap();
+        let shares: Vec<KeyShare> = joint_key.shares[1..].to_vec();
+        let signer = ThresholdSigner::new(
+            shares,
+            policy.threshold,
+            &hex::encode(joint_key.public_key.serialize()),
+        )
+        .unwrap();
+        (signer, address)
+    }
+
+    fn recipient() -> Address {
+        parse_address(
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+            Network::Regtest,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn derivation_paths_are_unhardened_under_purpose_84() {
+        let mainnet = Bitcoin::new(Network::Bitcoin)
+            .derivation_path(2, 5)
+            .unwrap();
+        let regtest = Bitcoin::new(Network::Regtest)
+            .derivation_path(0, 0)
+            .unwrap();
+
+        assert_eq!(mainnet.to_string(), "m/84/0/2/0/5");
+        assert_eq!(regtest.to_string(), "m/84/1/0/0/0");
+    }
+
+    #[test]
+    fn selection_adds_change_above_dust() {
+        let utxos = [utxo(1, 20_000), utxo(2, 100_000), utxo(3, 50_000)];
+
+        let selection = select_coins(&utxos, 120_000, 2).unwrap();
+
+        // Largest first: 100k and 50k, with change. 2 inputs and 2 outputs are 209 vB.
+        assert_eq!(
+            selection
+                .inputs
+                .iter()
+                .map(|utxo| utxo.value)
+                .collect::<Vec<_>>(),
+            [100_000, 50_000]
+        );
+        assert_eq!(selection.fee, 418);
+        assert_eq!(selection.change, 150_000 - 120_000 - 418);
+    }
+
+    #[test]
+    fn dust_change_goes_to_the_fee() {
+        // 1 input and 1 output are 110 vB; with change 141 vB, which would leave
+        // 159 sats of change, below the dust limit.
+        let selection = select_coins(&[utxo(1, 10_000)], 9_700, 1).unwrap();
+
+        assert_eq!(selection.change, 0);
+        assert_eq!(selection.fee, 300);
+    }
+
+    #[test]
+    fn selection_rejects_dust_and_short_balances() {
+        assert!(matches!(
+            select_coins(&[utxo(1, 10_000)], DUST_LIMIT - 1, 1),
+            Err(BitcoinError::AmountBelowDust(_))
+        ));
+        assert!(matches!(
+            select_coins(&[utxo(1, 5_000), utxo(2, 4_000)], 9_000, 1),
+            Err(BitcoinError::InsufficientFunds(needed, 9_000)) if needed > 9_000
+        ));
+        assert!(matches!(
+            select_coins(&[utxo(1, u64::MAX), utxo(2, 1)], u64::MAX - 10, 1),
+            Err(BitcoinError::InsufficientFunds(u64::MAX, u64::MAX))
+        ));
+    }
+
+    #[test]
+    fn signed_psbt_verifies_against_the_wallet_key() {
+        let (signer, from) = wallet();
+        let utxos = [utxo(1, 30_000), utxo(2, 25_000)];
+        let selection = select_coins(&utxos, 40_000, 1).unwrap();
+        let mut psbt = Bitcoin::build_psbt(&selection, &from, &recipient(), 40_000).unwrap();
+
+        Bitcoin::sign_psbt(&mut psbt, &signer).unwrap();
+        let unsigned = psbt.unsigned_tx.clone();
+        let spent = psbt
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone().unwrap())
+            .collect::<Vec<_>>();
+        let transaction = Bitcoin::finalize_psbt(psbt).unwrap();
+
+        assert_eq!(transaction.input.len(), 2);
+        assert_eq!(transaction.output[0].value, Amount::from_sat(40_000));
+        assert_eq!(transaction.output[1].script_pubkey, from.script_pubkey());
+        let public_key = CompressedPublicKey::from_slice(&signer.public_key().serialize()).unwrap();
+        let mut cache = SighashCache::new(&unsigned);
+        for (index, input) in transaction.input.iter().enumerate() {
+            let witness = input.witness.to_vec();
+            assert_eq!(witness.len(), 2);
+            assert_eq!(witness[1], public_key.to_bytes());
+            let signature = ecdsa::Signature::from_slice(&witness[0]).unwrap();
+            let sighash = cache
+                .p2wpkh_signature_hash(
+                    index,
+                    &from.script_pubkey(),
+                    spent[index].value,
+                    EcdsaSighashType::All,
+                )
+                .unwrap();
+            Secp256k1::verification_only()
+                .verify_ecdsa(
+                    &Message::from_digest(sighash.to_byte_array()),
+                    &signature.signature,
+                    &public_key.0,
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn inputs_of_other_scripts_are_not_signed() {
+        let (signer, from) = wallet();
+        let selection = select_coins(&[utxo(1, 30_000)], 10_000, 1).unwrap();
+        let mut psbt = Bitcoin::build_psbt(&selection, &recipient(), &from, 10_000).unwrap();
+
+        assert!(matches!(
+            Bitcoin::sign_psbt(&mut psbt, &signer),
+            Err(BitcoinError::ForeignInput(0))
+        ));
+    }
+
+    // Serves fixed UTXOs and fee rates and keeps what gets broadcast.
+    struct FixtureBackend {
+        utxos: Vec<Utxo>,
+        broadcasts: Mutex<Vec<BitcoinTransaction>>,
+    }
+
+    #[async_trait]
+    impl BitcoinBackend for FixtureBackend {
+        async fn utxos(&self, _address: &Address) -> Result<Vec<Utxo>, BitcoinError> {
+            Ok(self.utxos.clone())
+        }
+
+        async fn fee_rate(&self, target_blocks: u16) -> Result<f64, BitcoinError> {
+            Ok(12.0 / target_blocks as f64)
+        }
+
+        async fn broadcast(&self, transaction: &BitcoinTransaction) -> Result<Txid, BitcoinError> {
+            self.broadcasts.lock().unwrap().push(transaction.clone());
+            Ok(transaction.compute_txid())
+        }
+
+        async fn transaction(&self, _txid: &Txid) -> Result<Option<BitcoinReceipt>, BitcoinError> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn transfer_is_built_signed_and_broadcast() {
+        let (signer, from) = wallet();
+        let backend = Arc::new(FixtureBackend {
+            utxos: vec![utxo(1, 80_000)],
+            broadcasts: Mutex::new(Vec::new()),
+        });
+        let chain = Bitcoin::with_backend(Network::Regtest, backend.clone());
+        let chain_data = ChainInfo {
+            index: 0,
+            account: 0,
+            public_key: String::new(),
+            address: from.to_string(),
+            balance: String::from("0"),
+            rpc_url: String::new(),
+            chain_type: ChainType::BITCOIN,
+            addresses: Vec::new(),
+            tokens: Vec::new(),
+            nfts: Vec::new(),
+        };
+        let transfer = NativeTransfer {
+            from: from.to_string(),
+            to: recipient().to_string(),
+            amount: U256::from(50_000u64),
+            fees: FeeStrategy::default(),
+            nonce: None,
+        };
+
+        // The normal preset targets 6 blocks, 2 sat/vB here.
+        assert_eq!(
+            chain.estimate_fee(&chain_data, &transfer).await.unwrap(),
+            "282"
+        );
+        let transaction = chain
+            .build_transaction(&chain_data, &transfer)
+            .await
+            .unwrap();
+        let transaction = chain
+            .sign_transaction(transaction, &ChainSigner::Ecdsa(signer))
+            .await
+            .unwrap();
+        let txid = chain.broadcast(&chain_data, &transaction).await.unwrap();
+
+        let above_supply = NativeTransfer {
+            amount: U256::from(Amount::MAX_MONEY.to_sat() + 1),
+            ..transfer
+        };
+        assert!(matches!(
+            chain.build_transaction(&chain_data, &above_supply).await,
+            Err(ChainError::Amount(AmountError::InvalidAmount(_)))
+        ));
+
+        let broadcasts = backend.broadcasts.lock().unwrap();
+        assert_eq!(broadcasts.len(), 1);
+        assert_eq!(broadcasts[0].compute_txid().to_string(), txid);
+        assert_eq!(
+            broadcasts[0].output[1].value,
+            Amount::from_sat(80_000 - 50_000 - 282)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use ethers::{
+    contract::abigen,
+    providers::{Http, Provider},
+    types::{Address, Bytes, U256},
+};
+use std::sync::Arc;
+
+use crate::{
+    chains::ethereum::Ethereum,
+    errors::chain_errors::ChainError,
+    models::user_wallet_model::{ChainInfo, FungibleTokenData},
+};
+
+abigen!(
+    IERC20,
+    r#"[
+        function symbol() external view returns (string)
+        function decimals() external view returns (uint8)
+        function balanceOf(address owner) external view returns (uint256)
+        function transfer(address to, uint256 amount) external returns (bool)
+        function approve(address spender, uint256 amount) external returns (bool)
+    ]"#
+);
+
+pub struct Erc20 {
+    contract: IERC20<Provider<Http>>,
+}
+
+impl Erc20 {
+    pub fn new(chain_data: &ChainInfo, contract: &str) -> Result<Self, ChainError> {
+        let provider = Provider::<Http>::try_from(chain_data.rpc_url.as_str())
+            .map_err(|e| ChainError::Rpc(e.to_string()))?;
+        Ok(Erc20 {
+            contract: IERC20::new(Ethereum::parse_address(contract)?, Arc::new(provider)),
+        })
+    }
+
+    pub fn address(&self) -> Address {
+        self.contract.address()
+    }
+
+    pub async fn metadata(&self) -> Result<FungibleTokenData, ChainError> {
+        let symbol = self
+            .contract
+            .symbol()
+            .call()
+            .await
+            .map_err(|e| ChainError::Rpc(e.to_string()))?;
+        let decimals = self
+            .contract
+            .decimals()
+            .call()
+            .await
+            .map_err(|e| ChainError::Rpc(e.to_string()))?;
+        Ok(FungibleTokenData {
+            contract: format!("{:#x}", self.address()),
+            symbol,
+            decimals,
+        })
+    }
+
+    pub async fn balance_of(&self, owner: &str) -> Result<U256, ChainError> {
+        self.contract
+            .balance_of(Ethereum::parse_address(owner)?)
+            .call()
+            .await
+            .map_err(|e| ChainError::Rpc(e.to_string()))
+    }
+
+    pub fn transfer_calldata(&self, to: &str, amount: U256) -> Result<Bytes, ChainError> {
+        self.contract
+            .transfer(Ethereum::parse_address(to)?, amount)
+            .calldata()
+            .ok_or_else(|| ChainError::InvalidTransaction(String::from("Missing calldata")))
+    }
+
+    pub fn approve_calldata(&self, spender: &str, amount: U256) -> Result<Bytes, ChainError> {
+        self.contract
+            .approve(Ethereum::parse_address(spender)?, amount)
+            .calldata()
+            .ok_or_else(|| ChainError::InvalidTransaction(String::from("Missing calldata")))
+    }
+}
@@ -1,86 +1,599 @@
 use async_trait::async_trait;
 use ethers::providers::{Http, Middleware, Provider};
-use ethers::types::{BlockId, BlockNumber, Eip1559TransactionRequest, TransactionReceipt, U256};
-use ethers::{
-    core::types::Address,
-    middleware::SignerMiddleware,
-    signers::{LocalWallet, Signer},
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{
+    BlockId, BlockNumber, Bytes, Eip1559TransactionRequest, H256, Signature, Transaction,
+    TransactionRequest, U256,
 };
-use eyre::Result;
+use ethers::{core::types::Address, signers::to_eip155_v};
 use hex;
 use serde::{Deserialize, Serialize};
 
-use crate::routes::handler::response_handler::ErrorResponse;
+use crate::errors::chain_errors::ChainError;
 use crate::{
-    chains::features::ChainFeatures, models::user_wallet_model::ChainInfo,
-    routes::handler::transaction_handler::Transaction,
+    chains::features::{
+        AccountNonces, ChainFeatures, ChainPublicKey, ChainSigner, FeeStrategy, KeyScheme,
+        NativeTransfer, SignedTransaction, UnsignedTransaction,
+    },
+    models::user_wallet_model::ChainInfo,
+    services::{
+        chains_services::{EVMResponse, TXChain, generate_chain_data},
+        derivation_services::{DerivationPath, EVM_COIN_TYPE, EVM_PURPOSE},
+    },
 };
 
+// Blocks of fee history sampled for priority fees.
+const FEE_HISTORY_BLOCKS: u64 = 10;
+// Priority fee percentiles for the slow, normal and fast presets.
+const REWARD_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+// Gas price of the slow, normal and fast presets as a percentage of eth_gasPrice.
+const LEGACY_PRICE_PERCENT: [u64; 3] = [100, 110, 125];
+// 0.1 gwei, so quiet blocks with empty rewards still get a tip.
+const MIN_PRIORITY_FEE: u64 = 100_000_000;
+// Nodes only accept a replacement paying at least 10% more than the original.
+const REPLACEMENT_BUMP_PERCENT: u64 = 10;
+// Gas of a plain value transfer, which is what a cancellation is.
+const TRANSFER_GAS: u64 = 21_000;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Ethereum;
 
+#[derive(Debug, Clone, Copy)]
+enum GasFees {
+    Eip1559 { max_fee: U256, priority_fee: U256 },
+    Legacy { gas_price: U256 },
+}
+
+// Legacy transaction requests drop their chain id when serialized, so it is
+// carried next to the transaction.
+#[derive(Debug, Deserialize, Serialize)]
+struct EncodedTransaction {
+    chain_id: u64,
+    transaction: TypedTransaction,
+}
+
+fn cap(fee: U256, cap: Option<U256>) -> U256 {
+    cap.map_or(fee, |cap| fee.min(cap))
+}
+
+fn bump(fee: U256) -> U256 {
+    fee * (100 + REPLACEMENT_BUMP_PERCENT) / 100 + 1
+}
+
 impl Ethereum {
-    pub async fn send_native(
-        chain_data: &ChainInfo,
-        payload: &Transaction,
-        private_key: String,
-    ) -> Result<(ethers::types::Transaction, TransactionReceipt), eyre::Report> {
-        let provider = Provider::<Http>::try_from(&chain_data.rpc_url)?;
-        let priority_fee = U256::from(2_000_000_000u64);
+    fn provider(chain_data: &ChainInfo) -> Result<Provider<Http>, ChainError> {
+        Provider::<Http>::try_from(chain_data.rpc_url.as_str())
+            .map_err(|e| ChainError::Rpc(e.to_string()))
+    }
+
+    pub fn parse_address(address: &str) -> Result<Address, ChainError> {
+        address
+            .parse::<Address>()
+            .map_err(|_| ChainError::InvalidAddress(address.to_string()))
+    }
+
+    // Per-gas fees for `fees`. Chains without a base fee get a legacy gas price.
+    async fn gas_fees(
+        provider: &Provider<Http>,
+        fees: &FeeStrategy,
+    ) -> Result<GasFees, ChainError> {
         let latest_block = provider
             .get_block(BlockId::Number(BlockNumber::Latest))
             .await?
-            .expect("latest block error");
-        let base_fee = latest_block
-            .base_fee_per_gas
-            .ok_or_else(|| eyre::eyre!("Missing base fee in latest block"))?;
-        let max_fee = base_fee * 2 + priority_fee;
-
-        let from = payload.from.parse::<Address>()?;
-        let to = payload.to.parse::<Address>()?;
-        let nonce = provider.get_transaction_count(from, None).await?;
-        let chain_id = provider.get_chainid().await?;
-        let gas = match provider
-            .estimate_gas(
-                &ethers::types::transaction::eip2718::TypedTransaction::Eip1559(
+            .ok_or_else(|| ChainError::Rpc(String::from("Missing latest block")))?;
+        let Some(base_fee) = latest_block.base_fee_per_gas else {
+            let gas_price = provider.get_gas_price().await?;
+            let gas_price = gas_price * LEGACY_PRICE_PERCENT[fees.speed as usize] / 100;
+            return Ok(GasFees::Legacy {
+                gas_price: cap(gas_price, fees.max_fee_per_gas),
+            });
+        };
+
+        // The last entry is the base fee of the next block.
+        let history = provider
+            .fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &REWARD_PERCENTILES)
+            .await?;
+        let base_fee = history.base_fee_per_gas.last().copied().unwrap_or(base_fee);
+        let rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|rewards| rewards.get(fees.speed as usize).copied())
+            .filter(|reward| !reward.is_zero())
+            .collect();
+        let priority_fee = match rewards.len() {
+            0 => U256::zero(),
+            count => {
+                rewards
+                    .iter()
+                    .fold(U256::zero(), |sum, reward| sum + reward)
+                    / count
+            }
+        }
+        .max(U256::from(MIN_PRIORITY_FEE));
+
+        let max_fee = cap(base_fee * 2 + priority_fee, fees.max_fee_per_gas);
+        let priority_fee = cap(priority_fee, fees.max_priority_fee_per_gas).min(max_fee);
+        Ok(GasFees::Eip1559 {
+            max_fee,
+            priority_fee,
+        })
+    }
+
+    // A transaction from `from` to `to`, with `data` as calldata for contract
+    // calls.
+    async fn build(
+        provider: &Provider<Http>,
+        from: Address,
+        to: Address,
+        value: U256,
+        data: Bytes,
+        fees: &FeeStrategy,
+        nonce: Option<u64>,
+    ) -> Result<TypedTransaction, ChainError> {
+        let gas_fees = Self::gas_fees(provider, fees).await?;
+        let nonce = match nonce {
+            Some(nonce) => U256::from(nonce),
+            None => {
+                provider
+                    .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+                    .await?
+            }
+        };
+        let chain_id = provider.get_chainid().await?.as_u64();
+
+        // Gas is estimated on the transaction type that is sent, before fees
+        // are set, so the estimate doesn't need the balance to cover them.
+        Ok(match gas_fees {
+            GasFees::Eip1559 {
+                max_fee,
+                priority_fee,
+            } => {
+                let request = Eip1559TransactionRequest::new()
+                    .from(from)
+                    .to(to)
+                    .value(value)
+                    .data(data)
+                    .nonce(nonce);
+                let gas = provider.estimate_gas(&request.clone().into(), None).await?;
+                request
+                    .gas(gas)
+                    .max_fee_per_gas(max_fee)
+                    .max_priority_fee_per_gas(priority_fee)
+                    .chain_id(chain_id)
+                    .into()
+            }
+            GasFees::Legacy { gas_price } => {
+                let request = TransactionRequest::new()
+                    .from(from)
+                    .to(to)
+                    .value(value)
+                    .data(data)
+                    .nonce(nonce);
+                let gas = provider.estimate_gas(&request.clone().into(), None).await?;
+                request
+                    .gas(gas)
+                    .gas_price(gas_price)
+                    .chain_id(chain_id)
+                    .into()
+            }
+        })
+    }
+
+    async fn build_transfer(
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<TypedTransaction, ChainError> {
+        Self::build(
+            &Self::provider(chain_data)?,
+            Self::parse_address(&transfer.from)?,
+            Self::parse_address(&transfer.to)?,
+            transfer.amount,
+            Bytes::new(),
+            &transfer.fees,
+            transfer.nonce,
+        )
+        .await
+    }
+
+    // A call of `contract` from `from`, ready for `sign_transaction`.
+    pub async fn build_call(
+        &self,
+        chain_data: &ChainInfo,
+        from: &str,
+        contract: Address,
+        data: Bytes,
+        fees: &FeeStrategy,
+        nonce: Option<u64>,
+    ) -> Result<UnsignedTransaction, ChainError> {
+        let transaction = Self::build(
+            &Self::provider(chain_data)?,
+            Self::parse_address(from)?,
+            contract,
+            U256::zero(),
+            data,
+            fees,
+            nonce,
+        )
+        .await?;
+        Self::encode(&transaction)
+    }
+
+    pub async fn transaction(
+        &self,
+        chain_data: &ChainInfo,
+        hash: &str,
+    ) -> Result<Option<Transaction>, ChainError> {
+        let hash = hash
+            .parse::<H256>()
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))?;
+        Ok(Self::provider(chain_data)?.get_transaction(hash).await?)
+    }
+
+    // A transaction with the nonce of `original` paying more than it, either
+    // the same call again or, for `cancel`, an empty transfer to the sender.
+    pub async fn build_replacement(
+        &self,
+        chain_data: &ChainInfo,
+        original: &Transaction,
+        cancel: bool,
+        fees: &FeeStrategy,
+    ) -> Result<UnsignedTransaction, ChainError> {
+        let provider = Self::provider(chain_data)?;
+        let (to, value, data, gas) = if cancel {
+            (
+                original.from,
+                U256::zero(),
+                Bytes::new(),
+                U256::from(TRANSFER_GAS),
+            )
+        } else {
+            let to = original
+                .to
+                .ok_or_else(|| ChainError::InvalidTransaction(String::from("Contract creation")))?;
+            (to, original.value, original.input.clone(), original.gas)
+        };
+        let (max_fee, priority_fee) = match Self::gas_fees(&provider, fees).await? {
+            GasFees::Eip1559 {
+                max_fee,
+                priority_fee,
+            } => (max_fee, priority_fee),
+            GasFees::Legacy { gas_price } => (gas_price, gas_price),
+        };
+        let chain_id = provider.get_chainid().await?.as_u64();
+
+        // The replacement keeps the type of the original, since nodes compare
+        // fee caps and tips against the same fields.
+        let transaction: TypedTransaction =
+            match (original.max_fee_per_gas, original.max_priority_fee_per_gas) {
+                (Some(original_max_fee), Some(original_priority_fee)) => {
+                    let priority_fee = priority_fee.max(bump(original_priority_fee));
                     Eip1559TransactionRequest::new()
-                        .from(from)
+                        .from(original.from)
                         .to(to)
-                        .value(payload.amount)
-                        .nonce(nonce),
-                ),
-                None,
-            )
-            .await
-        {
-            Ok(data) => data,
-            Err(e) => {
-                return Err(eyre::eyre!("Gas estimation failed: {}", e));
-            }
+                        .value(value)
+                        .data(data)
+                        .nonce(original.nonce)
+                        .gas(gas)
+                        .max_fee_per_gas(max_fee.max(bump(original_max_fee)).max(priority_fee))
+                        .max_priority_fee_per_gas(priority_fee)
+                        .chain_id(chain_id)
+                        .into()
+                }
+                _ => TransactionRequest::new()
+                    .from(original.from)
+                    .to(to)
+                    .value(value)
+                    .data(data)
+                    .nonce(original.nonce)
+                    .gas(gas)
+                    .gas_price(max_fee.max(bump(original.gas_price.unwrap_or_default())))
+                    .chain_id(chain_id)
+                    .into(),
+            };
+        Self::encode(&transaction)
+    }
+
+    fn encode(transaction: &TypedTransaction) -> Result<UnsignedTransaction, ChainError> {
+        let chain_id = transaction
+            .chain_id()
+            .ok_or_else(|| ChainError::InvalidTransaction(String::from("Missing chain id")))?
+            .as_u64();
+        serde_json::to_vec(&EncodedTransaction {
+            chain_id,
+            transaction: transaction.clone(),
+        })
+        .map(UnsignedTransaction)
+        .map_err(|e| ChainError::InvalidTransaction(e.to_string()))
+    }
+
+    fn decode(transaction: &UnsignedTransaction) -> Result<(TypedTransaction, u64), ChainError> {
+        let encoded: EncodedTransaction = serde_json::from_slice(&transaction.0)
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))?;
+        let mut transaction = encoded.transaction;
+        transaction.set_chain_id(encoded.chain_id);
+        Ok((transaction, encoded.chain_id))
+    }
+}
+
+#[async_trait]
+impl ChainFeatures for Ethereum {
+    fn key_scheme(&self) -> KeyScheme {
+        KeyScheme::Secp256k1
+    }
+
+    fn native_decimals(&self) -> u32 {
+        18
+    }
+
+    fn derive_address(&self, public_key: &ChainPublicKey) -> Result<(String, String), ChainError> {
+        match public_key {
+            ChainPublicKey::Secp256k1(public_key) => Ok(generate_chain_data(public_key)),
+            ChainPublicKey::Ed25519(_) => Err(ChainError::UnsupportedKey),
+        }
+    }
+
+    fn derivation_path(&self, account: u32, index: u32) -> Result<DerivationPath, ChainError> {
+        Ok(DerivationPath::new(
+            EVM_PURPOSE,
+            EVM_COIN_TYPE,
+            account,
+            index,
+        ))
+    }
+
+    async fn balance(&self, chain_data: &ChainInfo, address: &str) -> Result<String, ChainError> {
+        let balance = Self::provider(chain_data)?
+            .get_balance(Self::parse_address(address)?, None)
+            .await?;
+        Ok(balance.to_string())
+    }
+
+    async fn account_nonces(
+        &self,
+        chain_data: &ChainInfo,
+        address: &str,
+    ) -> Result<Option<AccountNonces>, ChainError> {
+        let provider = Self::provider(chain_data)?;
+        let address = Self::parse_address(address)?;
+        let confirmed = provider
+            .get_transaction_count(address, Some(BlockNumber::Latest.into()))
+            .await?;
+        let pending = provider
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await?;
+        Ok(Some(AccountNonces {
+            confirmed: confirmed.as_u64(),
+            pending: pending.as_u64(),
+        }))
+    }
+
+    async fn estimate_fee(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<String, ChainError> {
+        let transaction = Self::build_transfer(chain_data, transfer).await?;
+        // The most it can cost: the max fee for EIP-1559, the gas price otherwise.
+        let (Some(gas), Some(gas_price)) = (transaction.gas(), transaction.gas_price()) else {
+            return Err(ChainError::InvalidTransaction(String::from("Missing gas")));
         };
+        Ok((gas * gas_price).to_string())
+    }
 
-        let tx = Eip1559TransactionRequest::new()
-            .from(from)
-            .to(to)
-            .value(payload.amount)
-            .nonce(nonce)
-            .gas(gas)
-            .max_fee_per_gas(max_fee)
-            .max_priority_fee_per_gas(priority_fee);
+    async fn build_transaction(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<UnsignedTransaction, ChainError> {
+        Self::encode(&Self::build_transfer(chain_data, transfer).await?)
+    }
 
-        let wallet: LocalWallet = private_key.parse()?;
-        let client = SignerMiddleware::new(provider, wallet);
-        let pending_tx = client.send_transaction(tx, None).await?;
+    // Signs the transaction sighash with the share holders of the wallet key.
+    async fn sign_transaction(
+        &self,
+        transaction: UnsignedTransaction,
+        signer: &ChainSigner,
+    ) -> Result<SignedTransaction, ChainError> {
+        let (transaction, chain_id) = Self::decode(&transaction)?;
 
-        let receipt = pending_tx
+        let signer = signer.ecdsa()?.clone();
+        let sighash = transaction.sighash().to_fixed_bytes();
+        let signature = tokio::task::spawn_blocking(move || signer.sign_hash(sighash))
             .await
-            .unwrap()
-            .ok_or_else(|| eyre::format_err!("tx dropped from mempool"))?;
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))??;
+        let signature = Signature {
+            r: U256::from_big_endian(&signature.r),
+            s: U256::from_big_endian(&signature.s),
+            v: to_eip155_v(signature.recovery_id, chain_id),
+        };
+        Ok(SignedTransaction(
+            transaction.rlp_signed(&signature).to_vec(),
+        ))
+    }
 
-        let tx = client
-            .get_transaction(receipt.transaction_hash)
+    async fn broadcast(
+        &self,
+        chain_data: &ChainInfo,
+        transaction: &SignedTransaction,
+    ) -> Result<String, ChainError> {
+        let provider = Self::provider(chain_data)?;
+        let pending = provider
+            .send_raw_transaction(Bytes::from(transaction.0.clone()))
+            .await?;
+        Ok(format!("{:#x}", pending.tx_hash()))
+    }
+
+    async fn receipt(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<Option<TXChain>, ChainError> {
+        let hash = transaction_id
+            .parse::<H256>()
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))?;
+        let provider = Self::provider(chain_data)?;
+        let Some(receipt) = provider.get_transaction_receipt(hash).await? else {
+            return Ok(None);
+        };
+        let transaction = provider
+            .get_transaction(hash)
+            .await?
+            .ok_or_else(|| ChainError::Rpc(String::from("Transaction not found")))?;
+        Ok(Some(TXChain::EVM(Box::new(EVMResponse {
+            transaction,
+            recepient: receipt,
+        }))))
+    }
+
+    async fn is_known(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<bool, ChainError> {
+        Ok(self
+            .transaction(chain_data, transaction_id)
             .await?
-            .ok_or_else(|| eyre::eyre!("Transaction not found"))?;
-        Ok((tx, receipt))
+            .is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::features::FeeSpeed;
+    use axum::{Json, Router, routing::post};
+    use serde_json::{Value, json};
+    use std::sync::{Arc, Mutex};
+
+    const FROM: &str = "0x00000000000000000000000000000000000000aa";
+    const TO: &str = "0x00000000000000000000000000000000000000bb";
+    const GWEI: u64 = 1_000_000_000;
+
+    type Requests = Arc<Mutex<Vec<Value>>>;
+
+    // A local EVM JSON-RPC node with a 1 gwei base fee, or none for a chain
+    // without EIP-1559. Every request is recorded.
+    async fn mock_rpc(base_fee: Option<u64>) -> (String, Requests) {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                let gwei = |amount: u64| format!("{:#x}", amount * GWEI);
+                let result = match request["method"].as_str().unwrap_or_default() {
+                    "eth_getBlockByNumber" => match base_fee {
+                        Some(base_fee) => {
+                            json!({"number": "0x10", "baseFeePerGas": gwei(base_fee)})
+                        }
+                        None => json!({"number": "0x10"}),
+                    },
+                    "eth_feeHistory" => json!({
+                        "oldestBlock": "0xf",
+                        "baseFeePerGas": [gwei(1), gwei(2)],
+                        "gasUsedRatio": [0.5],
+                        "reward": [[gwei(1), gwei(2), gwei(3)]],
+                    }),
+                    "eth_gasPrice" => json!(gwei(10)),
+                    "eth_chainId" => json!("0x1"),
+                    "eth_estimateGas" => json!("0x5208"),
+                    _ => Value::Null,
+                };
+                recorded.lock().unwrap().push(request.clone());
+                Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, requests)
+    }
+
+    async fn build(base_fee: Option<u64>, fees: FeeStrategy) -> (TypedTransaction, Value) {
+        let (url, requests) = mock_rpc(base_fee).await;
+        let provider = Provider::<Http>::try_from(url.as_str()).unwrap();
+        let transaction = Ethereum::build(
+            &provider,
+            Ethereum::parse_address(FROM).unwrap(),
+            Ethereum::parse_address(TO).unwrap(),
+            U256::from(GWEI),
+            Bytes::new(),
+            &fees,
+            Some(7),
+        )
+        .await
+        .unwrap();
+        let estimate = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|request| request["method"] == "eth_estimateGas")
+            .map(|request| request["params"][0].clone())
+            .unwrap();
+        (transaction, estimate)
+    }
+
+    fn gwei(amount: u64) -> U256 {
+        U256::from(amount * GWEI)
+    }
+
+    #[tokio::test]
+    async fn eip1559_fees_follow_the_fee_history() {
+        let (transaction, estimate) = build(Some(1), FeeStrategy::new(FeeSpeed::Normal)).await;
+
+        // The next base fee is 2 gwei and the median reward 2 gwei.
+        let TypedTransaction::Eip1559(request) = &transaction else {
+            panic!("expected an EIP-1559 transaction, got {:?}", transaction);
+        };
+        assert_eq!(request.max_fee_per_gas, Some(gwei(6)));
+        assert_eq!(request.max_priority_fee_per_gas, Some(gwei(2)));
+        assert_eq!(request.gas, Some(U256::from(21_000)));
+        assert_eq!(request.nonce, Some(U256::from(7)));
+        assert_eq!(transaction.chain_id(), Some(1.into()));
+        assert_eq!(estimate["type"], json!("0x02"));
+        assert!(estimate.get("maxFeePerGas").is_none());
+    }
+
+    #[tokio::test]
+    async fn eip1559_fees_respect_the_caps() {
+        let fees = FeeStrategy {
+            speed: FeeSpeed::Fast,
+            max_fee_per_gas: Some(gwei(5)),
+            max_priority_fee_per_gas: Some(gwei(1)),
+        };
+        let (transaction, _) = build(Some(1), fees).await;
+
+        let TypedTransaction::Eip1559(request) = transaction else {
+            panic!("expected an EIP-1559 transaction");
+        };
+        assert_eq!(request.max_fee_per_gas, Some(gwei(5)));
+        assert_eq!(request.max_priority_fee_per_gas, Some(gwei(1)));
+    }
+
+    #[tokio::test]
+    async fn chains_without_a_base_fee_get_legacy_transactions() {
+        let (transaction, estimate) = build(None, FeeStrategy::new(FeeSpeed::Normal)).await;
+
+        // 110% of the node's 10 gwei gas price.
+        let TypedTransaction::Legacy(request) = &transaction else {
+            panic!("expected a legacy transaction, got {:?}", transaction);
+        };
+        assert_eq!(request.gas_price, Some(gwei(11)));
+        assert_eq!(request.gas, Some(U256::from(21_000)));
+        assert_eq!(transaction.chain_id(), Some(1.into()));
+        // Gas is estimated on a legacy request too.
+        assert_eq!(estimate["type"], json!("0x00"));
+        assert!(estimate.get("maxPriorityFeePerGas").is_none());
+    }
+
+    #[tokio::test]
+    async fn legacy_gas_price_respects_the_cap() {
+        let fees = FeeStrategy {
+            speed: FeeSpeed::Fast,
+            max_fee_per_gas: Some(gwei(12)),
+            max_priority_fee_per_gas: None,
+        };
+        let (transaction, _) = build(None, fees).await;
+
+        assert_eq!(transaction.gas_price(), Some(gwei(12)));
     }
 }
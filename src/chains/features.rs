@@ -1,15 +1,197 @@
 use async_trait::async_trait;
-use axum::response::Result;
+use curve25519_dalek::edwards::EdwardsPoint;
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::{
-    models::user_wallet_model::ChainInfo, routes::handler::{response_handler::ErrorResponse, transaction_handler::Transaction},
+    errors::chain_errors::ChainError,
+    models::user_wallet_model::ChainInfo,
+    services::{
+        chains_services::TXChain, derivation_services::DerivationPath,
+        eddsa_services::EddsaThresholdSigner, signing_services::ThresholdSigner,
+    },
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScheme {
+    Secp256k1,
+    Ed25519,
+}
+
+#[derive(Debug, Clone)]
+pub enum ChainPublicKey {
+    Secp256k1(secp256k1::PublicKey),
+    Ed25519(EdwardsPoint),
+}
+
+#[derive(Clone)]
+pub enum ChainSigner {
+    Ecdsa(ThresholdSigner),
+    Eddsa(EddsaThresholdSigner),
+}
+
+impl ChainSigner {
+    pub fn ecdsa(&self) -> Result<&ThresholdSigner, ChainError> {
+        match self {
+            Self::Ecdsa(signer) => Ok(signer),
+            Self::Eddsa(_) => Err(ChainError::UnsupportedKey),
+        }
+    }
+
+    pub fn eddsa(&self) -> Result<&EddsaThresholdSigner, ChainError> {
+        match self {
+            Self::Eddsa(signer) => Ok(signer),
+            Self::Ecdsa(_) => Err(ChainError::UnsupportedKey),
+        }
+    }
+}
+
+// Transactions travel between the steps below in each chain's own encoding, so
+// the trait stays object safe and handlers never look inside them.
+#[derive(Debug, Clone)]
+pub struct UnsignedTransaction(pub Vec<u8>);
+
+#[derive(Debug, Clone)]
+pub struct SignedTransaction(pub Vec<u8>);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+// How much to pay for inclusion. The caps are per unit of gas in the chain's
+// base unit and only apply to chains that price gas.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeeStrategy {
+    pub speed: FeeSpeed,
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+}
+
+impl FeeStrategy {
+    pub fn new(speed: FeeSpeed) -> Self {
+        FeeStrategy {
+            speed,
+            ..Default::default()
+        }
+    }
+}
+
+// A native transfer with `amount` already in the chain's base unit. `nonce` is
+// a reserved account nonce, chains look it up themselves when it is `None`.
+#[derive(Debug, Clone)]
+pub struct NativeTransfer {
+    pub from: String,
+    pub to: String,
+    pub amount: U256,
+    pub fees: FeeStrategy,
+    pub nonce: Option<u64>,
+}
+
+// Transaction counts of an account: `confirmed` in the latest block, `pending`
+// including the node's mempool.
+#[derive(Debug, Clone, Copy)]
+pub struct AccountNonces {
+    pub confirmed: u64,
+    pub pending: u64,
+}
+
 #[async_trait]
-pub trait ChainFeatures {
-    async fn send_native(
+pub trait ChainFeatures: Send + Sync {
+    fn key_scheme(&self) -> KeyScheme;
+
+    // Decimal places of the native currency, used when no chain config sets them.
+    fn native_decimals(&self) -> u32;
+
+    // Address and public key encoding for a wallet key on this chain.
+    fn derive_address(&self, public_key: &ChainPublicKey) -> Result<(String, String), ChainError>;
+
+    // Where address `index` of `account` sits below the wallet's root key. Each
+    // chain uses its own purpose and coin type, so the same account never maps
+    // to one key on two chains. Chains without child derivation keep the default.
+    fn derivation_path(&self, _account: u32, _index: u32) -> Result<DerivationPath, ChainError> {
+        Err(ChainError::UnsupportedOperation("Address derivation"))
+    }
+
+    // Balance in the chain's smallest unit.
+    async fn balance(&self, chain_data: &ChainInfo, address: &str) -> Result<String, ChainError>;
+
+    // Only chains ordering transactions by account nonce return counts.
+    async fn account_nonces(
+        &self,
+        _chain_data: &ChainInfo,
+        _address: &str,
+    ) -> Result<Option<AccountNonces>, ChainError> {
+        Ok(None)
+    }
+
+    // Expected fee of `transfer` in the chain's smallest unit.
+    async fn estimate_fee(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<String, ChainError>;
+
+    async fn build_transaction(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<UnsignedTransaction, ChainError>;
+
+    async fn sign_transaction(
+        &self,
+        transaction: UnsignedTransaction,
+        signer: &ChainSigner,
+    ) -> Result<SignedTransaction, ChainError>;
+
+    // Returns the id the transaction can be looked up by.
+    async fn broadcast(
+        &self,
+        chain_data: &ChainInfo,
+        transaction: &SignedTransaction,
+    ) -> Result<String, ChainError>;
+
+    // `None` until the chain reports the transaction as accepted.
+    async fn receipt(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<Option<TXChain>, ChainError>;
+
+    // Whether the node still has the transaction, mined or waiting to be.
+    async fn is_known(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<bool, ChainError>;
+
+    fn receipt_poll_interval(&self) -> Duration {
+        Duration::from_secs(3)
+    }
+
+    fn receipt_timeout(&self) -> Duration {
+        Duration::from_secs(180)
+    }
+
+    async fn wait_for_receipt(
+        &self,
         chain_data: &ChainInfo,
-        payload: &Transaction,
-        private_key: String,
-    ) -> Result<String, ErrorResponse>;
+        transaction_id: &str,
+    ) -> Result<TXChain, ChainError> {
+        let deadline = tokio::time::Instant::now() + self.receipt_timeout();
+        loop {
+            if let Some(receipt) = self.receipt(chain_data, transaction_id).await? {
+                return Ok(receipt);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(ChainError::ReceiptTimeout(transaction_id.to_string()));
+            }
+            tokio::time::sleep(self.receipt_poll_interval()).await;
+        }
+    }
 }
@@ -1,2 +1,7 @@
 pub mod ethereum;
-pub mod features;
\ No newline at end of file
+pub mod features;
+pub mod solana;
+pub mod bitcoin;
+pub mod registry;
+pub mod erc20;
+pub mod nft;
@@ -0,0 +1,252 @@
+use ethers::{
+    contract::abigen,
+    providers::{Http, Provider},
+    types::{Address, Bytes, U256},
+};
+use std::sync::Arc;
+
+use crate::{
+    chains::ethereum::Ethereum,
+    errors::chain_errors::ChainError,
+    models::user_wallet_model::{ChainInfo, NftStandard},
+};
+
+// ERC-165 interface ids.
+const ERC721_INTERFACE_ID: [u8; 4] = [0x80, 0xac, 0x58, 0xcd];
+const ERC1155_INTERFACE_ID: [u8; 4] = [0xd9, 0xb6, 0x7a, 0x26];
+
+abigen!(
+    IERC721,
+    r#"[
+        function supportsInterface(bytes4 interfaceId) external view returns (bool)
+        function ownerOf(uint256 tokenId) external view returns (address)
+        function tokenURI(uint256 tokenId) external view returns (string)
+        function safeTransferFrom(address from, address to, uint256 tokenId) external
+    ]"#
+);
+
+abigen!(
+    IERC1155,
+    r#"[
+        function balanceOf(address account, uint256 id) external view returns (uint256)
+        function uri(uint256 id) external view returns (string)
+        function safeTransferFrom(address from, address to, uint256 id, uint256 amount, bytes data) external
+        function safeBatchTransferFrom(address from, address to, uint256[] ids, uint256[] amounts, bytes data) external
+    ]"#
+);
+
+// Both bindings point at the same contract; which one applies depends on the
+// standard it reports through ERC-165.
+pub struct Nft {
+    erc721: IERC721<Provider<Http>>,
+    erc1155: IERC1155<Provider<Http>>,
+}
+
+impl Nft {
+    pub fn new(chain_data: &ChainInfo, contract: &str) -> Result<Self, ChainError> {
+        let provider = Arc::new(
+            Provider::<Http>::try_from(chain_data.rpc_url.as_str())
+                .map_err(|e| ChainError::Rpc(e.to_string()))?,
+        );
+        let contract = Ethereum::parse_address(contract)?;
+        Ok(Nft {
+            erc721: IERC721::new(contract, provider.clone()),
+            erc1155: IERC1155::new(contract, provider),
+        })
+    }
+
+    pub fn address(&self) -> Address {
+        self.erc721.address()
+    }
+
+    pub async fn standard(&self) -> Result<NftStandard, ChainError> {
+        if self.supports_interface(ERC721_INTERFACE_ID).await? {
+            return Ok(NftStandard::ERC721);
+        }
+        if self.supports_interface(ERC1155_INTERFACE_ID).await? {
+            return Ok(NftStandard::ERC1155);
+        }
+        Err(ChainError::UnsupportedOperation(
+            "Contracts without ERC-721 or ERC-1155",
+        ))
+    }
+
+    async fn supports_interface(&self, interface_id: [u8; 4]) -> Result<bool, ChainError> {
+        self.erc721
+            .supports_interface(interface_id)
+            .call()
+            .await
+            .map_err(|e| ChainError::Rpc(e.to_string()))
+    }
+
+    // ERC-1155 uris may contain an `{id}` placeholder, which clients substitute
+    // with the hex token id themselves.
+    pub async fn metadata_uri(
+        &self,
+        standard: NftStandard,
+        token_id: U256,
+    ) -> Result<String, ChainError> {
+        match standard {
+            NftStandard::ERC721 => self.erc721.token_uri(token_id).call().await,
+            NftStandard::ERC1155 => self.erc1155.uri(token_id).call().await,
+        }
+        .map_err(|e| ChainError::Rpc(e.to_string()))
+    }
+
+    // How many of `token_id` `owner` holds, at most 1 for ERC-721.
+    pub async fn balance_of(
+        &self,
+        standard: NftStandard,
+        owner: &str,
+        token_id: U256,
+    ) -> Result<U256, ChainError> {
+        let owner = Ethereum::parse_address(owner)?;
+        match standard {
+            // `ownerOf` reverts for burned tokens, which nobody holds any more.
+            NftStandard::ERC721 => match self.erc721.owner_of(token_id).call().await {
+                Ok(holder) => Ok(U256::from((holder == owner) as u8)),
+                Err(e) if e.is_revert() => Ok(U256::zero()),
+                Err(e) => Err(e),
+            },
+            NftStandard::ERC1155 => self.erc1155.balance_of(owner, token_id).call().await,
+        }
+        .map_err(|e| ChainError::Rpc(e.to_string()))
+    }
+
+    pub fn transfer_calldata(
+        &self,
+        standard: NftStandard,
+        from: &str,
+        to: &str,
+        token_id: U256,
+        amount: U256,
+    ) -> Result<Bytes, ChainError> {
+        let (from, to) = (Ethereum::parse_address(from)?, Ethereum::parse_address(to)?);
+        match standard {
+            NftStandard::ERC721 => {
+                if amount != U256::one() {
+                    return Err(ChainError::InvalidTransaction(String::from(
+                        "ERC-721 tokens are transferred one at a time",
+                    )));
+                }
+                self.erc721
+                    .safe_transfer_from(from, to, token_id)
+                    .calldata()
+            }
+            NftStandard::ERC1155 => self
+                .erc1155
+                .safe_transfer_from(from, to, token_id, amount, Bytes::new())
+                .calldata(),
+        }
+        .ok_or_else(|| ChainError::InvalidTransaction(String::from("Missing calldata")))
+    }
+
+    pub fn batch_transfer_calldata(
+        &self,
+        from: &str,
+        to: &str,
+        token_ids: Vec<U256>,
+        amounts: Vec<U256>,
+    ) -> Result<Bytes, ChainError> {
+        if token_ids.is_empty() || token_ids.len() != amounts.len() {
+            return Err(ChainError::InvalidTransaction(String::from(
+                "Batch needs one amount per token id",
+            )));
+        }
+        self.erc1155
+            .safe_batch_transfer_from(
+                Ethereum::parse_address(from)?,
+                Ethereum::parse_address(to)?,
+                token_ids,
+                amounts,
+                Bytes::new(),
+            )
+            .calldata()
+            .ok_or_else(|| ChainError::InvalidTransaction(String::from("Missing calldata")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user_wallet_model::ChainType;
+    use axum::{Json, Router, routing::post};
+    use serde_json::{Value, json};
+
+    const OWNER: &str = "0x00000000000000000000000000000000000000aa";
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000bb";
+
+    // A local EVM JSON-RPC endpoint answering every `eth_call` with `response`,
+    // either a result or an error object.
+    async fn mock_rpc(response: Result<Value, Value>) -> String {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                Json(match &response {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+                    Err(error) => json!({"jsonrpc": "2.0", "id": request["id"], "error": error}),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    fn chain_data(rpc_url: &str) -> ChainInfo {
+        ChainInfo {
+            index: 0,
+            account: 0,
+            public_key: String::new(),
+            address: OWNER.to_string(),
+            balance: String::from("0"),
+            rpc_url: rpc_url.to_string(),
+            chain_type: ChainType::EVM,
+            addresses: Vec::new(),
+            tokens: Vec::new(),
+            nfts: Vec::new(),
+        }
+    }
+
+    async fn erc721_balance(response: Result<Value, Value>) -> Result<U256, ChainError> {
+        let url = mock_rpc(response).await;
+        Nft::new(&chain_data(&url), CONTRACT)
+            .unwrap()
+            .balance_of(NftStandard::ERC721, OWNER, U256::one())
+            .await
+    }
+
+    #[tokio::test]
+    async fn erc721_balance_is_one_for_the_owner() {
+        let owner = format!("0x{:0>64}", &OWNER[2..]);
+        let other = format!("0x{:0>64}", "cc");
+
+        assert_eq!(erc721_balance(Ok(json!(owner))).await.unwrap(), U256::one());
+        assert_eq!(
+            erc721_balance(Ok(json!(other))).await.unwrap(),
+            U256::zero()
+        );
+    }
+
+    #[tokio::test]
+    async fn burned_erc721_tokens_have_no_balance() {
+        let reverted = json!({
+            "code": 3,
+            "message": "execution reverted: ERC721: invalid token ID",
+            "data": "0x",
+        });
+
+        assert_eq!(erc721_balance(Err(reverted)).await.unwrap(), U256::zero());
+    }
+
+    #[tokio::test]
+    async fn node_errors_are_still_reported() {
+        let error = json!({"code": -32005, "message": "Node is behind"});
+
+        assert!(matches!(
+            erc721_balance(Err(error)).await,
+            Err(ChainError::Rpc(message)) if message.contains("Node is behind")
+        ));
+    }
+}
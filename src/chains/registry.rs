@@ -0,0 +1,128 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    chains::{
+        bitcoin::{Bitcoin, BitcoinConfig},
+        ethereum::Ethereum,
+        features::ChainFeatures,
+        solana::Solana,
+    },
+    models::user_wallet_model::ChainType,
+};
+
+// A chain every new wallet starts with. `account` is the BIP32 account its
+// addresses are derived under, for chains signing with the secp256k1 key.
+#[derive(Debug, Clone)]
+pub struct DefaultChain {
+    pub chain_id: String,
+    pub chain_type: ChainType,
+    pub account: u32,
+    pub rpc_url: String,
+}
+
+// One ChainFeatures implementation per chain type. Handlers only go through
+// here, so supporting a new chain is a new implementation plus a `register`.
+#[derive(Clone, Default)]
+pub struct ChainRegistry {
+    chains: HashMap<ChainType, Arc<dyn ChainFeatures>>,
+    defaults: Vec<DefaultChain>,
+}
+
+impl ChainRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_env() -> Self {
+        let bitcoin_config = BitcoinConfig::from_env();
+        let mut registry = Self::new();
+        registry.register(ChainType::EVM, Arc::new(Ethereum));
+        registry.register(ChainType::SOLANA, Arc::new(Solana::default()));
+        registry.register(
+            ChainType::BITCOIN,
+            Arc::new(Bitcoin::new(bitcoin_config.network)),
+        );
+
+        registry.add_default(DefaultChain {
+            chain_id: String::from("1"),
+            chain_type: ChainType::EVM,
+            account: 0,
+            rpc_url: String::from("https://eth.llamarpc.com"),
+        });
+        // Bitcoin derives under its own purpose (84) and coin type, so account 1
+        // no longer keeps it apart from the EVM addresses. It stays because the
+        // addresses of existing wallets were derived under it.
+        registry.add_default(DefaultChain {
+            chain_id: String::from("bitcoin"),
+            chain_type: ChainType::BITCOIN,
+            account: 1,
+            rpc_url: bitcoin_config.esplora_url,
+        });
+        registry.add_default(DefaultChain {
+            chain_id: String::from("solana"),
+            chain_type: ChainType::SOLANA,
+            account: 0,
+            rpc_url: String::from("https://api.mainnet-beta.solana.com"),
+        });
+        registry
+    }
+
+    pub fn register(&mut self, chain_type: ChainType, chain: Arc<dyn ChainFeatures>) {
+        self.chains.insert(chain_type, chain);
+    }
+
+    pub fn add_default(&mut self, chain: DefaultChain) {
+        self.defaults.push(chain);
+    }
+
+    pub fn defaults(&self) -> &[DefaultChain] {
+        &self.defaults
+    }
+
+    pub fn get(&self, chain_type: ChainType) -> Option<Arc<dyn ChainFeatures>> {
+        self.chains.get(&chain_type).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains::features::KeyScheme;
+    use crate::services::derivation_services::{BITCOIN_PATH_PURPOSE, EVM_COIN_TYPE, EVM_PURPOSE};
+
+    #[test]
+    fn each_chain_type_resolves_to_its_implementation() {
+        let registry = ChainRegistry::from_env();
+
+        let evm = registry.get(ChainType::EVM).unwrap();
+        assert_eq!(evm.key_scheme(), KeyScheme::Secp256k1);
+        assert_eq!(evm.native_decimals(), 18);
+        let path = evm.derivation_path(0, 3).unwrap();
+        assert_eq!((path.purpose, path.coin_type), (EVM_PURPOSE, EVM_COIN_TYPE));
+
+        let bitcoin = registry.get(ChainType::BITCOIN).unwrap();
+        assert_eq!(bitcoin.key_scheme(), KeyScheme::Secp256k1);
+        assert_eq!(bitcoin.native_decimals(), 8);
+        assert_eq!(
+            bitcoin.derivation_path(1, 0).unwrap().purpose,
+            BITCOIN_PATH_PURPOSE
+        );
+
+        // Ed25519 keys have no unhardened derivation.
+        let solana = registry.get(ChainType::SOLANA).unwrap();
+        assert_eq!(solana.key_scheme(), KeyScheme::Ed25519);
+        assert_eq!(solana.native_decimals(), 9);
+        assert!(solana.derivation_path(0, 0).is_err());
+    }
+
+    #[test]
+    fn defaults_only_use_registered_chains() {
+        let registry = ChainRegistry::from_env();
+
+        assert_eq!(registry.defaults().len(), 3);
+        for chain in registry.defaults() {
+            assert!(registry.get(chain.chain_type).is_some(), "{:?}", chain);
+        }
+        assert!(ChainRegistry::new().get(ChainType::EVM).is_none());
+    }
+}
@@ -0,0 +1,541 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+use crate::errors::{amount_errors::AmountError, chain_errors::ChainError};
+use crate::{
+    chains::features::{
+        ChainFeatures, ChainPublicKey, ChainSigner, KeyScheme, NativeTransfer, SignedTransaction,
+        UnsignedTransaction,
+    },
+    models::user_wallet_model::ChainInfo,
+    services::{chains_services::TXChain, eddsa_services::EddsaKeyServices},
+};
+
+// The system program id is 32 zero bytes, base58 "11111111111111111111111111111111".
+const SYSTEM_PROGRAM_ID: [u8; 32] = [0u8; 32];
+const SYSTEM_TRANSFER_INSTRUCTION: u32 = 2;
+// Every signature pays a flat base fee; transfers carry no priority fee.
+const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+#[derive(Debug, Default)]
+pub struct Solana {
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SolanaReceipt {
+    pub signature: String,
+    pub slot: u64,
+    pub confirmation_status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcContext<T> {
+    value: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LatestBlockhash {
+    blockhash: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureStatus {
+    slot: u64,
+    err: Option<Value>,
+    confirmation_status: Option<String>,
+}
+
+#[async_trait]
+impl ChainFeatures for Solana {
+    fn key_scheme(&self) -> KeyScheme {
+        KeyScheme::Ed25519
+    }
+
+    fn native_decimals(&self) -> u32 {
+        9
+    }
+
+    fn derive_address(&self, public_key: &ChainPublicKey) -> Result<(String, String), ChainError> {
+        match public_key {
+            ChainPublicKey::Ed25519(public_key) => Ok((
+                EddsaKeyServices::solana_address(public_key),
+                hex::encode(public_key.compress().as_bytes()),
+            )),
+            ChainPublicKey::Secp256k1(_) => Err(ChainError::UnsupportedKey),
+        }
+    }
+
+    async fn balance(&self, chain_data: &ChainInfo, address: &str) -> Result<String, ChainError> {
+        decode_pubkey(address)?;
+        let balance: RpcContext<u64> = rpc_call(
+            &self.client,
+            &chain_data.rpc_url,
+            "getBalance",
+            json!([address, {"commitment": "confirmed"}]),
+        )
+        .await?;
+        Ok(balance.value.to_string())
+    }
+
+    async fn estimate_fee(
+        &self,
+        _chain_data: &ChainInfo,
+        _transfer: &NativeTransfer,
+    ) -> Result<String, ChainError> {
+        Ok(LAMPORTS_PER_SIGNATURE.to_string())
+    }
+
+    // A single system transfer of `transfer.amount` lamports.
+    async fn build_transaction(
+        &self,
+        chain_data: &ChainInfo,
+        transfer: &NativeTransfer,
+    ) -> Result<UnsignedTransaction, ChainError> {
+        let lamports = u64::try_from(transfer.amount).map_err(|_| AmountError::Overflow)?;
+        let from = decode_pubkey(&transfer.from)?;
+        let to = decode_pubkey(&transfer.to)?;
+        if from == to {
+            return Err(ChainError::InvalidTransaction(String::from(
+                "Sender and recipient are the same account",
+            )));
+        }
+
+        let latest: RpcContext<LatestBlockhash> = rpc_call(
+            &self.client,
+            &chain_data.rpc_url,
+            "getLatestBlockhash",
+            json!([{"commitment": "finalized"}]),
+        )
+        .await?;
+        let blockhash = decode_pubkey(&latest.value.blockhash)?;
+        Ok(UnsignedTransaction(transfer_message(
+            &from, &to, lamports, &blockhash,
+        )))
+    }
+
+    async fn sign_transaction(
+        &self,
+        transaction: UnsignedTransaction,
+        signer: &ChainSigner,
+    ) -> Result<SignedTransaction, ChainError> {
+        let signer = signer.eddsa()?.clone();
+        // The fee payer is the first account key, right after the 4 byte header.
+        if transaction.0.get(4..36) != Some(signer.public_key().compress().as_bytes()) {
+            return Err(ChainError::WrongSigner);
+        }
+
+        let message = transaction.0;
+        let signing_message = message.clone();
+        let signature = tokio::task::spawn_blocking(move || signer.sign(&signing_message))
+            .await
+            .map_err(|e| ChainError::InvalidTransaction(e.to_string()))??;
+
+        let mut signed = Vec::with_capacity(1 + 64 + message.len());
+        encode_length(&mut signed, 1);
+        signed.extend_from_slice(&signature);
+        signed.extend_from_slice(&message);
+        Ok(SignedTransaction(signed))
+    }
+
+    async fn broadcast(
+        &self,
+        chain_data: &ChainInfo,
+        transaction: &SignedTransaction,
+    ) -> Result<String, ChainError> {
+        rpc_call(
+            &self.client,
+            &chain_data.rpc_url,
+            "sendTransaction",
+            json!([
+                bs58::encode(&transaction.0).into_string(),
+                {"encoding": "base58", "preflightCommitment": "confirmed"}
+            ]),
+        )
+        .await
+    }
+
+    async fn receipt(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<Option<TXChain>, ChainError> {
+        let statuses: RpcContext<Vec<Option<SignatureStatus>>> = rpc_call(
+            &self.client,
+            &chain_data.rpc_url,
+            "getSignatureStatuses",
+            json!([[transaction_id], {"searchTransactionHistory": false}]),
+        )
+        .await?;
+
+        let Some(Some(status)) = statuses.value.into_iter().next() else {
+            return Ok(None);
+        };
+        if let Some(err) = status.err {
+            return Err(ChainError::TransactionFailed(
+                transaction_id.to_string(),
+                err.to_string(),
+            ));
+        }
+        Ok(status
+            .confirmation_status
+            .filter(|status| status == "confirmed" || status == "finalized")
+            .map(|confirmation_status| {
+                TXChain::SOLANA(SolanaReceipt {
+                    signature: transaction_id.to_string(),
+                    slot: status.slot,
+                    confirmation_status,
+                })
+            }))
+    }
+
+    // Statuses are only kept for recent slots, so a transaction that expired
+    // with its blockhash is no longer known.
+    async fn is_known(
+        &self,
+        chain_data: &ChainInfo,
+        transaction_id: &str,
+    ) -> Result<bool, ChainError> {
+        let statuses: RpcContext<Vec<Option<SignatureStatus>>> = rpc_call(
+            &self.client,
+            &chain_data.rpc_url,
+            "getSignatureStatuses",
+            json!([[transaction_id], {"searchTransactionHistory": true}]),
+        )
+        .await?;
+        Ok(matches!(statuses.value.first(), Some(Some(_))))
+    }
+
+    fn receipt_poll_interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    // A finalized blockhash stays valid for about 150 blocks, roughly a minute.
+    fn receipt_timeout(&self) -> Duration {
+        Duration::from_secs(90)
+    }
+}
+
+async fn rpc_call<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+    params: Value,
+) -> Result<T, ChainError> {
+    let response: RpcResponse<T> = client
+        .post(rpc_url)
+        .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(ChainError::Rpc(format!("{} failed: {}", method, error))),
+        (Some(result), None) => Ok(result),
+        (None, None) => Err(ChainError::Rpc(format!("{} returned no result", method))),
+    }
+}
+
+fn decode_pubkey(value: &str) -> Result<[u8; 32], ChainError> {
+    bs58::decode(value)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ChainError::InvalidAddress(value.to_string()))
+}
+
+// Legacy message with one signer (the sender), a writable recipient and the
+// read-only system program.
+fn transfer_message(
+    from: &[u8; 32],
+    to: &[u8; 32],
+    lamports: u64,
+    blockhash: &[u8; 32],
+) -> Vec<u8> {
+    let mut message = vec![1, 0, 1];
+    encode_length(&mut message, 3);
+    message.extend_from_slice(from);
+    message.extend_from_slice(to);
+    message.extend_from_slice(&SYSTEM_PROGRAM_ID);
+    message.extend_from_slice(blockhash);
+
+    let mut data = SYSTEM_TRANSFER_INSTRUCTION.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    encode_length(&mut message, 1);
+    message.push(2);
+    encode_length(&mut message, 2);
+    message.extend_from_slice(&[0, 1]);
+    encode_length(&mut message, data.len());
+    message.extend_from_slice(&data);
+    message
+}
+
+// Solana's compact-u16 length prefix: 7 bits per byte, high bit set on all but the last.
+fn encode_length(buffer: &mut Vec<u8>, mut length: usize) {
+    loop {
+        let mut byte = (length & 0x7f) as u8;
+        length >>= 7;
+        if length == 0 {
+            buffer.push(byte);
+            return;
+        }
+        byte |= 0x80;
+        buffer.push(byte);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user_wallet_model::ChainType;
+    use crate::services::eddsa_services::Ed25519Group;
+    use crate::services::{
+        dkg_services, eddsa_services::EddsaThresholdSigner, key_services::SharePolicy,
+    };
+    use axum::{Json, Router, routing::post};
+    use ed25519_dalek::{Signature, VerifyingKey};
+    use ethers::types::U256;
+    use std::sync::{Arc, Mutex};
+
+    type Requests = Arc<Mutex<Vec<Value>>>;
+
+    // A local Solana JSON-RPC endpoint. `respond` maps a method and its params to
+    // a result or an error object; every request is recorded.
+    async fn mock_rpc<F>(respond: F) -> (String, Requests)
+    where
+        F: Fn(&str, &Value) -> Result<Value, Value> + Clone + Send + Sync + 'static,
+    {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                let method = request["method"].as_str().unwrap_or_default();
+                let body = match respond(method, &request["params"]) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+                    Err(error) => json!({"jsonrpc": "2.0", "id": request["id"], "error": error}),
+                };
+                recorded.lock().unwrap().push(request);
+                Json(body)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, requests)
+    }
+
+    fn chain_data(rpc_url: &str) -> ChainInfo {
+        ChainInfo {
+            index: 0,
+            account: 0,
+            public_key: String::new(),
+            address: String::new(),
+            balance: String::from("0"),
+            rpc_url: rpc_url.to_string(),
+            chain_type: ChainType::SOLANA,
+            addresses: Vec::new(),
+            tokens: Vec::new(),
+            nfts: Vec::new(),
+        }
+    }
+
+    fn context(value: Value) -> Value {
+        json!({"context": {"slot": 42}, "value": value})
+    }
+
+    // A 2-of-3 Ed25519 wallet key and its Solana address.
+    fn wallet() -> (ChainSigner, String) {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let joint_key = dkg_services::run_in_process::<Ed25519Group>(policy).unwrap();
+        let (address, public_key) = Solana::default()
+            .derive_address(&ChainPublicKey::Ed25519(joint_key.public_key))
+            .unwrap();
+        let signer =
+            EddsaThresholdSigner::new(joint_key.shares, policy.threshold, &public_key).unwrap();
+        (ChainSigner::Eddsa(signer), address)
+    }
+
+    fn transfer(from: &str, to: &str, lamports: u64) -> NativeTransfer {
+        NativeTransfer {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount: U256::from(lamports),
+            fees: Default::default(),
+            nonce: None,
+        }
+    }
+
+    const BLOCKHASH: [u8; 32] = [7u8; 32];
+    const RECIPIENT: [u8; 32] = [9u8; 32];
+
+    #[tokio::test]
+    async fn balance_reads_confirmed_lamports() {
+        let (url, requests) = mock_rpc(|method, _| match method {
+            "getBalance" => Ok(context(json!(1_500_000_000u64))),
+            _ => Err(json!({"code": -32601, "message": "Method not found"})),
+        })
+        .await;
+        let address = bs58::encode(RECIPIENT).into_string();
+
+        let balance = Solana::default()
+            .balance(&chain_data(&url), &address)
+            .await
+            .unwrap();
+
+        assert_eq!(balance, "1500000000");
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0]["params"][0], json!(address));
+        assert_eq!(requests[0]["params"][1]["commitment"], json!("confirmed"));
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_are_reported() {
+        let (url, _) =
+            mock_rpc(|_, _| Err(json!({"code": -32005, "message": "Node is behind"}))).await;
+
+        let result = Solana::default()
+            .balance(&chain_data(&url), &bs58::encode(RECIPIENT).into_string())
+            .await;
+
+        assert!(
+            matches!(result, Err(ChainError::Rpc(message)) if message.contains("Node is behind"))
+        );
+    }
+
+    #[tokio::test]
+    async fn transfer_is_built_signed_and_broadcast() {
+        let (signer, from) = wallet();
+        let to = bs58::encode(RECIPIENT).into_string();
+        let (url, requests) = mock_rpc(|method, params| match method {
+            "getLatestBlockhash" => Ok(context(json!({
+                "blockhash": bs58::encode(BLOCKHASH).into_string(),
+                "lastValidBlockHeight": 100,
+            }))),
+            "sendTransaction" => {
+                let transaction = bs58::decode(params[0].as_str().unwrap())
+                    .into_vec()
+                    .unwrap();
+                Ok(json!(bs58::encode(&transaction[1..65]).into_string()))
+            }
+            _ => Err(json!({"code": -32601, "message": "Method not found"})),
+        })
+        .await;
+        let chain = Solana::default();
+        let chain_data = chain_data(&url);
+
+        let unsigned = chain
+            .build_transaction(&chain_data, &transfer(&from, &to, 12_345))
+            .await
+            .unwrap();
+        let message = unsigned.0.clone();
+        assert_eq!(&message[4..36], decode_pubkey(&from).unwrap().as_slice());
+        assert_eq!(&message[36..68], RECIPIENT.as_slice());
+        assert_eq!(&message[100..132], BLOCKHASH.as_slice());
+        assert_eq!(
+            &message[message.len() - 8..],
+            12_345u64.to_le_bytes().as_slice()
+        );
+
+        let signed = chain.sign_transaction(unsigned, &signer).await.unwrap();
+        assert_eq!(signed.0[0], 1);
+        assert_eq!(&signed.0[65..], message.as_slice());
+        let signature = Signature::from_bytes(signed.0[1..65].try_into().unwrap());
+        VerifyingKey::from_bytes(&decode_pubkey(&from).unwrap())
+            .unwrap()
+            .verify_strict(&message, &signature)
+            .unwrap();
+
+        let transaction_id = chain.broadcast(&chain_data, &signed).await.unwrap();
+        assert_eq!(
+            transaction_id,
+            bs58::encode(signature.to_bytes()).into_string()
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1]["method"], json!("sendTransaction"));
+        assert_eq!(requests[1]["params"][1]["encoding"], json!("base58"));
+    }
+
+    #[tokio::test]
+    async fn transaction_for_another_account_is_not_signed() {
+        let (signer, _) = wallet();
+        let (url, _) = mock_rpc(|_, _| {
+            Ok(context(
+                json!({"blockhash": bs58::encode(BLOCKHASH).into_string()}),
+            ))
+        })
+        .await;
+        let chain = Solana::default();
+        let other = bs58::encode([3u8; 32]).into_string();
+        let to = bs58::encode(RECIPIENT).into_string();
+
+        let unsigned = chain
+            .build_transaction(&chain_data(&url), &transfer(&other, &to, 1))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            chain.sign_transaction(unsigned, &signer).await,
+            Err(ChainError::WrongSigner)
+        ));
+    }
+
+    #[tokio::test]
+    async fn receipt_follows_the_signature_status() {
+        let (url, _) = mock_rpc(|_, params| {
+            let status = match params[0][0].as_str().unwrap() {
+                "processed" => json!({"slot": 10, "err": null, "confirmationStatus": "processed"}),
+                "confirmed" => json!({"slot": 11, "err": null, "confirmationStatus": "confirmed"}),
+                "failed" => json!({
+                    "slot": 12,
+                    "err": {"InstructionError": [0, "Custom"]},
+                    "confirmationStatus": "confirmed",
+                }),
+                _ => Value::Null,
+            };
+            Ok(context(json!([status])))
+        })
+        .await;
+        let chain = Solana::default();
+        let chain_data = chain_data(&url);
+
+        assert!(
+            chain
+                .receipt(&chain_data, "unknown")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            chain
+                .receipt(&chain_data, "processed")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        match chain.receipt(&chain_data, "confirmed").await.unwrap() {
+            Some(TXChain::SOLANA(receipt)) => {
+                assert_eq!(receipt.signature, "confirmed");
+                assert_eq!(receipt.slot, 11);
+            }
+            other => panic!("unexpected receipt {:?}", other),
+        }
+        assert!(matches!(
+            chain.receipt(&chain_data, "failed").await,
+            Err(ChainError::TransactionFailed(id, _)) if id == "failed"
+        ));
+        assert!(!chain.is_known(&chain_data, "unknown").await.unwrap());
+        assert!(chain.is_known(&chain_data, "processed").await.unwrap());
+    }
+}
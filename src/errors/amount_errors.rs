@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AmountError {
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+
+    #[error("Amount has more than {0} decimal places")]
+    TooManyDecimals(u32),
+
+    #[error("Amount does not fit in 256 bits")]
+    Overflow,
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("Invalid, expired or revoked API key")]
+    InvalidKey,
+
+    #[error("Failed to generate random bytes")]
+    RandomnessError,
+
+    #[error("API key store error: {0}")]
+    Database(String),
+}
@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+use crate::errors::signing_errors::SigningError;
+
+#[derive(Debug, Error)]
+pub enum BitcoinError {
+    #[error("Invalid bitcoin address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Amount of {0} sats is below the dust limit")]
+    AmountBelowDust(u64),
+
+    #[error("Insufficient funds: need {0} sats, have {1} sats")]
+    InsufficientFunds(u64, u64),
+
+    #[error("Invalid PSBT: {0}")]
+    InvalidPsbt(String),
+
+    #[error("Input {0} is not spendable by the wallet key")]
+    ForeignInput(usize),
+
+    #[error("Bitcoin backend error: {0}")]
+    BackendError(String),
+
+    #[error(transparent)]
+    Signing(#[from] SigningError),
+}
@@ -0,0 +1,51 @@
+use thiserror::Error;
+
+use crate::errors::{
+    amount_errors::AmountError, bitcoin_errors::BitcoinError, nonce_errors::NonceError,
+    signing_errors::SigningError,
+};
+
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("Key type is not supported on this chain")]
+    UnsupportedKey,
+
+    #[error("{0} is not supported on this chain")]
+    UnsupportedOperation(&'static str),
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+
+    #[error("Signer does not match the sending account")]
+    WrongSigner,
+
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    #[error("Transaction {0} failed: {1}")]
+    TransactionFailed(String, String),
+
+    #[error("Timed out waiting for transaction {0}")]
+    ReceiptTimeout(String),
+
+    #[error(transparent)]
+    Provider(#[from] ethers::providers::ProviderError),
+
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Signing(#[from] SigningError),
+
+    #[error(transparent)]
+    Bitcoin(#[from] BitcoinError),
+
+    #[error(transparent)]
+    Amount(#[from] AmountError),
+
+    #[error(transparent)]
+    Nonce(#[from] NonceError),
+}
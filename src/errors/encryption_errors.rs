@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("Failed to generate randomness")]
+    RandomnessError,
+
+    #[error("Master key version {0} not found")]
+    UnknownKeyVersion(u32),
+
+    #[error("Master key store error: {0}")]
+    KeyStoreError(String),
+
+    #[error("Failed to encrypt key material")]
+    EncryptionFailed,
+
+    #[error("Failed to decrypt key material")]
+    DecryptionFailed,
+}
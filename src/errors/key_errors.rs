@@ -0,0 +1,58 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeyShareError {
+    #[error("Invalid share policy: threshold {0} of {1}")]
+    InvalidPolicy(u32, u32),
+
+    #[error("Not enough shares: need {0}, got {1}")]
+    NotEnoughShares(u32, usize),
+
+    #[error("Duplicate share index {0}")]
+    DuplicateIndex(u32),
+
+    #[error("Malformed key share")]
+    MalformedShare,
+
+    #[error("Failed to generate random scalar")]
+    RandomScalarError,
+
+    #[error("Invalid curve point")]
+    InvalidPoint,
+
+    #[error("Shares do not match the wallet public key")]
+    PublicKeyMismatch,
+
+    #[error("Cannot derive child key at index {0}")]
+    InvalidChildIndex(u32),
+
+    #[error("Malformed chain code")]
+    MalformedChainCode,
+
+    #[error("Invalid derivation path {0}")]
+    InvalidDerivationPath(String),
+}
+
+#[derive(Debug, Error)]
+pub enum DkgError {
+    #[error("Unexpected {0} message in the current session state")]
+    UnexpectedMessage(&'static str),
+
+    #[error("Missing {0} message from party {1}")]
+    MissingMessage(&'static str, u32),
+
+    #[error("Duplicate {0} message from party {1}")]
+    DuplicateMessage(&'static str, u32),
+
+    #[error("Unknown party {0}")]
+    UnknownParty(u32),
+
+    #[error("Share from party {0} does not match its commitments")]
+    InvalidShare(u32),
+
+    #[error("Parties derived different joint public keys")]
+    PublicKeyMismatch,
+
+    #[error(transparent)]
+    KeyShare(#[from] KeyShareError),
+}
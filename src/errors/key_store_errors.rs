@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+use crate::errors::encryption_errors::EncryptionError;
+
+#[derive(Debug, Error)]
+pub enum KeyStoreError {
+    #[error("Unknown key store backend: {0}")]
+    UnknownBackend(String),
+
+    #[error("Key store backend error: {0}")]
+    BackendError(String),
+
+    #[error("Only {0} of the required {1} key shares are available")]
+    SharesUnavailable(usize, usize),
+
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+}
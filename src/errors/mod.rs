@@ -1 +1,12 @@
-pub mod auth_errors;
\ No newline at end of file
+pub mod auth_errors;
+pub mod key_errors;
+pub mod signing_errors;
+pub mod encryption_errors;
+pub mod key_store_errors;
+pub mod bitcoin_errors;
+pub mod chain_errors;
+pub mod amount_errors;
+pub mod nonce_errors;
+pub mod session_errors;
+pub mod two_factor_errors;
+pub mod api_key_errors;
@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NonceError {
+    #[error("Nonce store error: {0}")]
+    Database(String),
+
+    #[error("Too many concurrent nonce updates for {0}")]
+    Contention(String),
+}
@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("Refresh token was already used")]
+    TokenReused,
+
+    #[error("JWT_SECRET must be at least {0} bytes")]
+    WeakSecret(usize),
+
+    #[error("Failed to generate random bytes")]
+    RandomnessError,
+
+    #[error("Session store error: {0}")]
+    Database(String),
+
+    #[error(transparent)]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+}
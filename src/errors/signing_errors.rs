@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+use crate::errors::key_errors::KeyShareError;
+
+#[derive(Debug, Error)]
+pub enum SigningError {
+    #[error("Failed to generate randomness")]
+    RandomnessError,
+
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+
+    #[error("Signing session aborted: {0}")]
+    SessionAborted(String),
+
+    #[error("Combined signature does not verify against the wallet public key")]
+    InvalidSignature,
+
+    #[error(transparent)]
+    KeyShare(#[from] KeyShareError),
+}
@@ -0,0 +1,43 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TwoFactorError {
+    #[error("Invalid TOTP secret")]
+    InvalidSecret,
+
+    #[error("Failed to generate random bytes")]
+    RandomnessError,
+
+    #[error("Malformed WebAuthn response: {0}")]
+    MalformedResponse(&'static str),
+
+    #[error("WebAuthn challenge is unknown, used or expired")]
+    InvalidChallenge,
+
+    #[error("WebAuthn response was made for {0}")]
+    WrongOrigin(String),
+
+    #[error("WebAuthn response is for another relying party")]
+    WrongRelyingParty,
+
+    #[error("User presence was not confirmed on the authenticator")]
+    UserNotPresent,
+
+    #[error("Only ES256 credentials are supported")]
+    UnsupportedAlgorithm,
+
+    #[error("Invalid WebAuthn signature")]
+    InvalidSignature,
+
+    #[error("Signature counter went from {0} to {1}, the authenticator may be cloned")]
+    CounterRegression(u32, u32),
+
+    #[error("WebAuthn config error: {0}")]
+    Config(String),
+
+    #[error("Challenge store error: {0}")]
+    Database(String),
+
+    #[error(transparent)]
+    Hash(#[from] bcrypt::BcryptError),
+}
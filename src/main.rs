@@ -12,6 +12,7 @@ use mongodb::{
 use services::database::Database;
 use std::{env, sync::Arc};
 use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
 
 mod chains;
 mod errors;
@@ -22,12 +23,23 @@ mod services;
 #[tokio::main]
 async fn main() {
     dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
     let db = Arc::new(Database::init().await);
+    tokio::spawn(db.clone().track_submissions());
 
     let app = Router::new()
         .nest("/api/v1", routes::chain::chain_routes())
         .nest("/api/v1", routes::auth::auth_routes())
         .nest("/api/v1", routes::transaction::transaction_routes())
+        .nest("/api/v1", routes::token::token_routes())
+        .nest("/api/v1", routes::nft::nft_routes())
+        .nest("/api/v1", routes::two_factor::two_factor_routes())
+        .nest("/api/v1", routes::api_key::api_key_routes())
+        .nest("/api/v1", routes::organization::organization_routes())
         .layer(Extension(db.clone()));
 
     let listener = TcpListener::bind("127.0.0.1:9000").await.unwrap();
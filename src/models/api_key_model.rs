@@ -0,0 +1,45 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_wallet_model::AccountRole;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    ReadBalances,
+    Transfer,
+    AdminChainConfig,
+}
+
+impl ApiKeyScope {
+    // Scopes that act on the whole service, only open to operators.
+    pub fn requires_operator(&self) -> bool {
+        matches!(self, ApiKeyScope::AdminChainConfig)
+    }
+
+    pub fn allowed_for(&self, role: AccountRole) -> bool {
+        !self.requires_operator() || role == AccountRole::Operator
+    }
+}
+
+// An API key of a user, stored as a SHA-256 hash of the key. `prefix` is the
+// start of the key, kept so the user can tell their keys apart. Keys issued on
+// an organization carry its id, belong to all of its members and act with the
+// organization's role. They still act on the wallet of the member who issued them.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKeyDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub email: String,
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: DateTime,
+    pub expires_at: Option<DateTime>,
+    pub last_used_at: Option<DateTime>,
+    pub revoked_at: Option<DateTime>,
+}
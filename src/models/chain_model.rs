@@ -14,6 +14,9 @@ pub struct WalletChainDataSchema {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ChainData {
     pub symbol: String,
+    // Decimal places of the native currency, e.g. 18 for ETH.
+    #[serde(default)]
+    pub decimals: Option<u32>,
     pub network: String,
     pub market_cap: String,
     pub total_supply: String,
@@ -28,6 +31,7 @@ pub struct PerfomanceData {
     pub trades: String,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ChainType {
     EVM,
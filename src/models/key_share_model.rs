@@ -0,0 +1,13 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_wallet_model::EncryptedKeyShare;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KeyShareDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub owner: String,
+    pub epoch: u32,
+    pub share: EncryptedKeyShare,
+}
@@ -1,2 +1,10 @@
 pub mod user_wallet_model;
-pub mod chain_model;
\ No newline at end of file
+pub mod chain_model;
+pub mod key_share_model;
+pub mod nonce_model;
+pub mod transaction_model;
+pub mod submission_model;
+pub mod session_model;
+pub mod webauthn_model;
+pub mod api_key_model;
+pub mod organization_model;
@@ -0,0 +1,23 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+// Nonce state of one sending account. `version` is bumped on every write so
+// concurrent reservations can't both succeed from the same read.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NonceDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub chain_id: String,
+    pub address: String,
+    pub version: i64,
+    pub next_nonce: i64,
+    pub pending: Vec<PendingNonce>,
+}
+
+// A reserved nonce that isn't mined yet. `hash` is set once it is broadcast.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PendingNonce {
+    pub nonce: i64,
+    pub hash: Option<String>,
+    pub reserved_at: DateTime,
+}
@@ -0,0 +1,36 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_wallet_model::AccountRole;
+
+// A group of users that shares API keys. `members` are user ids, and `admins`
+// the members who manage the membership, starting with whoever created it. Like
+// a user's, the role is only ever set on the document in the database, no route
+// grants it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OrganizationDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub admins: Vec<String>,
+    #[serde(default)]
+    pub role: AccountRole,
+    pub created_at: DateTime,
+}
+
+impl OrganizationDocument {
+    pub fn is_member(&self, user_id: &str) -> bool {
+        self.members.iter().any(|member| member == user_id)
+    }
+
+    // Organizations created before admins were kept are managed by their
+    // creator, the first member.
+    pub fn is_admin(&self, user_id: &str) -> bool {
+        if self.admins.is_empty() {
+            return self.members.first().is_some_and(|member| member == user_id);
+        }
+        self.is_member(user_id) && self.admins.iter().any(|admin| admin == user_id)
+    }
+}
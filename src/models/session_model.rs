@@ -0,0 +1,16 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+// A refresh token, stored as a SHA-256 hash of the token itself. Every refresh
+// consumes the token and issues the next one in the same `family`, so a token
+// presented twice means it leaked and the whole family is revoked.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RefreshTokenDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub token_hash: String,
+    pub user_id: String,
+    pub family: String,
+    pub used: bool,
+    pub expires_at: DateTime,
+}
@@ -0,0 +1,40 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::models::user_wallet_model::NonFungibleTokenData;
+use crate::services::chains_services::TXChain;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmissionStatus {
+    Queued,
+    Broadcast,
+    Confirmed,
+    Failed,
+    Dropped,
+}
+
+// A transfer accepted by the API. It is signed and broadcast in the background
+// and the submission worker follows it until it is mined or given up on.
+// `amount` is in the chain's base unit.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubmissionDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub email: String,
+    pub chain_id: String,
+    pub from: String,
+    pub to: String,
+    pub amount: String,
+    pub status: SubmissionStatus,
+    pub hash: Option<String>,
+    pub nonce: Option<i64>,
+    pub receipt: Option<TXChain>,
+    pub error: Option<String>,
+    pub created_at: DateTime,
+    pub broadcast_at: Option<DateTime>,
+    // An ERC-721 token the transfer sends away. It stays tracked until the
+    // transfer is confirmed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub untracks: Option<NonFungibleTokenData>,
+}
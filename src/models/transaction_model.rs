@@ -0,0 +1,33 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TransactionKind {
+    Original,
+    SpeedUp,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TransactionStatus {
+    Pending,
+    Confirmed,
+    Replaced,
+}
+
+// One broadcast EVM transaction. Replacements share the nonce of the
+// transaction they replace and point back at it through `replaces`, so every
+// record of a (chain, sender, nonce) is one replacement chain.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransactionDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub chain_id: String,
+    pub from: String,
+    pub nonce: i64,
+    pub hash: String,
+    pub kind: TransactionKind,
+    pub replaces: Option<String>,
+    pub status: TransactionStatus,
+    pub created_at: DateTime,
+}
@@ -2,19 +2,65 @@ use mongodb::bson::{DateTime, oid::ObjectId};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Serialize)]
+// `index` is the next unused address index under this chain's account, while
+// `public_key` and `address` stay the primary address at index 0.
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ChainInfo {
     pub index: u32,
+    pub account: u32,
     pub public_key: String,
     pub address: String,
     pub balance: String,
     pub rpc_url: String,
     pub chain_type: ChainType,
+    pub addresses: Vec<DerivedAddress>,
+    #[serde(default)]
+    pub tokens: Vec<FungibleTokenData>,
+    #[serde(default)]
+    pub nfts: Vec<NonFungibleTokenData>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl ChainInfo {
+    pub fn find_address(&self, address: &str) -> Option<&DerivedAddress> {
+        self.addresses
+            .iter()
+            .find(|derived| derived.address.eq_ignore_ascii_case(address))
+    }
+
+    pub fn find_token(&self, contract: &str) -> Option<&FungibleTokenData> {
+        self.tokens
+            .iter()
+            .find(|token| token.contract.eq_ignore_ascii_case(contract))
+    }
+
+    pub fn find_nft(
+        &self,
+        contract: &str,
+        token_id: &str,
+        owner: &str,
+    ) -> Option<&NonFungibleTokenData> {
+        self.nfts.iter().find(|nft| {
+            nft.contract.eq_ignore_ascii_case(contract)
+                && nft.token_id == token_id
+                && nft.owner.eq_ignore_ascii_case(owner)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DerivedAddress {
+    pub index: u32,
+    pub path: String,
+    pub public_key: String,
+    pub address: String,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum ChainType {
     EVM,
+    SOLANA,
+    BITCOIN,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -23,10 +69,135 @@ pub struct UserWalletSchema {
     pub id: Option<ObjectId>,
     pub email: String,
     pub password: String,
-    pub private_key_a: String,
-    pub private_key_b: String,
-    pub private_key_c: String,
+    pub share_threshold: u32,
+    pub share_total: u32,
+    pub share_epoch: u32,
+    pub client_share_index: u32,
+    pub public_key: String,
+    pub chain_code: String,
+    pub eddsa_key: EddsaKeyInfo,
+    pub chains: HashMap<String, ChainInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp: Option<TotpSettings>,
+    #[serde(default)]
+    pub passkeys: Vec<PasskeyCredential>,
+    #[serde(default)]
+    pub role: AccountRole,
+}
+
+// What a user may see of their own wallet document: everything but the password
+// hash and the second factor secrets.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PublicUserWallet {
+    pub id: String,
+    pub email: String,
+    pub share_threshold: u32,
+    pub share_total: u32,
+    pub share_epoch: u32,
+    pub client_share_index: u32,
+    pub public_key: String,
+    pub chain_code: String,
+    pub eddsa_key: EddsaKeyInfo,
     pub chains: HashMap<String, ChainInfo>,
+    pub role: AccountRole,
+}
+
+impl From<UserWalletSchema> for PublicUserWallet {
+    fn from(user: UserWalletSchema) -> Self {
+        PublicUserWallet {
+            id: user.key_owner(),
+            email: user.email,
+            share_threshold: user.share_threshold,
+            share_total: user.share_total,
+            share_epoch: user.share_epoch,
+            client_share_index: user.client_share_index,
+            public_key: user.public_key,
+            chain_code: user.chain_code,
+            eddsa_key: user.eddsa_key,
+            chains: user.chains,
+            role: user.role,
+        }
+    }
+}
+
+// Operators run the wallet service itself, e.g. rotate the master key. The role
+// is only ever set on the document in the database, no route grants it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountRole {
+    #[default]
+    Member,
+    Operator,
+}
+
+// The user's TOTP authenticator. `secret` is base32, as shown to the app, and is
+// only enforced once a first code confirmed the enrollment. `last_step` is the
+// time step of the last accepted code, so no code is accepted twice. Recovery
+// codes are bcrypt hashes, each removed once used.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TotpSettings {
+    pub secret: String,
+    pub confirmed: bool,
+    pub last_step: i64,
+    pub recovery_codes: Vec<String>,
+}
+
+// A registered WebAuthn credential. `credential_id` is base64url as sent by the
+// browser, `public_key` the uncompressed P-256 key in hex. `sign_count` is the
+// authenticator's counter from the last accepted assertion.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PasskeyCredential {
+    pub credential_id: String,
+    pub public_key: String,
+    pub sign_count: i64,
+    pub name: String,
+    pub created_at: DateTime,
+}
+
+// The Ed25519 key is shared between the same indices as the secp256k1 key but
+// keeps its own policy and epoch, since refresh and resharing act on one key.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EddsaKeyInfo {
+    pub public_key: String,
+    pub share_threshold: u32,
+    pub share_total: u32,
+    pub share_epoch: u32,
+    pub client_share_index: u32,
+}
+
+impl UserWalletSchema {
+    // Key shares are stored outside the user document, keyed by the user id.
+    pub fn key_owner(&self) -> String {
+        self.id.map(|id| id.to_hex()).unwrap_or_default()
+    }
+
+    pub fn eddsa_key_owner(&self) -> String {
+        format!("{}-ed25519", self.key_owner())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeyShare {
+    pub index: u32,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SealedKeyShare {
+    pub index: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EncryptedKeyShare {
+    pub index: u32,
+    pub ciphertext: String,
+    pub nonce: String,
+    pub wrapped_key: String,
+    pub key_nonce: String,
+    pub key_version: u32,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -58,8 +229,26 @@ pub struct Chain {
     pub non_fungible_token: Vec<NonFungibleTokenData>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct FungibleTokenData {}
+// An ERC-20 token, amounts of it are in units of 10^-decimals.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FungibleTokenData {
+    pub contract: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct NonFungibleTokenData {}
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftStandard {
+    ERC721,
+    ERC1155,
+}
+
+// One NFT held by `owner`. `token_id` is a decimal string since ids are uint256.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NonFungibleTokenData {
+    pub contract: String,
+    pub token_id: String,
+    pub standard: NftStandard,
+    pub metadata_uri: String,
+    pub owner: String,
+}
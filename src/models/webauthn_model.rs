@@ -0,0 +1,21 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebauthnCeremony {
+    Registration,
+    Authentication,
+}
+
+// A challenge handed to the browser for one ceremony. It is deleted when the
+// response comes back, so every challenge is answered at most once.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebauthnChallengeDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub ceremony: WebauthnCeremony,
+    pub challenge: String,
+    pub expires_at: DateTime,
+}
@@ -0,0 +1,55 @@
+use axum::{
+    Extension, Json, Router,
+    extract::Path,
+    middleware,
+    response::IntoResponse,
+    routing::{delete, post},
+};
+use std::sync::Arc;
+
+use crate::routes::handler::api_key_handler::{ApiKeyRequest, UserApiKeyServices};
+use crate::routes::handler::auth_handler::{AuthUser, authenticate};
+use crate::routes::handler::two_factor_handler::SecondFactor;
+use crate::services::database::Database;
+
+// Keys are managed from a user session only, never with another API key.
+pub fn api_key_routes() -> Router {
+    Router::new()
+        .route(
+            "/user/api-keys",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<ApiKeyRequest>| async move {
+                    match db.create_api_key(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            )
+            .get(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>| async move {
+                    match db.list_api_keys(&session).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/api-keys/{id}",
+            delete(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Path(id): Path<String>| async move {
+                    match db.revoke_api_key(&session, id).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn(authenticate))
+}
@@ -3,15 +3,48 @@ use std::sync::Arc;
 use crate::{routes::handler::auth_handler, services::database::Database};
 use axum::{
     Extension, Json, Router,
+    response::IntoResponse,
     routing::{get, post},
 };
 
-use super::handler::auth_handler::{RegisterRequest, UserAuthServices};
+use super::handler::auth_handler::{
+    LoginRequest, RefreshRequest, RegisterRequest, RegisterResponse, UserAuthServices,
+};
 
 pub fn auth_routes() -> Router {
     Router::new()
         .route("/user/register", post(|Extension(db): Extension<Arc<Database>>, Json(payload):Json<RegisterRequest>| async move {
             db.register_user(payload).await
         }))
-        .route("/user/login", get({ async || "user logged in" }))
+        .route(
+            "/user/register/policy",
+            get(|Extension(db): Extension<Arc<Database>>| async move {
+                match db.key_policy() {
+                    Ok(success) => success.into_response(),
+                    Err(error) => error.into_response(),
+                }
+            }),
+        )
+        .route(
+            "/user/login",
+            post(
+                |Extension(db): Extension<Arc<Database>>, Json(payload): Json<LoginRequest>| async move {
+                    match UserAuthServices::<RegisterResponse>::login_user(&*db, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/session/refresh",
+            post(
+                |Extension(db): Extension<Arc<Database>>, Json(payload): Json<RefreshRequest>| async move {
+                    match UserAuthServices::<RegisterResponse>::refresh_session(&*db, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
 }
@@ -1,17 +1,21 @@
 use axum::{
-    Extension, Json, Router,
+    Extension, Json, Router, middleware,
     response::IntoResponse,
     routing::{get, post},
 };
 use std::sync::Arc;
 
 use crate::{
-    models::chain_model::WalletChainDataSchema, routes::handler::chain_handler,
+    models::{api_key_model::ApiKeyScope, chain_model::WalletChainDataSchema},
+    routes::handler::{
+        auth_handler::{AuthUser, authenticate, authorize, operator_only},
+        chain_handler::{self, ChainAddressRequest},
+    },
     services::database::Database,
 };
 
 pub fn chain_routes() -> Router {
-    Router::new()
+    let admin = Router::new()
         .route(
             "/protocol/config",
             post(
@@ -24,6 +28,40 @@ pub fn chain_routes() -> Router {
                 },
             ),
         )
+        .route_layer(middleware::from_fn_with_state(
+            ApiKeyScope::AdminChainConfig,
+            authorize,
+        ));
+
+    // Rotating the master key rewraps the shares of every user.
+    let operators = Router::new()
+        .route(
+            "/protocol/master-key/rotate",
+            post(|Extension(db): Extension<Arc<Database>>| async move {
+                match db.rotate_master_key().await {
+                    Ok(success) => success.into_response(),
+                    Err(error) => error.into_response(),
+                }
+            }),
+        )
+        .route_layer(middleware::from_fn(operator_only));
+
+    Router::new()
+        .route(
+            "/user/chain/address",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<ChainAddressRequest>| async move {
+                    match db.create_chain_address(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        // The supported chains are public, everything above needs a session.
+        .route_layer(middleware::from_fn(authenticate))
         .route(
             "/get/protocols",
             get(|Extension(db): Extension<Arc<Database>>| async move {
@@ -33,4 +71,6 @@ pub fn chain_routes() -> Router {
                 }
             }),
         )
+        .merge(admin)
+        .merge(operators)
 }
@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::api_key_errors::ApiKeyError,
+    models::{
+        api_key_model::{ApiKeyDocument, ApiKeyScope},
+        user_wallet_model::AccountRole,
+    },
+    services::database::Database,
+};
+
+use super::auth_handler::AuthUser;
+use super::response_handler::{ErrorResponse, SuccessResponse};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+// Transfer keys move funds without a second factor, so they must expire.
+const MAX_TRANSFER_KEY_DAYS: u32 = 90;
+
+// Keys without `expires_in_days` stay valid until revoked, except transfer keys
+// which need one of at most `MAX_TRANSFER_KEY_DAYS`. With `organization_id`
+// the key is issued on that organization, which the caller must be a member of.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiKeyRequest {
+    pub password: String,
+    pub name: String,
+    pub organization_id: Option<String>,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_in_days: Option<u32>,
+}
+
+// Times are RFC 3339.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub organization_id: Option<String>,
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked: bool,
+}
+
+// `key` is only shown here, the wallet keeps a hash of it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IssuedApiKey {
+    pub key: String,
+    pub api_key: ApiKeyInfo,
+}
+
+#[async_trait]
+pub trait UserApiKeyServices {
+    async fn create_api_key(
+        &self,
+        session: &AuthUser,
+        payload: ApiKeyRequest,
+    ) -> std::result::Result<SuccessResponse<IssuedApiKey>, ErrorResponse>;
+
+    async fn list_api_keys(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<Vec<ApiKeyInfo>>, ErrorResponse>;
+
+    async fn revoke_api_key(
+        &self,
+        session: &AuthUser,
+        id: String,
+    ) -> std::result::Result<SuccessResponse<ApiKeyInfo>, ErrorResponse>;
+}
+
+#[async_trait]
+impl UserApiKeyServices for Database {
+    async fn create_api_key(
+        &self,
+        session: &AuthUser,
+        mut payload: ApiKeyRequest,
+    ) -> std::result::Result<SuccessResponse<IssuedApiKey>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        payload.scopes.sort();
+        payload.scopes.dedup();
+        if payload.scopes.is_empty() {
+            return Err(ErrorResponse {
+                error: Some(String::from("API_KEY_SCOPES_REQUIRED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+        if payload.scopes.contains(&ApiKeyScope::Transfer)
+            && !payload
+                .expires_in_days
+                .is_some_and(|days| (1..=MAX_TRANSFER_KEY_DAYS).contains(&days))
+        {
+            return Err(ErrorResponse {
+                error: Some(String::from("TRANSFER_KEY_EXPIRY_REQUIRED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+        // Keys on an organization carry its role instead of the user's.
+        let role = match &payload.organization_id {
+            Some(id) => self.member_organization(&session.user_id, id).await?.role,
+            None => user.role,
+        };
+        if !payload.scopes.iter().all(|scope| scope.allowed_for(role)) {
+            return Err(ErrorResponse {
+                error: Some(String::from("OPERATOR_ONLY!")),
+                status: StatusCode::FORBIDDEN,
+            });
+        }
+        let expires_at = payload.expires_in_days.map(|days| {
+            DateTime::from_millis(DateTime::now().timestamp_millis() + days as i64 * DAY_MILLIS)
+        });
+
+        match self
+            .api_keys
+            .issue(
+                &session.user_id,
+                &user.email,
+                payload.organization_id.as_deref(),
+                &payload.name,
+                payload.scopes,
+                expires_at,
+            )
+            .await
+        {
+            Ok((key, api_key)) => Ok(SuccessResponse {
+                data: Some(IssuedApiKey {
+                    key,
+                    api_key: api_key_info(api_key),
+                }),
+                message: Some(String::from("API KEY CREATED")),
+                status: StatusCode::OK,
+            }),
+            Err(e) => Err(api_key_error(e)),
+        }
+    }
+
+    async fn list_api_keys(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<Vec<ApiKeyInfo>>, ErrorResponse> {
+        let organization_ids = self.member_organization_ids(session).await?;
+        match self
+            .api_keys
+            .list(&session.user_id, &organization_ids)
+            .await
+        {
+            Ok(api_keys) => Ok(SuccessResponse {
+                data: Some(api_keys.into_iter().map(api_key_info).collect()),
+                message: Some(String::from("OK!")),
+                status: StatusCode::OK,
+            }),
+            Err(e) => Err(api_key_error(e)),
+        }
+    }
+
+    async fn revoke_api_key(
+        &self,
+        session: &AuthUser,
+        id: String,
+    ) -> std::result::Result<SuccessResponse<ApiKeyInfo>, ErrorResponse> {
+        let Ok(id) = ObjectId::parse_str(&id) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("API_KEY_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+        let organization_ids = self.member_organization_ids(session).await?;
+        match self
+            .api_keys
+            .revoke(&session.user_id, &organization_ids, id)
+            .await
+        {
+            Ok(api_key) => Ok(SuccessResponse {
+                data: Some(api_key_info(api_key)),
+                message: Some(String::from("API KEY REVOKED")),
+                status: StatusCode::OK,
+            }),
+            Err(e) => Err(api_key_error(e)),
+        }
+    }
+}
+
+impl Database {
+    async fn member_organization_ids(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<Vec<String>, ErrorResponse> {
+        Ok(self
+            .member_organizations(&session.user_id)
+            .await?
+            .into_iter()
+            .filter_map(|organization| organization.id.map(|id| id.to_hex()))
+            .collect())
+    }
+}
+
+fn api_key_info(api_key: ApiKeyDocument) -> ApiKeyInfo {
+    let time = |time: DateTime| time.try_to_rfc3339_string().unwrap_or_default();
+    ApiKeyInfo {
+        id: api_key.id.map(|id| id.to_hex()).unwrap_or_default(),
+        name: api_key.name,
+        organization_id: api_key.organization_id,
+        prefix: api_key.prefix,
+        scopes: api_key.scopes,
+        created_at: time(api_key.created_at),
+        expires_at: api_key.expires_at.map(time),
+        last_used_at: api_key.last_used_at.map(time),
+        revoked: api_key.revoked_at.is_some(),
+    }
+}
+
+fn api_key_error(e: ApiKeyError) -> ErrorResponse {
+    match e {
+        ApiKeyError::InvalidKey => ErrorResponse {
+            error: Some(String::from("API_KEY_NOT_FOUND!")),
+            status: StatusCode::NOT_FOUND,
+        },
+        e => {
+            tracing::error!(error = %e, "api key query failed");
+            ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operator_scopes_need_the_operator_role() {
+        for scope in [ApiKeyScope::ReadBalances, ApiKeyScope::Transfer] {
+            assert!(scope.allowed_for(AccountRole::Member));
+            assert!(scope.allowed_for(AccountRole::Operator));
+        }
+        assert!(!ApiKeyScope::AdminChainConfig.allowed_for(AccountRole::Member));
+        assert!(ApiKeyScope::AdminChainConfig.allowed_for(AccountRole::Operator));
+    }
+}
@@ -1,13 +1,17 @@
 use async_trait::async_trait;
 use axum::{
     Extension, Json, body,
-    extract::State,
-    http::{StatusCode, status},
-    response::IntoResponse,
+    extract::{FromRequestParts, Request, State},
+    http::{StatusCode, header::AUTHORIZATION, request::Parts, status},
+    middleware::Next,
+    response::{IntoResponse, Response},
 };
 use bcrypt::{DEFAULT_COST, hash};
 use hex;
-use mongodb::{bson::doc, results};
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    results,
+};
 use num_bigint::BigInt;
 use rand::{RngCore, TryRngCore, rngs::OsRng};
 use secp256k1::SecretKey;
@@ -16,16 +20,225 @@ use sss_rs::basic_sharing;
 use std::{collections::HashMap, fmt::Debug, sync::Arc};
 use wcookie::SetCookie;
 
+use crate::errors::{api_key_errors::ApiKeyError, session_errors::SessionError};
+use crate::models::api_key_model::ApiKeyScope;
+use crate::routes::handler::response_handler::{ErrorResponse, SuccessResponse};
+use crate::services::{api_key_services::ApiKeyManager, session_services::ACCESS_TOKEN_TTL};
 use crate::{models::user_wallet_model::ChainType, routes::handler::response_handler::{AxumApiResponse, JsonApiResponse}};
 use crate::{
-    models::user_wallet_model::{UserWalletSchema, ChainInfo},
-    services::{database::Database, key_services},
+    models::user_wallet_model::{AccountRole, PublicUserWallet, UserWalletSchema, ChainInfo, DerivedAddress, EddsaKeyInfo, SealedKeyShare},
+    services::{database::Database, dkg_services::DkgPartyMessage, key_services},
 };
 
+// The client's device runs the DKG parties of the client and recovery indices
+// of `KeyPolicy`, one set per wallet key. Each message carries the party's
+// commitments and the shares it dealt to the server's parties.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RegisterRequest {
     pub email: String,
     pub password: String,
+    pub parties: Vec<DkgPartyMessage>,
+    pub eddsa_parties: Vec<DkgPartyMessage>,
+}
+
+// The server's parties' commitments and the shares they dealt to the device's
+// parties. The device finishes its parties with them, checks it arrived at the
+// user's public keys and seals the client and recovery shares with the password
+// (see `seal_client_share`). Those shares never reach the server.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegisterResponse {
+    pub user: PublicUserWallet,
+    pub parties: Vec<DkgPartyMessage>,
+    pub eddsa_parties: Vec<DkgPartyMessage>,
+}
+
+// The share policy new wallets are generated with, and which of its indices the
+// server runs. The device runs all the others.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyPolicy {
+    pub threshold: u32,
+    pub total: u32,
+    pub server_indices: Vec<u32>,
+    pub client_index: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+// `expires_in` is the lifetime of the access token in seconds. The refresh
+// token can be used once, refreshing returns the next one.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+}
+
+// Resolves the caller of every wallet route from its access token. Handlers
+// read the result with `Extension<AuthUser>`. API keys are turned away, routes
+// open to them go through `authorize` instead.
+pub async fn authenticate(session: AuthUser, mut request: Request, next: Next) -> Response {
+    if session.scopes.is_some() {
+        return ErrorResponse {
+            error: Some(String::from("API_KEY_NOT_ALLOWED!")),
+            status: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+    request.extensions_mut().insert(session);
+    next.run(request).await
+}
+
+// Like `authenticate`, but also lets in API keys that carry `scope`. Operator
+// scopes also need the operator role, checked on every request so that taking
+// the role away locks out existing keys too.
+pub async fn authorize(
+    State(scope): State<ApiKeyScope>,
+    Extension(db): Extension<Arc<Database>>,
+    session: AuthUser,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !session.allows(scope) {
+        return ErrorResponse {
+            error: Some(String::from("API_KEY_SCOPE_MISSING!")),
+            status: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+    if scope.requires_operator() {
+        match db.is_operator(&session).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return ErrorResponse {
+                    error: Some(String::from("OPERATOR_ONLY!")),
+                    status: StatusCode::FORBIDDEN,
+                }
+                .into_response();
+            }
+            Err(error) => return error.into_response(),
+        }
+    }
+    request.extensions_mut().insert(session);
+    next.run(request).await
+}
+
+// Lets in the sessions of operators only. Operator actions affect every wallet,
+// so they are never open to API keys.
+pub async fn operator_only(
+    Extension(db): Extension<Arc<Database>>,
+    session: AuthUser,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if session.scopes.is_some() {
+        return ErrorResponse {
+            error: Some(String::from("API_KEY_NOT_ALLOWED!")),
+            status: StatusCode::FORBIDDEN,
+        }
+        .into_response();
+    }
+    match db.is_operator(&session).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return ErrorResponse {
+                error: Some(String::from("OPERATOR_ONLY!")),
+                status: StatusCode::FORBIDDEN,
+            }
+            .into_response();
+        }
+        Err(error) => return error.into_response(),
+    }
+    request.extensions_mut().insert(session);
+    next.run(request).await
+}
+
+// The caller of a wallet route, taken from the bearer access token or API key.
+// `scopes` is only set for API keys, sessions can do everything their role
+// allows. `organization_id` is set for keys issued on an organization.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: String,
+    pub email: String,
+    pub scopes: Option<Vec<ApiKeyScope>>,
+    pub organization_id: Option<String>,
+}
+
+impl AuthUser {
+    pub fn allows(&self, scope: ApiKeyScope) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope))
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(db) = parts.extensions.get::<Arc<Database>>() else {
+            return Err(ErrorResponse {
+                error: Some(String::from("SERVER_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+        let Some(token) = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Err(ErrorResponse {
+                error: Some(String::from("MISSING_ACCESS_TOKEN!")),
+                status: StatusCode::UNAUTHORIZED,
+            });
+        };
+        if ApiKeyManager::is_api_key(token) {
+            return match db.api_keys.authenticate(token).await {
+                Ok(api_key) => Ok(AuthUser {
+                    user_id: api_key.user_id,
+                    email: api_key.email,
+                    scopes: Some(api_key.scopes),
+                    organization_id: api_key.organization_id,
+                }),
+                Err(ApiKeyError::InvalidKey) => Err(ErrorResponse {
+                    error: Some(String::from("INVALID_API_KEY!")),
+                    status: StatusCode::UNAUTHORIZED,
+                }),
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to authenticate api key");
+                    Err(ErrorResponse {
+                        error: Some(String::from("DATABASE_ERROR!")),
+                        status: StatusCode::INTERNAL_SERVER_ERROR,
+                    })
+                }
+            };
+        }
+        match db.sessions.verify_access_token(token) {
+            Ok(claims) => Ok(AuthUser {
+                user_id: claims.sub,
+                email: claims.email,
+                scopes: None,
+                organization_id: None,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("INVALID_ACCESS_TOKEN!")),
+                status: StatusCode::UNAUTHORIZED,
+            }),
+        }
+    }
 }
 
 
@@ -35,14 +248,26 @@ where
     T: Serialize + Debug
 {
     async fn register_user(&self, payload: RegisterRequest) -> AxumApiResponse<T>;
-    async fn login_user(&self) -> AxumApiResponse<T>;
+    async fn login_user(
+        &self,
+        payload: LoginRequest,
+    ) -> Result<SuccessResponse<SessionTokens>, ErrorResponse>;
+    async fn refresh_session(
+        &self,
+        payload: RefreshRequest,
+    ) -> Result<SuccessResponse<SessionTokens>, ErrorResponse>;
 }
 
 #[async_trait]
-impl UserAuthServices<UserWalletSchema> for Database {
-    async fn register_user(&self, payload: RegisterRequest) -> AxumApiResponse<UserWalletSchema> {
-        use crate::services::key_services::KeyServices;
-        use crate::services::chains_services::generate_chain_data;
+impl UserAuthServices<RegisterResponse> for Database {
+    async fn register_user(&self, payload: RegisterRequest) -> AxumApiResponse<RegisterResponse> {
+        use crate::services::{
+            dkg_services,
+            key_services::{Secp256k1Group, SharePolicy},
+        };
+        use crate::chains::features::{ChainPublicKey, KeyScheme};
+        use crate::services::derivation_services::derive_public_key;
+        use crate::services::eddsa_services::{EDDSA_ROOT_PATH, Ed25519Group};
 
         // Check if user already exists
         if self
@@ -63,18 +288,51 @@ impl UserAuthServices<UserWalletSchema> for Database {
             );
         }
 
-        // Generate secret key parts
-        let secret_key = KeyServices::generate_secret_key().unwrap();
-        let hex_secret_key = hex::encode(secret_key.secret_bytes());
-        let part_size = hex_secret_key.len() / 3;
-        let (part_one, part_two, part_thr) = (
-            &hex_secret_key[..part_size],
-            &hex_secret_key[part_size..part_size * 2],
-            &hex_secret_key[2 * part_size..],
-        );
+        // Run distributed key generation with the device's parties, once for the
+        // secp256k1 key and once for the Ed25519 key. The server keeps the shares
+        // of its own parties, one short of the threshold.
+        let policy = match SharePolicy::from_env() {
+            Ok(policy) => policy,
+            Err(e) => {
+                return AxumApiResponse::ERROR(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    JsonApiResponse {
+                        data: None,
+                        message: Some(String::from("Unable to generate wallet key")),
+                        error: Some(e.to_string()),
+                    },
+                );
+            }
+        };
+        let server_indices = policy.server_indices();
+        let (joint_key, eddsa_joint_key) = match dkg_services::run_with_remote::<Secp256k1Group>(
+            policy,
+            &server_indices,
+            &payload.parties,
+        )
+        .and_then(|joint_key| {
+            let eddsa_joint_key = dkg_services::run_with_remote::<Ed25519Group>(
+                policy,
+                &server_indices,
+                &payload.eddsa_parties,
+            )?;
+            Ok((joint_key, eddsa_joint_key))
+        }) {
+            Ok(data) => data,
+            Err(e) => {
+                return AxumApiResponse::ERROR(
+                    StatusCode::BAD_REQUEST,
+                    JsonApiResponse {
+                        data: None,
+                        message: Some(String::from("Unable to generate wallet key")),
+                        error: Some(e.to_string()),
+                    },
+                );
+            }
+        };
 
         // Hash password
-        let hash_password = match hash(payload.password, DEFAULT_COST) {
+        let hash_password = match hash(&payload.password, DEFAULT_COST) {
             Ok(hash) => hash,
             Err(_) => {
                 return AxumApiResponse::ERROR(
@@ -93,33 +351,123 @@ impl UserAuthServices<UserWalletSchema> for Database {
         cookie.http_only = true;
         cookie.path = Some(String::from("/"));
 
-        // Addind default chain configurations
-        let (_address, _public_key) = generate_chain_data(&hex_secret_key);
+        // Addind default chain configurations, each one's primary address is the
+        // first child of its account
+        let eddsa_public_key = hex::encode(eddsa_joint_key.public_key.compress().as_bytes());
         let mut chains = HashMap::new();
-        chains.insert(String::from("1"), ChainInfo {
-            index: 1,
-            public_key: _public_key,
-            address: _address,
-            balance: String::from("0"),
-            rpc_url: String::from("https://eth.llamarpc.com"),
-            chain_type: ChainType::EVM
-        });
+        for default_chain in self.chains.defaults() {
+            let Some(chain) = self.chains.get(default_chain.chain_type) else {
+                continue;
+            };
+            let chain_data = match chain.key_scheme() {
+                KeyScheme::Secp256k1 => chain
+                    .derivation_path(default_chain.account, 0)
+                    .map_err(|e| e.to_string())
+                    .and_then(|path| {
+                        let key =
+                            derive_public_key(&joint_key.public_key, &joint_key.chain_code, &path)
+                                .map_err(|e| e.to_string())?;
+                        chain
+                            .derive_address(&ChainPublicKey::Secp256k1(key.public_key))
+                            .map_err(|e| e.to_string())
+                            .map(|address| (address, path.to_string()))
+                    }),
+                KeyScheme::Ed25519 => chain
+                    .derive_address(&ChainPublicKey::Ed25519(eddsa_joint_key.public_key))
+                    .map_err(|e| e.to_string())
+                    .map(|address| (address, String::from(EDDSA_ROOT_PATH))),
+            };
+            let ((address, public_key), path) = match chain_data {
+                Ok(data) => data,
+                Err(e) => {
+                    return AxumApiResponse::ERROR(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        JsonApiResponse {
+                            data: None,
+                            message: Some(String::from("Unable to derive wallet address")),
+                            error: Some(e),
+                        },
+                    );
+                }
+            };
+            chains.insert(default_chain.chain_id.clone(), ChainInfo {
+                index: 1,
+                account: default_chain.account,
+                public_key: public_key.clone(),
+                address: address.clone(),
+                balance: String::from("0"),
+                rpc_url: default_chain.rpc_url.clone(),
+                chain_type: default_chain.chain_type,
+                addresses: vec![DerivedAddress {
+                    index: 0,
+                    path,
+                    public_key,
+                    address,
+                }],
+                tokens: Vec::new(),
+                nfts: Vec::new(),
+            });
+        }
 
         // Create user schema
         let user_wallet = UserWalletSchema {
-            id: None,
+            id: Some(ObjectId::new()),
             email: payload.email.clone(),
             password: hash_password,
-            private_key_a: part_one.to_string(),
-            private_key_b: part_two.to_string(),
-            private_key_c: part_thr.to_string(),
-            chains
+            share_threshold: policy.threshold,
+            share_total: policy.total,
+            share_epoch: 0,
+            client_share_index: policy.total,
+            public_key: hex::encode(joint_key.public_key.serialize()),
+            chain_code: hex::encode(joint_key.chain_code),
+            eddsa_key: EddsaKeyInfo {
+                public_key: eddsa_public_key,
+                share_threshold: policy.threshold,
+                share_total: policy.total,
+                share_epoch: 0,
+                client_share_index: policy.total,
+            },
+            chains,
+            totp: None,
+            passkeys: Vec::new(),
+            role: AccountRole::Member,
         };
 
+        // Store each server share in the key store configured for its index
+        let (key_owner, eddsa_key_owner) = (user_wallet.key_owner(), user_wallet.eddsa_key_owner());
+        let stored = match self.key_vault.store_shares(&key_owner, 0, &joint_key.shares).await {
+            Ok(()) => {
+                self.key_vault
+                    .store_shares(&eddsa_key_owner, 0, &eddsa_joint_key.shares)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = stored {
+            let _ = self.key_vault.delete_shares(&key_owner, 0, policy.total).await;
+            let _ = self
+                .key_vault
+                .delete_shares(&eddsa_key_owner, 0, policy.total)
+                .await;
+            return AxumApiResponse::ERROR(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JsonApiResponse {
+                    data: None,
+                    message: Some(String::from("Unable to store key shares")),
+                    error: Some(e.to_string()),
+                },
+            );
+        }
+
         // Insert user into database
         let insert_result = match self.user_wallet.insert_one(user_wallet).await {
             Ok(data) => data,
             Err(e) => {
+                let _ = self.key_vault.delete_shares(&key_owner, 0, policy.total).await;
+                let _ = self
+                    .key_vault
+                    .delete_shares(&eddsa_key_owner, 0, policy.total)
+                    .await;
                 let error_msg = if e.to_string().contains("E11000 duplicate key error") {
                     "Device ID already exists"
                 } else {
@@ -170,15 +518,147 @@ impl UserAuthServices<UserWalletSchema> for Database {
         AxumApiResponse::SUCCESS(
             StatusCode::OK,
             JsonApiResponse {
-                data: Some(user_data),
+                data: Some(RegisterResponse {
+                    user: PublicUserWallet::from(user_data),
+                    parties: joint_key.replies,
+                    eddsa_parties: eddsa_joint_key.replies,
+                }),
                 message: Some(String::from("User registered successfully")),
                 error: None,
             },
         )
     }
 
-    async fn login_user(&self) -> AxumApiResponse<UserWalletSchema> {
-        unimplemented!()
+    async fn login_user(
+        &self,
+        payload: LoginRequest,
+    ) -> Result<SuccessResponse<SessionTokens>, ErrorResponse> {
+        let user = self
+            .authenticate_user(&payload.email, &payload.password)
+            .await
+            .map_err(|e| match e.status {
+                // Unknown emails look the same as wrong passwords.
+                StatusCode::NOT_FOUND => ErrorResponse {
+                    error: Some(String::from("INVALID_CREDENTIALS!")),
+                    status: StatusCode::UNAUTHORIZED,
+                },
+                _ => e,
+            })?;
+        self.session_tokens(&user.key_owner(), &user.email, None)
+            .await
+    }
+
+    async fn refresh_session(
+        &self,
+        payload: RefreshRequest,
+    ) -> Result<SuccessResponse<SessionTokens>, ErrorResponse> {
+        let (user_id, refresh_token) = match self
+            .sessions
+            .rotate_refresh_token(&payload.refresh_token)
+            .await
+        {
+            Ok(rotated) => rotated,
+            Err(SessionError::InvalidToken | SessionError::TokenReused) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("INVALID_REFRESH_TOKEN!")),
+                    status: StatusCode::UNAUTHORIZED,
+                });
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to rotate refresh token");
+                return Err(ErrorResponse {
+                    error: Some(String::from("SESSION_ERROR!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                });
+            }
+        };
+        let user = match ObjectId::parse_str(&user_id) {
+            Ok(id) => self.user_wallet.find_one(doc! {"_id": id}).await.ok().flatten(),
+            Err(_) => None,
+        };
+        let Some(user) = user else {
+            return Err(ErrorResponse {
+                error: Some(String::from("INVALID_REFRESH_TOKEN!")),
+                status: StatusCode::UNAUTHORIZED,
+            });
+        };
+        self.session_tokens(&user_id, &user.email, Some(refresh_token))
+            .await
+    }
+}
+
+impl Database {
+    // What a device needs to know before it runs its parties of the DKG.
+    pub fn key_policy(&self) -> Result<SuccessResponse<KeyPolicy>, ErrorResponse> {
+        match key_services::SharePolicy::from_env() {
+            Ok(policy) => Ok(SuccessResponse {
+                data: Some(KeyPolicy {
+                    threshold: policy.threshold,
+                    total: policy.total,
+                    server_indices: policy.server_indices(),
+                    client_index: policy.total,
+                }),
+                message: Some(String::from("OK!")),
+                status: StatusCode::OK,
+            }),
+            Err(e) => {
+                tracing::error!(error = %e, "invalid share policy");
+                Err(ErrorResponse {
+                    error: Some(String::from("KEY_POLICY_INVALID!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                })
+            }
+        }
+    }
+
+    // Whether the caller holds the operator role. Keys on an organization hold
+    // the organization's role while their issuer is still a member, everything
+    // else the user's.
+    pub async fn is_operator(&self, session: &AuthUser) -> Result<bool, ErrorResponse> {
+        let role = match &session.organization_id {
+            Some(id) => match self.member_organization(&session.user_id, id).await {
+                Ok(organization) => organization.role,
+                Err(error) if error.status == StatusCode::NOT_FOUND => return Ok(false),
+                Err(error) => return Err(error),
+            },
+            None => self.session_user(session).await?.role,
+        };
+        Ok(role == AccountRole::Operator)
+    }
+
+    // A new access token, with `refresh_token` or else a new refresh token family.
+    async fn session_tokens(
+        &self,
+        user_id: &str,
+        email: &str,
+        refresh_token: Option<String>,
+    ) -> Result<SuccessResponse<SessionTokens>, ErrorResponse> {
+        let tokens = async {
+            let access_token = self.sessions.access_token(user_id, email)?;
+            let refresh_token = match refresh_token {
+                Some(refresh_token) => refresh_token,
+                None => self.sessions.refresh_token(user_id).await?,
+            };
+            Ok::<_, SessionError>((access_token, refresh_token))
+        };
+        match tokens.await {
+            Ok((access_token, refresh_token)) => Ok(SuccessResponse {
+                data: Some(SessionTokens {
+                    access_token,
+                    refresh_token,
+                    token_type: String::from("Bearer"),
+                    expires_in: ACCESS_TOKEN_TTL.as_secs(),
+                }),
+                message: Some(String::from("OK!")),
+                status: StatusCode::OK,
+            }),
+            Err(e) => {
+                tracing::error!(user_id = %user_id, error = %e, "failed to issue session tokens");
+                Err(ErrorResponse {
+                    error: Some(String::from("SESSION_ERROR!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                })
+            }
+        }
     }
-    
 }
@@ -4,13 +4,24 @@ use futures::TryStreamExt;
 use mongodb::bson::doc;
 use tracing::instrument::WithSubscriber;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    models::chain_model::WalletChainDataSchema,
+    chains::features::{ChainPublicKey, KeyScheme},
+    models::{chain_model::WalletChainDataSchema, user_wallet_model::DerivedAddress},
+    routes::handler::auth_handler::AuthUser,
     routes::handler::response_handler::{AxumApiResponse, ErrorResponse, SuccessResponse},
-    services::database::Database,
+    services::{database::Database, derivation_services::derive_user_key},
 };
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChainAddressRequest {
+    pub password: String,
+    pub chain_id: String,
+}
+
 impl Database {
+    #[allow(clippy::needless_return)]
     pub async fn config_chain(
         &self,
         payload: WalletChainDataSchema,
@@ -65,4 +76,94 @@ impl Database {
             status: StatusCode::OK,
         })
     }
+
+    // Derives the next address of a chain's account from the user's public root
+    // key. No key shares are touched, so the client share is not needed here.
+    pub async fn create_chain_address(
+        &self,
+        session: &AuthUser,
+        payload: ChainAddressRequest,
+    ) -> std::result::Result<SuccessResponse<DerivedAddress>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let Some(chain_data) = user.chains.get(&payload.chain_id) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_CHAIN_DATA_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+
+        let Some(chain) = self.chains.get(chain_data.chain_type) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_CHAIN_NOT_SUPPORTED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+        // Only the secp256k1 root supports non-hardened derivation, and each
+        // chain derives below its own purpose and coin type.
+        let path = match chain.derivation_path(chain_data.account, chain_data.index) {
+            Ok(path) if chain.key_scheme() == KeyScheme::Secp256k1 => path,
+            _ => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("ADDRESS_DERIVATION_NOT_SUPPORTED!")),
+                    status: StatusCode::BAD_REQUEST,
+                });
+            }
+        };
+        let chain_address = derive_user_key(&user.public_key, &user.chain_code, &path)
+            .ok()
+            .and_then(|key| {
+                chain
+                    .derive_address(&ChainPublicKey::Secp256k1(key.public_key))
+                    .ok()
+            });
+        let Some((address, public_key)) = chain_address else {
+            return Err(ErrorResponse {
+                error: Some(String::from("ADDRESS_DERIVATION_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+        let derived_address = DerivedAddress {
+            index: path.index,
+            path: path.to_string(),
+            public_key,
+            address,
+        };
+        let Ok(address_document) = mongodb::bson::to_bson(&derived_address) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("ADDRESS_DERIVATION_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+
+        // Only advance the index we derived from, so two concurrent requests
+        // can never hand out the same address.
+        let chain_key = format!("chains.{}", payload.chain_id);
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, format!("{}.index", chain_key): chain_data.index},
+                doc! {
+                    "$set": {format!("{}.index", chain_key): chain_data.index + 1},
+                    "$push": {format!("{}.addresses", chain_key): address_document},
+                },
+            )
+            .await
+        {
+            Ok(result) if result.modified_count == 1 => Ok(SuccessResponse {
+                data: Some(derived_address),
+                message: Some(String::from("ADDRESS CREATED")),
+                status: StatusCode::OK,
+            }),
+            Ok(_) => Err(ErrorResponse {
+                error: Some(String::from("CHAIN_ADDRESS_CONFLICT!")),
+                status: StatusCode::CONFLICT,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
 }
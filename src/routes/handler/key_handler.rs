@@ -0,0 +1,802 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use bcrypt::verify;
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chains::features::{ChainSigner, KeyScheme},
+    errors::key_store_errors::KeyStoreError,
+    models::user_wallet_model::{ChainInfo, KeyShare, SealedKeyShare, UserWalletSchema},
+    services::{
+        database::Database,
+        derivation_services::{DerivationPath, derive_user_key, tweak_shares},
+        eddsa_services::{Ed25519Group, EddsaThresholdSigner},
+        encryption_services::{open_client_share, seal_client_share},
+        key_services::{Secp256k1Group, ShareGroup, ShareHolder, SharePolicy},
+        signing_services::ThresholdSigner,
+    },
+};
+
+use super::auth_handler::AuthUser;
+use super::response_handler::{ErrorResponse, SuccessResponse};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyRefreshRequest {
+    pub password: String,
+    pub client_share: SealedKeyShare,
+    pub client_eddsa_share: SealedKeyShare,
+}
+
+// Every refresh replaces the recovery shares too, older ones no longer combine
+// with the new epoch. Both wallet keys move to a new epoch together.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyRefreshResponse {
+    pub share_epoch: u32,
+    pub client_share: SealedKeyShare,
+    pub recovery_shares: Vec<SealedKeyShare>,
+    pub client_eddsa_share: SealedKeyShare,
+    pub recovery_eddsa_shares: Vec<SealedKeyShare>,
+}
+
+// For a lost client device: the recovery shares stand in for the client shares
+// and the wallet moves to a new epoch, which retires the lost shares.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyRecoveryRequest {
+    pub password: String,
+    pub recovery_share: SealedKeyShare,
+    pub recovery_eddsa_share: SealedKeyShare,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyReshareRequest {
+    pub password: String,
+    pub client_share: SealedKeyShare,
+    pub client_eddsa_share: SealedKeyShare,
+    pub threshold: u32,
+    pub total: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyReshareResponse {
+    pub share_epoch: u32,
+    pub threshold: u32,
+    pub total: u32,
+    pub client_share: SealedKeyShare,
+    pub recovery_shares: Vec<SealedKeyShare>,
+    pub client_eddsa_share: SealedKeyShare,
+    pub recovery_eddsa_shares: Vec<SealedKeyShare>,
+}
+
+// The sealed client and recovery shares of both wallet keys after a commit.
+struct CommittedShares {
+    client_share: SealedKeyShare,
+    recovery_shares: Vec<SealedKeyShare>,
+    client_eddsa_share: SealedKeyShare,
+    recovery_eddsa_shares: Vec<SealedKeyShare>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MasterKeyRotationResponse {
+    pub key_version: u32,
+    pub rewrapped_users: u64,
+    pub failed_users: u64,
+}
+
+#[async_trait]
+pub trait UserKeyServices {
+    async fn refresh_key_shares(
+        &self,
+        session: &AuthUser,
+        payload: KeyRefreshRequest,
+    ) -> std::result::Result<SuccessResponse<KeyRefreshResponse>, ErrorResponse>;
+
+    async fn reshare_key_shares(
+        &self,
+        session: &AuthUser,
+        payload: KeyReshareRequest,
+    ) -> std::result::Result<SuccessResponse<KeyReshareResponse>, ErrorResponse>;
+
+    async fn recover_key_shares(
+        &self,
+        session: &AuthUser,
+        payload: KeyRecoveryRequest,
+    ) -> std::result::Result<SuccessResponse<KeyRefreshResponse>, ErrorResponse>;
+}
+
+impl Database {
+    // The wallet of the session's user. Requests never name the wallet they act
+    // on, it always comes from the caller's identity.
+    pub async fn session_user(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<UserWalletSchema, ErrorResponse> {
+        let Ok(id) = ObjectId::parse_str(&session.user_id) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+        match self.user_wallet.find_one(doc! {"_id": id}).await {
+            Ok(Some(user)) => Ok(user),
+            Ok(None) => Err(ErrorResponse {
+                error: Some(String::from("USER_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::FORBIDDEN,
+            }),
+        }
+    }
+
+    // The session's wallet, for requests that confirm the password again.
+    pub async fn authenticate_session(
+        &self,
+        session: &AuthUser,
+        password: &str,
+    ) -> std::result::Result<UserWalletSchema, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        match verify(password, &user.password) {
+            Ok(true) => Ok(user),
+            _ => Err(ErrorResponse {
+                error: Some(String::from("INVALID_CREDENTIALS!")),
+                status: StatusCode::UNAUTHORIZED,
+            }),
+        }
+    }
+
+    pub async fn authenticate_user(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> std::result::Result<UserWalletSchema, ErrorResponse> {
+        let user = match self.user_wallet.find_one(doc! {"email": email}).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_NOT_FOUND!")),
+                    status: StatusCode::NOT_FOUND,
+                });
+            }
+            Err(_) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("DATABASE_ERROR!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                });
+            }
+        };
+
+        match verify(password, &user.password) {
+            Ok(true) => Ok(user),
+            _ => Err(ErrorResponse {
+                error: Some(String::from("INVALID_CREDENTIALS!")),
+                status: StatusCode::UNAUTHORIZED,
+            }),
+        }
+    }
+
+    // Opens the client share sent with the request and tops it up with server
+    // shares. The server only keeps threshold - 1 shares, so without the client
+    // share (or a recovery share in its place, see `recover_key_shares`) the
+    // stored shares cannot sign.
+    // Note the client share is opened here with the request's password: while a
+    // request is served the server does hold a full threshold in memory. Only at
+    // rest is the client share out of its reach.
+    pub async fn load_key_shares(
+        &self,
+        user: &UserWalletSchema,
+        client_share: &SealedKeyShare,
+        password: &str,
+        required: u32,
+    ) -> std::result::Result<Vec<KeyShare>, ErrorResponse> {
+        let client_share = unseal_client_share(client_share, password, user.client_share_index)?;
+        self.load_shares_with_client(
+            &user.key_owner(),
+            user.share_epoch,
+            user.share_total,
+            client_share,
+            required,
+        )
+        .await
+    }
+
+    // Same as `load_key_shares` for the Ed25519 key, which always needs exactly
+    // its threshold of shares.
+    pub async fn load_eddsa_key_shares(
+        &self,
+        user: &UserWalletSchema,
+        client_share: &SealedKeyShare,
+        password: &str,
+    ) -> std::result::Result<Vec<KeyShare>, ErrorResponse> {
+        let key = &user.eddsa_key;
+        let client_share = unseal_client_share(client_share, password, key.client_share_index)?;
+        self.load_shares_with_client(
+            &user.eddsa_key_owner(),
+            key.share_epoch,
+            key.share_total,
+            client_share,
+            key.share_threshold,
+        )
+        .await
+    }
+
+    async fn load_shares_with_client(
+        &self,
+        owner: &str,
+        epoch: u32,
+        total: u32,
+        client_share: KeyShare,
+        required: u32,
+    ) -> std::result::Result<Vec<KeyShare>, ErrorResponse> {
+        match self
+            .key_vault
+            .load_shares(owner, epoch, total, required.saturating_sub(1))
+            .await
+        {
+            Ok(server_shares) => Ok(std::iter::once(client_share).chain(server_shares).collect()),
+            Err(KeyStoreError::SharesUnavailable(_, _)) => Err(ErrorResponse {
+                error: Some(String::from("KEY_SHARES_MISSING!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+            Err(e) => {
+                tracing::error!(owner, epoch, error = %e, "loading key shares failed");
+                Err(ErrorResponse {
+                    error: Some(String::from("KEY_SHARES_UNAVAILABLE!")),
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                })
+            }
+        }
+    }
+
+    // The shares behind one derived address are the master shares shifted by the
+    // address's BIP32 tweak. Returns them together with the address public key.
+    pub fn address_key_shares(
+        &self,
+        user: &UserWalletSchema,
+        chain: &ChainInfo,
+        address: &str,
+        key_shares: Vec<KeyShare>,
+    ) -> std::result::Result<(Vec<KeyShare>, String), ErrorResponse> {
+        let Some(derived_address) = chain.find_address(address) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_ADDRESS_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+
+        // The stored path is what the address was derived with, so it is what the
+        // shares are tweaked with as well.
+        derived_address
+            .path
+            .parse::<DerivationPath>()
+            .and_then(|path| derive_user_key(&user.public_key, &user.chain_code, &path))
+            .and_then(|derived_key| {
+                let key_shares = tweak_shares(&key_shares, &derived_key.tweak)?;
+                Ok((
+                    key_shares,
+                    hex::encode(derived_key.public_key.serialize_uncompressed()),
+                ))
+            })
+            .map_err(|_| ErrorResponse {
+                error: Some(String::from("USER_ADDRESS_INVALID!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            })
+    }
+
+    // Builds the signer for `address` on `chain`, from whichever wallet key the
+    // chain's implementation signs with.
+    pub async fn chain_signer(
+        &self,
+        user: &UserWalletSchema,
+        chain: &ChainInfo,
+        key_scheme: KeyScheme,
+        address: &str,
+        client_share: &SealedKeyShare,
+        password: &str,
+    ) -> std::result::Result<ChainSigner, ErrorResponse> {
+        let signer = match key_scheme {
+            KeyScheme::Secp256k1 => {
+                let key_shares = self
+                    .load_key_shares(user, client_share, password, user.share_threshold)
+                    .await?;
+                let (key_shares, public_key) =
+                    self.address_key_shares(user, chain, address, key_shares)?;
+                ThresholdSigner::new(key_shares, user.share_threshold, &public_key)
+                    .map(ChainSigner::Ecdsa)
+            }
+            KeyScheme::Ed25519 => {
+                if chain.find_address(address).is_none() {
+                    return Err(ErrorResponse {
+                        error: Some(String::from("USER_ADDRESS_NOT_FOUND!")),
+                        status: StatusCode::NOT_FOUND,
+                    });
+                }
+                let key_shares = self
+                    .load_eddsa_key_shares(user, client_share, password)
+                    .await?;
+                EddsaThresholdSigner::new(
+                    key_shares,
+                    user.eddsa_key.share_threshold,
+                    &user.eddsa_key.public_key,
+                )
+                .map(ChainSigner::Eddsa)
+            }
+        };
+        signer.map_err(|_| ErrorResponse {
+            error: Some(String::from("USER_KEY_SHARES_INVALID!")),
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+        })
+    }
+
+    // Re-wraps every stored data key under a freshly generated master key. Older
+    // master key versions stay readable, so users that fail here keep working and
+    // are picked up by the next rotation.
+    pub async fn rotate_master_key(
+        &self,
+    ) -> std::result::Result<SuccessResponse<MasterKeyRotationResponse>, ErrorResponse> {
+        let key_version = match self.key_vault.encryption().rotate_master_key() {
+            Ok(version) => version,
+            Err(_) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("MASTER_KEY_ROTATION_ERROR!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                });
+            }
+        };
+
+        let mut users = match self.user_wallet.find(doc! {}).await {
+            Ok(users) => users,
+            Err(_) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("DATABASE_ERROR!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                });
+            }
+        };
+
+        let (mut rewrapped_users, mut failed_users) = (0, 0);
+        while let Ok(Some(user)) = users.try_next().await {
+            let key = &user.eddsa_key;
+            match self
+                .key_vault
+                .rewrap_shares(&user.key_owner(), user.share_epoch, user.share_total)
+                .await
+                .and(
+                    self.key_vault
+                        .rewrap_shares(&user.eddsa_key_owner(), key.share_epoch, key.share_total)
+                        .await,
+                )
+            {
+                Ok(()) => rewrapped_users += 1,
+                Err(_) => failed_users += 1,
+            }
+        }
+
+        Ok(SuccessResponse {
+            data: Some(MasterKeyRotationResponse {
+                key_version,
+                rewrapped_users,
+                failed_users,
+            }),
+            message: Some(String::from("MASTER KEY ROTATED")),
+            status: StatusCode::OK,
+        })
+    }
+
+    // Refreshes both wallet keys from a full threshold of shares and commits the
+    // result as the next epoch.
+    async fn refresh_and_commit(
+        &self,
+        user: &UserWalletSchema,
+        key_shares: &[KeyShare],
+        eddsa_key_shares: &[KeyShare],
+        password: &str,
+    ) -> std::result::Result<KeyRefreshResponse, ErrorResponse> {
+        let shares_invalid = || ErrorResponse {
+            error: Some(String::from("USER_KEY_SHARES_INVALID!")),
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let policy = SharePolicy::new(user.share_threshold, user.share_total)
+            .map_err(|_| shares_invalid())?;
+        let (key_shares, eddsa_key_shares) = Secp256k1Group::refresh_shares(key_shares, &policy)
+            .and_then(|key_shares| {
+                Ok((
+                    key_shares,
+                    Ed25519Group::refresh_shares(eddsa_key_shares, &policy)?,
+                ))
+            })
+            .map_err(|_| shares_invalid())?;
+
+        let shares = self
+            .commit_key_shares(user, &key_shares, &eddsa_key_shares, &policy, password)
+            .await?;
+        Ok(KeyRefreshResponse {
+            share_epoch: user.share_epoch + 1,
+            client_share: shares.client_share,
+            recovery_shares: shares.recovery_shares,
+            client_eddsa_share: shares.client_eddsa_share,
+            recovery_eddsa_shares: shares.recovery_eddsa_shares,
+        })
+    }
+
+    // Writes the server shares of the next epoch for both wallet keys, then moves
+    // the user document over to them in one update and only then drops the
+    // previous epochs. Losing the epoch race removes the freshly written shares
+    // instead, so epochs never get mixed. The new client and recovery shares are
+    // sealed and handed back to the caller.
+    async fn commit_key_shares(
+        &self,
+        user: &UserWalletSchema,
+        key_shares: &[KeyShare],
+        eddsa_key_shares: &[KeyShare],
+        policy: &SharePolicy,
+        password: &str,
+    ) -> std::result::Result<CommittedShares, ErrorResponse> {
+        let (owner, eddsa_owner) = (user.key_owner(), user.eddsa_key_owner());
+        let eddsa_key = &user.eddsa_key;
+        let share_total = policy.total;
+        let (share_epoch, eddsa_share_epoch) = (user.share_epoch + 1, eddsa_key.share_epoch + 1);
+        let next_epochs = [
+            (owner.as_str(), share_epoch, share_total),
+            (eddsa_owner.as_str(), eddsa_share_epoch, share_total),
+        ];
+        let (client_share, recovery_shares, server_shares) =
+            split_key_shares(key_shares, policy, password)?;
+        let (client_eddsa_share, recovery_eddsa_shares, server_eddsa_shares) =
+            split_key_shares(eddsa_key_shares, policy, password)?;
+
+        if self
+            .key_vault
+            .store_shares(&owner, share_epoch, &server_shares)
+            .await
+            .and(
+                self.key_vault
+                    .store_shares(&eddsa_owner, eddsa_share_epoch, &server_eddsa_shares)
+                    .await,
+            )
+            .is_err()
+        {
+            self.delete_epochs(&next_epochs).await;
+            return Err(ErrorResponse {
+                error: Some(String::from("KEY_STORE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        }
+
+        match self
+            .user_wallet
+            .update_one(
+                doc! {
+                    "_id": user.id,
+                    "share_epoch": user.share_epoch,
+                    "eddsa_key.share_epoch": eddsa_key.share_epoch,
+                },
+                doc! {"$set": {
+                    "share_threshold": policy.threshold,
+                    "share_total": share_total,
+                    "share_epoch": share_epoch,
+                    "client_share_index": share_total,
+                    "eddsa_key.share_threshold": policy.threshold,
+                    "eddsa_key.share_total": share_total,
+                    "eddsa_key.share_epoch": eddsa_share_epoch,
+                    "eddsa_key.client_share_index": share_total,
+                }},
+            )
+            .await
+        {
+            Ok(result) if result.modified_count == 1 => {
+                self.delete_epochs(&[
+                    (owner.as_str(), user.share_epoch, user.share_total),
+                    (
+                        eddsa_owner.as_str(),
+                        eddsa_key.share_epoch,
+                        eddsa_key.share_total,
+                    ),
+                ])
+                .await;
+                Ok(CommittedShares {
+                    client_share,
+                    recovery_shares,
+                    client_eddsa_share,
+                    recovery_eddsa_shares,
+                })
+            }
+            Ok(_) => {
+                self.delete_epochs(&next_epochs).await;
+                Err(ErrorResponse {
+                    error: Some(String::from("KEY_SHARES_CONFLICT!")),
+                    status: StatusCode::CONFLICT,
+                })
+            }
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    // Best effort: shares left behind by a failed delete belong to an epoch the
+    // user document no longer points at, so they are never loaded again.
+    async fn delete_epochs(&self, epochs: &[(&str, u32, u32)]) {
+        for &(owner, epoch, total) in epochs {
+            let _ = self.key_vault.delete_shares(owner, epoch, total).await;
+        }
+    }
+}
+
+// Splits a fresh set of shares by holder: the client and recovery shares come
+// back sealed with the user's password, the server shares are left to store.
+pub fn split_key_shares(
+    key_shares: &[KeyShare],
+    policy: &SharePolicy,
+    password: &str,
+) -> std::result::Result<(SealedKeyShare, Vec<SealedKeyShare>, Vec<KeyShare>), ErrorResponse> {
+    let sealing_error = || ErrorResponse {
+        error: Some(String::from("CLIENT_SHARE_INVALID!")),
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let mut client_share = None;
+    let mut recovery_shares = Vec::new();
+    let mut server_shares = Vec::new();
+    for share in key_shares {
+        match policy.holder(share.index) {
+            ShareHolder::Server => server_shares.push(share.clone()),
+            ShareHolder::Client => {
+                client_share =
+                    Some(seal_client_share(share, password).map_err(|_| sealing_error())?);
+            }
+            ShareHolder::Recovery => recovery_shares
+                .push(seal_client_share(share, password).map_err(|_| sealing_error())?),
+        }
+    }
+    let client_share = client_share.ok_or_else(sealing_error)?;
+    Ok((client_share, recovery_shares, server_shares))
+}
+
+// Rejects a sending address that isn't one of the caller's own on this chain.
+pub fn owned_address(
+    chain_data: &ChainInfo,
+    address: &str,
+) -> std::result::Result<(), ErrorResponse> {
+    match chain_data.find_address(address) {
+        Some(_) => Ok(()),
+        None => Err(ErrorResponse {
+            error: Some(String::from("USER_ADDRESS_NOT_FOUND!")),
+            status: StatusCode::FORBIDDEN,
+        }),
+    }
+}
+
+#[async_trait]
+impl UserKeyServices for Database {
+    async fn refresh_key_shares(
+        &self,
+        session: &AuthUser,
+        payload: KeyRefreshRequest,
+    ) -> std::result::Result<SuccessResponse<KeyRefreshResponse>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let key_shares = self
+            .load_key_shares(
+                &user,
+                &payload.client_share,
+                &payload.password,
+                user.share_threshold,
+            )
+            .await?;
+        let eddsa_key_shares = self
+            .load_eddsa_key_shares(&user, &payload.client_eddsa_share, &payload.password)
+            .await?;
+
+        let refreshed = self
+            .refresh_and_commit(&user, &key_shares, &eddsa_key_shares, &payload.password)
+            .await?;
+        Ok(SuccessResponse {
+            data: Some(refreshed),
+            message: Some(String::from("KEY SHARES REFRESHED")),
+            status: StatusCode::OK,
+        })
+    }
+
+    async fn reshare_key_shares(
+        &self,
+        session: &AuthUser,
+        payload: KeyReshareRequest,
+    ) -> std::result::Result<SuccessResponse<KeyReshareResponse>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+
+        let policy = match SharePolicy::new(payload.threshold, payload.total) {
+            Ok(policy) => policy,
+            Err(e) => {
+                return Err(ErrorResponse {
+                    error: Some(e.to_string()),
+                    status: StatusCode::BAD_REQUEST,
+                });
+            }
+        };
+
+        let key_shares = self
+            .load_key_shares(
+                &user,
+                &payload.client_share,
+                &payload.password,
+                user.share_threshold,
+            )
+            .await?;
+        let eddsa_key_shares = self
+            .load_eddsa_key_shares(&user, &payload.client_eddsa_share, &payload.password)
+            .await?;
+        let (key_shares, eddsa_key_shares) =
+            match Secp256k1Group::reshare_shares(&key_shares, user.share_threshold, &policy)
+                .and_then(|key_shares| {
+                    let eddsa_key_shares = Ed25519Group::reshare_shares(
+                        &eddsa_key_shares,
+                        user.eddsa_key.share_threshold,
+                        &policy,
+                    )?;
+                    Ok((key_shares, eddsa_key_shares))
+                }) {
+                Ok(shares) => shares,
+                Err(_) => {
+                    return Err(ErrorResponse {
+                        error: Some(String::from("USER_KEY_SHARES_INVALID!")),
+                        status: StatusCode::INTERNAL_SERVER_ERROR,
+                    });
+                }
+            };
+
+        // The old epochs are deleted once the new ones are committed, which
+        // retires them.
+        let shares = self
+            .commit_key_shares(
+                &user,
+                &key_shares,
+                &eddsa_key_shares,
+                &policy,
+                &payload.password,
+            )
+            .await?;
+
+        Ok(SuccessResponse {
+            data: Some(KeyReshareResponse {
+                share_epoch: user.share_epoch + 1,
+                threshold: policy.threshold,
+                total: policy.total,
+                client_share: shares.client_share,
+                recovery_shares: shares.recovery_shares,
+                client_eddsa_share: shares.client_eddsa_share,
+                recovery_eddsa_shares: shares.recovery_eddsa_shares,
+            }),
+            message: Some(String::from("KEY SHARES RESHARED")),
+            status: StatusCode::OK,
+        })
+    }
+
+    // Signs nothing with the recovery shares, they only rebuild a threshold
+    // for a refresh. The response carries the new client share for the
+    // replacement device, and new recovery shares since the old ones retire
+    // with their epoch.
+    async fn recover_key_shares(
+        &self,
+        session: &AuthUser,
+        payload: KeyRecoveryRequest,
+    ) -> std::result::Result<SuccessResponse<KeyRefreshResponse>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let eddsa_key = &user.eddsa_key;
+        let (Ok(policy), Ok(eddsa_policy)) = (
+            SharePolicy::new(user.share_threshold, user.share_total),
+            SharePolicy::new(eddsa_key.share_threshold, eddsa_key.share_total),
+        ) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_KEY_SHARES_INVALID!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+        let recovery_share =
+            unseal_recovery_share(&payload.recovery_share, &payload.password, &policy)?;
+        let recovery_eddsa_share = unseal_recovery_share(
+            &payload.recovery_eddsa_share,
+            &payload.password,
+            &eddsa_policy,
+        )?;
+
+        let key_shares = self
+            .load_shares_with_client(
+                &user.key_owner(),
+                user.share_epoch,
+                user.share_total,
+                recovery_share,
+                user.share_threshold,
+            )
+            .await?;
+        let eddsa_key_shares = self
+            .load_shares_with_client(
+                &user.eddsa_key_owner(),
+                eddsa_key.share_epoch,
+                eddsa_key.share_total,
+                recovery_eddsa_share,
+                eddsa_key.share_threshold,
+            )
+            .await?;
+
+        let refreshed = self
+            .refresh_and_commit(&user, &key_shares, &eddsa_key_shares, &payload.password)
+            .await?;
+        Ok(SuccessResponse {
+            data: Some(refreshed),
+            message: Some(String::from("KEY SHARES RECOVERED")),
+            status: StatusCode::OK,
+        })
+    }
+}
+
+fn unseal_client_share(
+    client_share: &SealedKeyShare,
+    password: &str,
+    client_share_index: u32,
+) -> std::result::Result<KeyShare, ErrorResponse> {
+    match open_client_share(client_share, password) {
+        Ok(share) if share.index == client_share_index => Ok(share),
+        _ => Err(ErrorResponse {
+            error: Some(String::from("CLIENT_SHARE_INVALID!")),
+            status: StatusCode::UNAUTHORIZED,
+        }),
+    }
+}
+
+// Recovery shares are sealed like the client share, but only indices the policy
+// hands out for recovery are accepted.
+fn unseal_recovery_share(
+    recovery_share: &SealedKeyShare,
+    password: &str,
+    policy: &SharePolicy,
+) -> std::result::Result<KeyShare, ErrorResponse> {
+    match open_client_share(recovery_share, password) {
+        Ok(share)
+            if share.index < policy.total
+                && policy.holder(share.index) == ShareHolder::Recovery =>
+        {
+            Ok(share)
+        }
+        _ => Err(ErrorResponse {
+            error: Some(String::from("RECOVERY_SHARE_INVALID!")),
+            status: StatusCode::UNAUTHORIZED,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sealed(index: u32, password: &str) -> SealedKeyShare {
+        let share = KeyShare {
+            index,
+            value: String::from("01"),
+        };
+        seal_client_share(&share, password).unwrap()
+    }
+
+    #[test]
+    fn only_recovery_indices_stand_in_for_the_client_share() {
+        // 3-of-5: the server keeps 1 and 2, recovery gets 3 and 4, the client 5.
+        let policy = SharePolicy::new(3, 5).unwrap();
+        for index in [3, 4] {
+            let share = unseal_recovery_share(&sealed(index, "password"), "password", &policy);
+            assert_eq!(share.unwrap().index, index);
+        }
+        for index in [0, 1, 2, 5, 6] {
+            assert!(
+                unseal_recovery_share(&sealed(index, "password"), "password", &policy).is_err()
+            );
+        }
+        assert!(unseal_recovery_share(&sealed(3, "password"), "wrong", &policy).is_err());
+    }
+}
@@ -1,4 +1,10 @@
 pub mod auth_handler;
 pub mod transaction_handler;
 pub mod response_handler;
-pub mod chain_handler;
\ No newline at end of file
+pub mod chain_handler;
+pub mod key_handler;
+pub mod token_handler;
+pub mod nft_handler;
+pub mod two_factor_handler;
+pub mod api_key_handler;
+pub mod organization_handler;
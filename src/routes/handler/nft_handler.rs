@@ -0,0 +1,362 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use ethers::types::U256;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    chains::{
+        features::{FeeSpeed, FeeStrategy, KeyScheme},
+        nft::Nft,
+    },
+    errors::chain_errors::ChainError,
+    models::user_wallet_model::{ChainInfo, NftStandard, NonFungibleTokenData, SealedKeyShare},
+    services::{database::Database, units_services::parse_units},
+};
+
+use super::auth_handler::AuthUser;
+use super::key_handler::owned_address;
+use super::response_handler::{ErrorResponse, SuccessResponse};
+use super::transaction_handler::{SubmissionResponse, evm_chain};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftRequest {
+    pub password: String,
+    pub chain_id: String,
+    pub contract: String,
+    pub token_id: String,
+    pub owner: String,
+}
+
+// Without `address` the holdings of every address on the chain are listed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftListRequest {
+    pub password: String,
+    pub chain_id: String,
+    pub address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftHolding {
+    pub nft: NonFungibleTokenData,
+    pub balance: String,
+}
+
+// `amount` defaults to 1 and must be 1 for ERC-721 tokens.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftTransferRequest {
+    pub chain_id: String,
+    pub contract: String,
+    pub token_id: String,
+    pub amount: Option<String>,
+    pub to: String,
+    pub from: String,
+    #[serde(default)]
+    pub speed: FeeSpeed,
+    pub password: String,
+    pub client_share: SealedKeyShare,
+}
+
+// ERC-1155 only, `amounts[i]` of `token_ids[i]` are sent in one transaction.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftBatchTransferRequest {
+    pub chain_id: String,
+    pub contract: String,
+    pub token_ids: Vec<String>,
+    pub amounts: Vec<String>,
+    pub to: String,
+    pub from: String,
+    #[serde(default)]
+    pub speed: FeeSpeed,
+    pub password: String,
+    pub client_share: SealedKeyShare,
+}
+
+#[async_trait]
+pub trait UserNftServices {
+    async fn add_nft(
+        &self,
+        session: &AuthUser,
+        payload: NftRequest,
+    ) -> std::result::Result<SuccessResponse<NonFungibleTokenData>, ErrorResponse>;
+
+    async fn list_nfts(
+        &self,
+        session: &AuthUser,
+        payload: NftListRequest,
+    ) -> std::result::Result<SuccessResponse<Vec<NftHolding>>, ErrorResponse>;
+
+    async fn transfer_nft(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: NftTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse>;
+
+    async fn batch_transfer_nfts(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: NftBatchTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse>;
+}
+
+#[async_trait]
+impl UserNftServices for Database {
+    // Starts tracking an NFT once the chain confirms `owner` holds it.
+    async fn add_nft(
+        &self,
+        session: &AuthUser,
+        payload: NftRequest,
+    ) -> std::result::Result<SuccessResponse<NonFungibleTokenData>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        if chain_data.find_address(&payload.owner).is_none() {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_ADDRESS_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        }
+        let token_id = parse_token_id(&payload.token_id)?;
+        if let Some(nft) =
+            chain_data.find_nft(&payload.contract, &token_id.to_string(), &payload.owner)
+        {
+            return Ok(SuccessResponse {
+                data: Some(nft.clone()),
+                message: Some(String::from("NFT EXISTS")),
+                status: StatusCode::OK,
+            });
+        }
+
+        let contract = nft_contract(chain_data, &payload.contract)?;
+        let standard = contract.standard().await.map_err(nft_error)?;
+        let balance = contract
+            .balance_of(standard, &payload.owner, token_id)
+            .await
+            .map_err(nft_error)?;
+        if balance.is_zero() {
+            return Err(ErrorResponse {
+                error: Some(String::from("NFT_NOT_OWNED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+        let metadata_uri = contract
+            .metadata_uri(standard, token_id)
+            .await
+            .map_err(nft_error)?;
+
+        let nft = NonFungibleTokenData {
+            contract: format!("{:#x}", contract.address()),
+            token_id: token_id.to_string(),
+            standard,
+            metadata_uri,
+            owner: payload.owner.to_lowercase(),
+        };
+        let Ok(nft_document) = mongodb::bson::to_bson(&nft) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("NFT_METADATA_INVALID!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id},
+                doc! {"$push": {format!("chains.{}.nfts", payload.chain_id): nft_document}},
+            )
+            .await
+        {
+            Ok(_) => Ok(SuccessResponse {
+                data: Some(nft),
+                message: Some(String::from("NFT ADDED")),
+                status: StatusCode::OK,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    // Tracked NFTs with their current on-chain balance, 0 for burned tokens.
+    async fn list_nfts(
+        &self,
+        session: &AuthUser,
+        payload: NftListRequest,
+    ) -> std::result::Result<SuccessResponse<Vec<NftHolding>>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+
+        let mut holdings = Vec::new();
+        for nft in chain_data.nfts.iter().filter(|nft| {
+            payload
+                .address
+                .as_ref()
+                .is_none_or(|address| nft.owner.eq_ignore_ascii_case(address))
+        }) {
+            let token_id = parse_token_id(&nft.token_id)?;
+            let balance = nft_contract(chain_data, &nft.contract)?
+                .balance_of(nft.standard, &nft.owner, token_id)
+                .await
+                .map_err(nft_error)?;
+            holdings.push(NftHolding {
+                nft: nft.clone(),
+                balance: balance.to_string(),
+            });
+        }
+
+        Ok(SuccessResponse {
+            data: Some(holdings),
+            message: Some(String::from("OK!")),
+            status: StatusCode::OK,
+        })
+    }
+
+    async fn transfer_nft(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: NftTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        owned_address(chain_data, &payload.from)?;
+        let token_id = parse_token_id(&payload.token_id)?;
+        let amount = match &payload.amount {
+            Some(amount) => parse_nft_amount(amount)?,
+            None => U256::one(),
+        };
+
+        let contract = nft_contract(chain_data, &payload.contract)?;
+        let tracked = chain_data
+            .find_nft(&payload.contract, &token_id.to_string(), &payload.from)
+            .cloned();
+        let standard = match &tracked {
+            Some(nft) => nft.standard,
+            None => contract.standard().await.map_err(nft_error)?,
+        };
+        let data = contract
+            .transfer_calldata(standard, &payload.from, &payload.to, token_id, amount)
+            .map_err(|_| ErrorResponse {
+                error: Some(String::from("NFT_TRANSFER_INVALID!")),
+                status: StatusCode::BAD_REQUEST,
+            })?;
+
+        let signer = self
+            .chain_signer(
+                &user,
+                chain_data,
+                KeyScheme::Secp256k1,
+                &payload.from,
+                &payload.client_share,
+                &payload.password,
+            )
+            .await?;
+        self.send_evm_call(
+            session,
+            &payload.chain_id,
+            chain_data,
+            &payload.from,
+            contract.address(),
+            data,
+            FeeStrategy::new(payload.speed),
+            // An ERC-721 token leaves the wallet address for good, so it
+            // stops being tracked once the transfer is confirmed.
+            tracked.filter(|nft| nft.standard == NftStandard::ERC721),
+            signer,
+        )
+        .await
+    }
+
+    async fn batch_transfer_nfts(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: NftBatchTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        owned_address(chain_data, &payload.from)?;
+        let token_ids = payload
+            .token_ids
+            .iter()
+            .map(|token_id| parse_token_id(token_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let amounts = payload
+            .amounts
+            .iter()
+            .map(|amount| parse_nft_amount(amount))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let contract = nft_contract(chain_data, &payload.contract)?;
+        if contract.standard().await.map_err(nft_error)? != NftStandard::ERC1155 {
+            return Err(ErrorResponse {
+                error: Some(String::from("NFT_BATCH_REQUIRES_ERC1155!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+        let data = contract
+            .batch_transfer_calldata(&payload.from, &payload.to, token_ids, amounts)
+            .map_err(|_| ErrorResponse {
+                error: Some(String::from("NFT_TRANSFER_INVALID!")),
+                status: StatusCode::BAD_REQUEST,
+            })?;
+
+        let signer = self
+            .chain_signer(
+                &user,
+                chain_data,
+                KeyScheme::Secp256k1,
+                &payload.from,
+                &payload.client_share,
+                &payload.password,
+            )
+            .await?;
+        self.send_evm_call(
+            session,
+            &payload.chain_id,
+            chain_data,
+            &payload.from,
+            contract.address(),
+            data,
+            FeeStrategy::new(payload.speed),
+            None,
+            signer,
+        )
+        .await
+    }
+}
+
+fn nft_contract(chain_data: &ChainInfo, contract: &str) -> std::result::Result<Nft, ErrorResponse> {
+    Nft::new(chain_data, contract).map_err(|_| ErrorResponse {
+        error: Some(String::from("NFT_CONTRACT_INVALID!")),
+        status: StatusCode::BAD_REQUEST,
+    })
+}
+
+fn parse_token_id(token_id: &str) -> std::result::Result<U256, ErrorResponse> {
+    U256::from_dec_str(token_id).map_err(|_| ErrorResponse {
+        error: Some(String::from("NFT_TOKEN_ID_INVALID!")),
+        status: StatusCode::BAD_REQUEST,
+    })
+}
+
+// NFT amounts are whole tokens.
+fn parse_nft_amount(amount: &str) -> std::result::Result<U256, ErrorResponse> {
+    match parse_units(amount, 0) {
+        Ok(amount) if !amount.is_zero() => Ok(amount),
+        _ => Err(ErrorResponse {
+            error: Some(String::from("INVALID_AMOUNT!")),
+            status: StatusCode::BAD_REQUEST,
+        }),
+    }
+}
+
+fn nft_error(e: ChainError) -> ErrorResponse {
+    tracing::warn!(error = %e, "NFT contract call failed");
+    ErrorResponse {
+        error: Some(String::from("NFT_CALL_ERROR!")),
+        status: StatusCode::BAD_GATEWAY,
+    }
+}
@@ -0,0 +1,377 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use futures::TryStreamExt;
+use mongodb::bson::{DateTime, doc, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{organization_model::OrganizationDocument, user_wallet_model::AccountRole},
+    services::database::Database,
+};
+
+use super::auth_handler::AuthUser;
+use super::response_handler::{ErrorResponse, SuccessResponse};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrganizationRequest {
+    pub name: String,
+}
+
+// `admin` also lets the new member manage the membership.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrganizationMemberRequest {
+    pub email: String,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+// `members` and `admins` are user ids.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OrganizationInfo {
+    pub id: String,
+    pub name: String,
+    pub role: AccountRole,
+    pub members: Vec<String>,
+    pub admins: Vec<String>,
+}
+
+#[async_trait]
+pub trait UserOrganizationServices {
+    async fn create_organization(
+        &self,
+        session: &AuthUser,
+        payload: OrganizationRequest,
+    ) -> std::result::Result<SuccessResponse<OrganizationInfo>, ErrorResponse>;
+
+    async fn list_organizations(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<Vec<OrganizationInfo>>, ErrorResponse>;
+
+    async fn add_organization_member(
+        &self,
+        session: &AuthUser,
+        id: String,
+        payload: OrganizationMemberRequest,
+    ) -> std::result::Result<SuccessResponse<OrganizationInfo>, ErrorResponse>;
+
+    async fn remove_organization_member(
+        &self,
+        session: &AuthUser,
+        id: String,
+        member_id: String,
+    ) -> std::result::Result<SuccessResponse<OrganizationInfo>, ErrorResponse>;
+}
+
+#[async_trait]
+impl UserOrganizationServices for Database {
+    // New organizations are members, whoever creates them. The creator is
+    // their first admin.
+    async fn create_organization(
+        &self,
+        session: &AuthUser,
+        payload: OrganizationRequest,
+    ) -> std::result::Result<SuccessResponse<OrganizationInfo>, ErrorResponse> {
+        let mut organization = OrganizationDocument {
+            id: None,
+            name: payload.name,
+            members: vec![session.user_id.clone()],
+            admins: vec![session.user_id.clone()],
+            role: AccountRole::Member,
+            created_at: DateTime::now(),
+        };
+        match self.organizations.insert_one(&organization).await {
+            Ok(inserted) => {
+                organization.id = inserted.inserted_id.as_object_id();
+                Ok(SuccessResponse {
+                    data: Some(organization_info(organization)),
+                    message: Some(String::from("ORGANIZATION CREATED")),
+                    status: StatusCode::OK,
+                })
+            }
+            Err(e) => Err(organization_error(e)),
+        }
+    }
+
+    async fn list_organizations(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<Vec<OrganizationInfo>>, ErrorResponse> {
+        let organizations = self.member_organizations(&session.user_id).await?;
+        Ok(SuccessResponse {
+            data: Some(organizations.into_iter().map(organization_info).collect()),
+            message: Some(String::from("OK!")),
+            status: StatusCode::OK,
+        })
+    }
+
+    // Only admins add members.
+    async fn add_organization_member(
+        &self,
+        session: &AuthUser,
+        id: String,
+        payload: OrganizationMemberRequest,
+    ) -> std::result::Result<SuccessResponse<OrganizationInfo>, ErrorResponse> {
+        let organization = self.member_organization(&session.user_id, &id).await?;
+        if !organization.is_admin(&session.user_id) {
+            return Err(organization_admin_only());
+        }
+        let member = match self
+            .user_wallet
+            .find_one(doc! {"email": &payload.email})
+            .await
+        {
+            Ok(Some(member)) => member,
+            Ok(None) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_NOT_FOUND!")),
+                    status: StatusCode::NOT_FOUND,
+                });
+            }
+            Err(e) => return Err(organization_error(e)),
+        };
+        let Some(member_id) = member.id.map(|id| id.to_hex()) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+
+        // The creator of an organization from before admins were kept is
+        // recorded as one with the first change.
+        let mut admins = Vec::new();
+        if organization.admins.is_empty() {
+            admins.push(session.user_id.clone());
+        }
+        if payload.admin {
+            admins.push(member_id.clone());
+        }
+        let mut update = doc! {"members": &member_id};
+        if !admins.is_empty() {
+            update.insert("admins", doc! {"$each": admins});
+        }
+        match self
+            .organizations
+            .find_one_and_update(doc! {"_id": organization.id}, doc! {"$addToSet": update})
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await
+        {
+            Ok(Some(organization)) => Ok(SuccessResponse {
+                data: Some(organization_info(organization)),
+                message: Some(String::from("MEMBER ADDED")),
+                status: StatusCode::OK,
+            }),
+            Ok(None) => Err(organization_not_found()),
+            Err(e) => Err(organization_error(e)),
+        }
+    }
+
+    // Admins remove anyone and members only themselves. The last admin has to
+    // stay, so the organization can still be managed.
+    async fn remove_organization_member(
+        &self,
+        session: &AuthUser,
+        id: String,
+        member_id: String,
+    ) -> std::result::Result<SuccessResponse<OrganizationInfo>, ErrorResponse> {
+        let organization = self.member_organization(&session.user_id, &id).await?;
+        check_member_removal(&organization, &session.user_id, &member_id)?;
+
+        // The filter keeps a concurrent removal from taking out the last admin.
+        let mut filter = doc! {"_id": organization.id, "members": &member_id};
+        if organization.admins.iter().any(|admin| admin == &member_id) {
+            filter.insert("admins.1", doc! {"$exists": true});
+        }
+        match self
+            .organizations
+            .find_one_and_update(
+                filter,
+                doc! {"$pull": {"members": &member_id, "admins": &member_id}},
+            )
+            .return_document(mongodb::options::ReturnDocument::After)
+            .await
+        {
+            Ok(Some(organization)) => Ok(SuccessResponse {
+                data: Some(organization_info(organization)),
+                message: Some(String::from("MEMBER REMOVED")),
+                status: StatusCode::OK,
+            }),
+            Ok(None) => Err(ErrorResponse {
+                error: Some(String::from("ORGANIZATION_MEMBER_CHANGED!")),
+                status: StatusCode::CONFLICT,
+            }),
+            Err(e) => Err(organization_error(e)),
+        }
+    }
+}
+
+impl Database {
+    // The organization `id` if `user_id` is one of its members.
+    pub async fn member_organization(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> std::result::Result<OrganizationDocument, ErrorResponse> {
+        let Ok(id) = ObjectId::parse_str(id) else {
+            return Err(organization_not_found());
+        };
+        match self
+            .organizations
+            .find_one(doc! {"_id": id, "members": user_id})
+            .await
+        {
+            Ok(Some(organization)) => Ok(organization),
+            Ok(None) => Err(organization_not_found()),
+            Err(e) => Err(organization_error(e)),
+        }
+    }
+
+    pub async fn member_organizations(
+        &self,
+        user_id: &str,
+    ) -> std::result::Result<Vec<OrganizationDocument>, ErrorResponse> {
+        let organizations = async {
+            self.organizations
+                .find(doc! {"members": user_id})
+                .sort(doc! {"created_at": -1})
+                .await?
+                .try_collect()
+                .await
+        };
+        organizations.await.map_err(organization_error)
+    }
+}
+
+fn organization_info(organization: OrganizationDocument) -> OrganizationInfo {
+    OrganizationInfo {
+        id: organization.id.map(|id| id.to_hex()).unwrap_or_default(),
+        name: organization.name,
+        role: organization.role,
+        members: organization.members,
+        admins: organization.admins,
+    }
+}
+
+// Whether `user_id` may take `member_id` out of `organization`.
+fn check_member_removal(
+    organization: &OrganizationDocument,
+    user_id: &str,
+    member_id: &str,
+) -> std::result::Result<(), ErrorResponse> {
+    if member_id != user_id && !organization.is_admin(user_id) {
+        return Err(organization_admin_only());
+    }
+    if !organization.is_member(member_id) {
+        return Err(ErrorResponse {
+            error: Some(String::from("ORGANIZATION_MEMBER_NOT_FOUND!")),
+            status: StatusCode::NOT_FOUND,
+        });
+    }
+    if organization.is_admin(member_id)
+        && organization.admins.iter().all(|admin| admin == member_id)
+    {
+        return Err(ErrorResponse {
+            error: Some(String::from("ORGANIZATION_LAST_ADMIN!")),
+            status: StatusCode::CONFLICT,
+        });
+    }
+    Ok(())
+}
+
+fn organization_admin_only() -> ErrorResponse {
+    ErrorResponse {
+        error: Some(String::from("ORGANIZATION_ADMIN_ONLY!")),
+        status: StatusCode::FORBIDDEN,
+    }
+}
+
+fn organization_not_found() -> ErrorResponse {
+    ErrorResponse {
+        error: Some(String::from("ORGANIZATION_NOT_FOUND!")),
+        status: StatusCode::NOT_FOUND,
+    }
+}
+
+fn organization_error(e: mongodb::error::Error) -> ErrorResponse {
+    tracing::error!(error = %e, "organization query failed");
+    ErrorResponse {
+        error: Some(String::from("DATABASE_ERROR!")),
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn organization_of(members: &[&str], admins: &[&str]) -> OrganizationDocument {
+        OrganizationDocument {
+            id: Some(ObjectId::new()),
+            name: String::from("treasury"),
+            members: members.iter().map(|member| member.to_string()).collect(),
+            admins: admins.iter().map(|admin| admin.to_string()).collect(),
+            role: AccountRole::Member,
+            created_at: DateTime::now(),
+        }
+    }
+
+    fn error(result: std::result::Result<(), ErrorResponse>) -> Option<String> {
+        result.err().and_then(|error| error.error)
+    }
+
+    #[test]
+    fn only_admins_manage_other_members() {
+        let organization = organization_of(&["alice", "bob", "carol"], &["alice"]);
+
+        assert!(organization.is_admin("alice"));
+        assert!(!organization.is_admin("bob"));
+        assert!(check_member_removal(&organization, "alice", "bob").is_ok());
+        assert_eq!(
+            error(check_member_removal(&organization, "bob", "carol")).as_deref(),
+            Some("ORGANIZATION_ADMIN_ONLY!")
+        );
+        assert_eq!(
+            error(check_member_removal(&organization, "mallory", "bob")).as_deref(),
+            Some("ORGANIZATION_ADMIN_ONLY!")
+        );
+        assert_eq!(
+            error(check_member_removal(&organization, "alice", "mallory")).as_deref(),
+            Some("ORGANIZATION_MEMBER_NOT_FOUND!")
+        );
+    }
+
+    #[test]
+    fn members_leave_but_the_last_admin_stays() {
+        let organization = organization_of(&["alice", "bob"], &["alice"]);
+
+        assert!(check_member_removal(&organization, "bob", "bob").is_ok());
+        assert_eq!(
+            error(check_member_removal(&organization, "alice", "alice")).as_deref(),
+            Some("ORGANIZATION_LAST_ADMIN!")
+        );
+
+        let two_admins = organization_of(&["alice", "bob"], &["alice", "bob"]);
+        assert!(check_member_removal(&two_admins, "alice", "alice").is_ok());
+        assert!(check_member_removal(&two_admins, "bob", "alice").is_ok());
+    }
+
+    #[test]
+    fn creators_of_older_organizations_are_their_admin() {
+        let organization = organization_of(&["alice", "bob"], &[]);
+
+        assert!(organization.is_admin("alice"));
+        assert!(!organization.is_admin("bob"));
+        assert_eq!(
+            error(check_member_removal(&organization, "alice", "alice")).as_deref(),
+            Some("ORGANIZATION_LAST_ADMIN!")
+        );
+    }
+
+    #[test]
+    fn admins_who_left_lose_the_role() {
+        let organization = organization_of(&["bob"], &["alice", "bob"]);
+
+        assert!(!organization.is_admin("alice"));
+        assert!(organization.is_admin("bob"));
+    }
+}
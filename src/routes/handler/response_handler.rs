@@ -8,6 +8,7 @@ pub struct JsonApiResponse<T> {
     pub message: Option<String>,
     pub error: Option<String>,
 }
+#[allow(clippy::upper_case_acronyms)]
 pub enum AxumApiResponse<T> {
     SUCCESS(StatusCode, JsonApiResponse<T>),
     ERROR(StatusCode, JsonApiResponse<T>)
@@ -0,0 +1,266 @@
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use ethers::types::{Bytes, U256};
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    chains::{
+        erc20::Erc20,
+        features::{FeeSpeed, FeeStrategy, KeyScheme},
+    },
+    errors::chain_errors::ChainError,
+    models::user_wallet_model::{ChainInfo, FungibleTokenData, SealedKeyShare},
+    services::{
+        database::Database,
+        units_services::{format_units, parse_units},
+    },
+};
+
+use super::auth_handler::AuthUser;
+use super::key_handler::owned_address;
+use super::response_handler::{ErrorResponse, SuccessResponse};
+use super::transaction_handler::{SubmissionResponse, amount_error, evm_chain};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenRequest {
+    pub password: String,
+    pub chain_id: String,
+    pub contract: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenBalanceRequest {
+    pub password: String,
+    pub chain_id: String,
+    pub contract: String,
+    pub address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenBalanceResponse {
+    pub token: FungibleTokenData,
+    pub address: String,
+    pub balance: String,
+}
+
+// `amount` is a decimal string in whole tokens, e.g. "1.5". For approvals `to`
+// is the spender.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenTransferRequest {
+    pub chain_id: String,
+    pub contract: String,
+    pub to: String,
+    pub from: String,
+    pub amount: String,
+    #[serde(default)]
+    pub speed: FeeSpeed,
+    pub password: String,
+    pub client_share: SealedKeyShare,
+}
+
+#[async_trait]
+pub trait UserTokenServices {
+    async fn add_token(
+        &self,
+        session: &AuthUser,
+        payload: TokenRequest,
+    ) -> std::result::Result<SuccessResponse<FungibleTokenData>, ErrorResponse>;
+
+    async fn token_balance(
+        &self,
+        session: &AuthUser,
+        payload: TokenBalanceRequest,
+    ) -> std::result::Result<SuccessResponse<TokenBalanceResponse>, ErrorResponse>;
+
+    async fn transfer_token(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: TokenTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse>;
+
+    async fn approve_token(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: TokenTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse>;
+}
+
+#[async_trait]
+impl UserTokenServices for Database {
+    // Starts tracking a token on one of the user's EVM chains.
+    async fn add_token(
+        &self,
+        session: &AuthUser,
+        payload: TokenRequest,
+    ) -> std::result::Result<SuccessResponse<FungibleTokenData>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        if let Some(token) = chain_data.find_token(&payload.contract) {
+            return Ok(SuccessResponse {
+                data: Some(token.clone()),
+                message: Some(String::from("TOKEN EXISTS")),
+                status: StatusCode::OK,
+            });
+        }
+
+        let token = token_contract(chain_data, &payload.contract)?
+            .metadata()
+            .await
+            .map_err(token_error)?;
+        let Ok(token_document) = mongodb::bson::to_bson(&token) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("TOKEN_METADATA_INVALID!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id},
+                doc! {"$push": {format!("chains.{}.tokens", payload.chain_id): token_document}},
+            )
+            .await
+        {
+            Ok(_) => Ok(SuccessResponse {
+                data: Some(token),
+                message: Some(String::from("TOKEN ADDED")),
+                status: StatusCode::OK,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    async fn token_balance(
+        &self,
+        session: &AuthUser,
+        payload: TokenBalanceRequest,
+    ) -> std::result::Result<SuccessResponse<TokenBalanceResponse>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        let erc20 = token_contract(chain_data, &payload.contract)?;
+        let token = token_metadata(chain_data, &erc20).await?;
+        let balance = erc20
+            .balance_of(&payload.address)
+            .await
+            .map_err(token_error)?;
+
+        Ok(SuccessResponse {
+            data: Some(TokenBalanceResponse {
+                balance: format_units(balance, token.decimals as u32),
+                token,
+                address: payload.address,
+            }),
+            message: Some(String::from("OK!")),
+            status: StatusCode::OK,
+        })
+    }
+
+    async fn transfer_token(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: TokenTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        self.send_token_call(session, &payload, |erc20, amount| {
+            erc20.transfer_calldata(&payload.to, amount)
+        })
+        .await
+    }
+
+    async fn approve_token(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: TokenTransferRequest,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        self.send_token_call(session, &payload, |erc20, amount| {
+            erc20.approve_calldata(&payload.to, amount)
+        })
+        .await
+    }
+}
+
+impl Database {
+    // Queues a call of the token contract from `payload.from`, with the calldata
+    // built from the parsed amount.
+    async fn send_token_call<F>(
+        self: Arc<Self>,
+        session: &AuthUser,
+        payload: &TokenTransferRequest,
+        calldata: F,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse>
+    where
+        F: FnOnce(&Erc20, U256) -> Result<Bytes, ChainError> + Send,
+    {
+        let user = self.session_user(session).await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        owned_address(chain_data, &payload.from)?;
+        let erc20 = token_contract(chain_data, &payload.contract)?;
+        let token = token_metadata(chain_data, &erc20).await?;
+        let amount = parse_units(&payload.amount, token.decimals as u32).map_err(amount_error)?;
+        let data = calldata(&erc20, amount).map_err(|_| ErrorResponse {
+            error: Some(String::from("USER_ADDRESS_INVALID!")),
+            status: StatusCode::BAD_REQUEST,
+        })?;
+
+        let signer = self
+            .chain_signer(
+                &user,
+                chain_data,
+                KeyScheme::Secp256k1,
+                &payload.from,
+                &payload.client_share,
+                &payload.password,
+            )
+            .await?;
+
+        self.send_evm_call(
+            session,
+            &payload.chain_id,
+            chain_data,
+            &payload.from,
+            erc20.address(),
+            data,
+            FeeStrategy::new(payload.speed),
+            None,
+            signer,
+        )
+        .await
+    }
+}
+
+fn token_contract(
+    chain_data: &ChainInfo,
+    contract: &str,
+) -> std::result::Result<Erc20, ErrorResponse> {
+    Erc20::new(chain_data, contract).map_err(|_| ErrorResponse {
+        error: Some(String::from("TOKEN_CONTRACT_INVALID!")),
+        status: StatusCode::BAD_REQUEST,
+    })
+}
+
+// Tracked tokens use their stored metadata, anything else is read from the contract.
+async fn token_metadata(
+    chain_data: &ChainInfo,
+    erc20: &Erc20,
+) -> std::result::Result<FungibleTokenData, ErrorResponse> {
+    match chain_data.find_token(&format!("{:#x}", erc20.address())) {
+        Some(token) => Ok(token.clone()),
+        None => erc20.metadata().await.map_err(token_error),
+    }
+}
+
+fn token_error(e: ChainError) -> ErrorResponse {
+    tracing::warn!(error = %e, "token contract call failed");
+    ErrorResponse {
+        error: Some(String::from("TOKEN_CALL_ERROR!")),
+        status: StatusCode::BAD_GATEWAY,
+    }
+}
@@ -2,115 +2,1242 @@ use async_trait::async_trait;
 use axum::http::StatusCode;
 use ethers::{
     providers::{Http, Middleware, Provider},
-    types::{Address, BlockId, BlockNumber, Eip1559TransactionRequest, U256},
+    types::{Address, BlockId, BlockNumber, Bytes, Eip1559TransactionRequest, U256},
 };
-use mongodb::bson::doc;
+use futures::TryStreamExt;
+use mongodb::bson::{DateTime, doc, oid::ObjectId, to_bson};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::Debug,
     ops::Add,
     result::Result::{Err, Ok},
+    sync::Arc,
+    time::Duration,
 };
 
 use crate::{
-    chains::ethereum::Ethereum,
-    models::user_wallet_model::ChainType,
+    chains::{
+        ethereum::Ethereum,
+        features::{ChainFeatures, ChainSigner, FeeSpeed, FeeStrategy, KeyScheme, NativeTransfer},
+    },
+    errors::{amount_errors::AmountError, bitcoin_errors::BitcoinError, chain_errors::ChainError},
+    models::{
+        submission_model::{SubmissionDocument, SubmissionStatus},
+        transaction_model::{TransactionDocument, TransactionKind, TransactionStatus},
+        user_wallet_model::{
+            ChainInfo, ChainType, NonFungibleTokenData, SealedKeyShare, UserWalletSchema,
+        },
+    },
     routes::handler::response_handler::{AxumApiResponse, JsonApiResponse},
     services::{
-        chains_services::{ChainResponse, ChainTypeTxn, EVMResponse, TXChain},
+        chains_services::{ChainTypeTxn, TXChain},
         database::Database,
+        eddsa_services::EddsaThresholdSigner,
+        units_services::{format_units, parse_units},
     },
 };
 
+use super::auth_handler::AuthUser;
+use super::key_handler::owned_address;
 use super::response_handler::{ErrorResponse, SuccessResponse};
 
+const GWEI_DECIMALS: u32 = 9;
+// How often the submission worker checks on broadcast transactions.
+const SUBMISSION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Signing runs in the request's own task. A submission still queued after
+// this was cut off, e.g. by a restart, and the shares it needed are gone.
+const SUBMISSION_SIGNING_TIMEOUT: Duration = Duration::from_secs(600);
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Transaction {
-    pub email: String,
     pub chain_id: String,
     pub tx_type: String,
     pub to: String,
     pub from: String,
-    pub amount: u32,
+    pub amount: String,
+    pub unit: AmountUnit,
+    #[serde(default)]
+    pub speed: FeeSpeed,
+    pub max_fee_per_gas: Option<String>,
+    pub max_priority_fee_per_gas: Option<String>,
+    pub password: String,
+    pub client_share: SealedKeyShare,
+}
+
+// Fee caps are decimal gwei strings, e.g. "30.5".
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeeEstimateRequest {
+    pub chain_id: String,
+    pub to: String,
+    pub from: String,
+    pub amount: String,
+    pub unit: AmountUnit,
+    pub max_fee_per_gas: Option<String>,
+    pub max_priority_fee_per_gas: Option<String>,
+}
+
+// `fee` is the most the transfer can cost in base units, `native_fee` the same
+// in the chain's currency.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeeEstimate {
+    pub speed: FeeSpeed,
+    pub fee: String,
+    pub native_fee: String,
+}
+
+// `Base` amounts are integers in the chain's smallest unit (wei, lamports,
+// sats). `Native` amounts are decimals of the currency itself, e.g. "1.5" ETH.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AmountUnit {
+    Base,
+    Native,
+}
+
+// `message` is hex encoded and signed as is, e.g. a serialized Solana transaction
+// message. `client_share` is the client's Ed25519 share.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignMessageRequest {
+    pub chain_id: String,
+    pub message: String,
+    pub password: String,
+    pub client_share: SealedKeyShare,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SignMessageResponse {
+    pub address: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubmissionResponse {
+    pub id: String,
+    pub status: SubmissionStatus,
+}
+
+// `receipt` is set once the transaction is mined, `error` once it failed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SubmissionStatusResponse {
+    pub id: String,
+    pub chain_id: String,
+    pub from: String,
+    pub to: String,
+    pub amount: String,
+    pub status: SubmissionStatus,
+    pub hash: Option<String>,
+    pub receipt: Option<TXChain>,
+    pub error: Option<String>,
+}
+
+// Re-signs a pending EVM transaction at `speed`. `chain_id` is the user's
+// chain the transaction was sent on.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplaceTransactionRequest {
+    pub chain_id: String,
+    #[serde(default)]
+    pub speed: FeeSpeed,
+    pub password: String,
+    pub client_share: SealedKeyShare,
+}
+
+// `id` is the submission following the nonce, `hash` the replacement just
+// broadcast. Whichever transaction of the nonce is mined settles the submission.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReplacementResponse {
+    pub id: String,
+    pub status: SubmissionStatus,
+    pub hash: String,
+}
+
+// What a queued submission sends: a native transfer on any chain, or a call of
+// an EVM contract such as a token or NFT transfer. `untracks` is an NFT the call
+// moves out of the wallet.
+pub enum QueuedTransaction {
+    Native(NativeTransfer),
+    EvmCall {
+        from: String,
+        contract: Address,
+        data: Bytes,
+        fees: FeeStrategy,
+        untracks: Option<NonFungibleTokenData>,
+    },
+}
+
+impl QueuedTransaction {
+    fn sender(&self) -> &str {
+        match self {
+            Self::Native(transfer) => &transfer.from,
+            Self::EvmCall { from, .. } => from,
+        }
+    }
 }
 
 #[async_trait]
 pub trait UserTransactionServices {
     async fn send_native_funds(
-        &self,
+        self: Arc<Self>,
+        session: &AuthUser,
         payload: Transaction,
-    ) -> std::result::Result<SuccessResponse<ChainResponse>, ErrorResponse>;
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse>;
+
+    async fn transaction_status(
+        &self,
+        session: &AuthUser,
+        id: String,
+    ) -> std::result::Result<SuccessResponse<SubmissionStatusResponse>, ErrorResponse>;
+
+    async fn sign_solana_message(
+        &self,
+        session: &AuthUser,
+        payload: SignMessageRequest,
+    ) -> std::result::Result<SuccessResponse<SignMessageResponse>, ErrorResponse>;
+
+    async fn estimate_fees(
+        &self,
+        session: &AuthUser,
+        payload: FeeEstimateRequest,
+    ) -> std::result::Result<SuccessResponse<Vec<FeeEstimate>>, ErrorResponse>;
+
+    async fn speed_up_transaction(
+        &self,
+        session: &AuthUser,
+        hash: String,
+        payload: ReplaceTransactionRequest,
+    ) -> std::result::Result<SuccessResponse<ReplacementResponse>, ErrorResponse>;
+
+    async fn cancel_transaction(
+        &self,
+        session: &AuthUser,
+        hash: String,
+        payload: ReplaceTransactionRequest,
+    ) -> std::result::Result<SuccessResponse<ReplacementResponse>, ErrorResponse>;
 }
 
 #[async_trait]
 impl UserTransactionServices for Database {
+    // Queues the transfer and returns its submission id. Signing and
+    // broadcasting happen in the background, see `submit`.
     async fn send_native_funds(
-        &self,
+        self: Arc<Self>,
+        session: &AuthUser,
         payload: Transaction,
-    ) -> std::result::Result<SuccessResponse<ChainResponse>, ErrorResponse> {
-        println!("Hell!");
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let Some(chain_data) = user.chains.get(&payload.chain_id) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_CHAIN_DATA_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+        owned_address(chain_data, &payload.from)?;
+        let Some(chain) = self.chains.get(chain_data.chain_type) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_CHAIN_NOT_SUPPORTED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
 
-        let res = match self
-            .user_wallet
-            .find_one(doc! {"email": &payload.email})
+        let decimals = match payload.unit {
+            AmountUnit::Base => 0,
+            AmountUnit::Native => self.native_decimals(&payload.chain_id, &chain).await,
+        };
+        let mut fees = fee_caps(&payload.max_fee_per_gas, &payload.max_priority_fee_per_gas)?;
+        fees.speed = payload.speed;
+        let transfer = NativeTransfer {
+            from: payload.from.clone(),
+            to: payload.to.clone(),
+            amount: parse_units(&payload.amount, decimals).map_err(amount_error)?,
+            fees,
+            nonce: None,
+        };
+
+        let signer = self
+            .chain_signer(
+                &user,
+                chain_data,
+                chain.key_scheme(),
+                &payload.from,
+                &payload.client_share,
+                &payload.password,
+            )
+            .await?;
+
+        self.queue_submission(
+            session,
+            &payload.chain_id,
+            chain_data.clone(),
+            chain,
+            QueuedTransaction::Native(transfer),
+            signer,
+        )
+        .await
+    }
+
+    async fn transaction_status(
+        &self,
+        session: &AuthUser,
+        id: String,
+    ) -> std::result::Result<SuccessResponse<SubmissionStatusResponse>, ErrorResponse> {
+        let Ok(id) = ObjectId::parse_str(&id) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("INVALID_TX_ID!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+        // Another user's submission is reported as missing.
+        match self
+            .submissions
+            .find_one(doc! {"_id": id, "email": &session.email})
             .await
         {
-            Ok(data) => match data {
-                Some(user) => match user.chains.get(&payload.chain_id) {
-                    Some(chain_data) => match chain_data.chain_type {
-                        ChainType::EVM => {
-
-                            println!("{:?}", chain_data.chain_type);
-                            let private_key = format!(
-                                "{}{}{}",
-                                user.private_key_a, user.private_key_b, user.private_key_c
-                            );
-
-                            match Ethereum::send_native(&chain_data, &payload, private_key).await {
-                                Ok(tx_result) => {
-                                    SuccessResponse {
-                                        data: Some(ChainResponse {
-                                            chain: TXChain::EVM(EVMResponse {
-                                                transaction: tx_result.0,
-                                                recepient: tx_result.1,
-                                            }),
-                                        }),
-                                        message: Some(String::from("OK!")),
-                                        status: StatusCode::OK,
-                                    }
-                                },
-                                Err(e) => {
-                                    println!("{:?} HEllo", e);
-                                    return Err(ErrorResponse {
-                                        error: Some(String::from("USER_TX_ERROR!")),
-                                        status: StatusCode::NOT_FOUND,
-                                    });
-                                }
-                            }
-                        }
-                    },
-                    None => {
-                        return Err(ErrorResponse {
-                            error: Some(String::from("USER_CHAIN_DATA_NOT_FOUND!")),
-                            status: StatusCode::NOT_FOUND,
-                        });
-                    }
-                },
-                None => {
+            Ok(Some(submission)) => Ok(SuccessResponse {
+                data: Some(SubmissionStatusResponse {
+                    id: id.to_hex(),
+                    chain_id: submission.chain_id,
+                    from: submission.from,
+                    to: submission.to,
+                    amount: submission.amount,
+                    status: submission.status,
+                    hash: submission.hash,
+                    receipt: submission.receipt,
+                    error: submission.error,
+                }),
+                message: Some(String::from("OK!")),
+                status: StatusCode::OK,
+            }),
+            Ok(None) => Err(ErrorResponse {
+                error: Some(String::from("USER_TX_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::FORBIDDEN,
+            }),
+        }
+    }
+
+    async fn sign_solana_message(
+        &self,
+        session: &AuthUser,
+        payload: SignMessageRequest,
+    ) -> std::result::Result<SuccessResponse<SignMessageResponse>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let chain_data = match user.chains.get(&payload.chain_id) {
+            Some(chain_data) if matches!(chain_data.chain_type, ChainType::SOLANA) => chain_data,
+            _ => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_CHAIN_DATA_NOT_FOUND!")),
+                    status: StatusCode::NOT_FOUND,
+                });
+            }
+        };
+        let Ok(message) = hex::decode(&payload.message) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("INVALID_MESSAGE!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+
+        let key_shares = self
+            .load_eddsa_key_shares(&user, &payload.client_share, &payload.password)
+            .await?;
+        let signer = match EddsaThresholdSigner::new(
+            key_shares,
+            user.eddsa_key.share_threshold,
+            &user.eddsa_key.public_key,
+        ) {
+            Ok(signer) => signer,
+            Err(_) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_KEY_SHARES_INVALID!")),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                });
+            }
+        };
+
+        match tokio::task::spawn_blocking(move || signer.sign(&message)).await {
+            Ok(Ok(signature)) => Ok(SuccessResponse {
+                data: Some(SignMessageResponse {
+                    address: chain_data.address.clone(),
+                    signature: bs58::encode(signature).into_string(),
+                }),
+                message: Some(String::from("OK!")),
+                status: StatusCode::OK,
+            }),
+            _ => Err(ErrorResponse {
+                error: Some(String::from("USER_SIGNING_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    // Prices the transfer at every fee speed so the client can pick one.
+    async fn estimate_fees(
+        &self,
+        session: &AuthUser,
+        payload: FeeEstimateRequest,
+    ) -> std::result::Result<SuccessResponse<Vec<FeeEstimate>>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let Some(chain_data) = user.chains.get(&payload.chain_id) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_CHAIN_DATA_NOT_FOUND!")),
+                status: StatusCode::NOT_FOUND,
+            });
+        };
+        owned_address(chain_data, &payload.from)?;
+        let Some(chain) = self.chains.get(chain_data.chain_type) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_CHAIN_NOT_SUPPORTED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+
+        let native_decimals = self.native_decimals(&payload.chain_id, &chain).await;
+        let decimals = match payload.unit {
+            AmountUnit::Base => 0,
+            AmountUnit::Native => native_decimals,
+        };
+        let mut transfer = NativeTransfer {
+            from: payload.from,
+            to: payload.to,
+            amount: parse_units(&payload.amount, decimals).map_err(amount_error)?,
+            fees: fee_caps(&payload.max_fee_per_gas, &payload.max_priority_fee_per_gas)?,
+            nonce: None,
+        };
+
+        let mut estimates = Vec::new();
+        for speed in [FeeSpeed::Slow, FeeSpeed::Normal, FeeSpeed::Fast] {
+            transfer.fees.speed = speed;
+            let fee = match chain.estimate_fee(chain_data, &transfer).await {
+                Ok(fee) => fee,
+                Err(ChainError::Amount(e)) => return Err(amount_error(e)),
+                Err(e) => {
+                    tracing::warn!(chain_id = %payload.chain_id, error = %e, "fee estimate failed");
                     return Err(ErrorResponse {
-                        error: Some(String::from("USER_NOT_FOUND!")),
-                        status: StatusCode::NOT_FOUND,
+                        error: Some(String::from("FEE_ESTIMATE_ERROR!")),
+                        status: StatusCode::BAD_GATEWAY,
                     });
                 }
-            },
+            };
+            let native_fee = U256::from_dec_str(&fee)
+                .map(|fee| format_units(fee, native_decimals))
+                .unwrap_or_default();
+            estimates.push(FeeEstimate {
+                speed,
+                fee,
+                native_fee,
+            });
+        }
+
+        Ok(SuccessResponse {
+            data: Some(estimates),
+            message: Some(String::from("OK!")),
+            status: StatusCode::OK,
+        })
+    }
+
+    async fn speed_up_transaction(
+        &self,
+        session: &AuthUser,
+        hash: String,
+        payload: ReplaceTransactionRequest,
+    ) -> std::result::Result<SuccessResponse<ReplacementResponse>, ErrorResponse> {
+        self.replace_transaction(session, hash, payload, TransactionKind::SpeedUp)
+            .await
+    }
+
+    // Replaces the transaction with an empty transfer to the sender, which
+    // frees its nonce once mined.
+    async fn cancel_transaction(
+        &self,
+        session: &AuthUser,
+        hash: String,
+        payload: ReplaceTransactionRequest,
+    ) -> std::result::Result<SuccessResponse<ReplacementResponse>, ErrorResponse> {
+        self.replace_transaction(session, hash, payload, TransactionKind::Cancel)
+            .await
+    }
+}
+
+impl Database {
+    // Decimals set in the chain's config, or the chain's own default.
+    async fn native_decimals(&self, chain_id: &str, chain: &Arc<dyn ChainFeatures>) -> u32 {
+        self.wallet_chain_data
+            .find_one(doc! {"chain_id": chain_id})
+            .await
+            .ok()
+            .flatten()
+            .and_then(|config| config.chain_data.decimals)
+            .unwrap_or_else(|| chain.native_decimals())
+    }
+
+    async fn replace_transaction(
+        &self,
+        session: &AuthUser,
+        hash: String,
+        payload: ReplaceTransactionRequest,
+        kind: TransactionKind,
+    ) -> std::result::Result<SuccessResponse<ReplacementResponse>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let chain_data = evm_chain(&user, &payload.chain_id)?;
+        let ethereum = Ethereum;
+
+        let original = match ethereum.transaction(chain_data, &hash).await {
+            Ok(Some(original)) => original,
+            Ok(None) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_TX_NOT_FOUND!")),
+                    status: StatusCode::NOT_FOUND,
+                });
+            }
             Err(e) => {
+                tracing::warn!(chain_id = %payload.chain_id, hash, error = %e, "loading the transaction to replace failed");
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_TX_ERROR!")),
+                    status: StatusCode::BAD_GATEWAY,
+                });
+            }
+        };
+        if original.block_number.is_some() {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_TX_ALREADY_MINED!")),
+                status: StatusCode::CONFLICT,
+            });
+        }
+        let from = format!("{:#x}", original.from);
+        let nonce = original.nonce.as_u64();
+        owned_address(chain_data, &from)?;
+
+        // Fees are bumped from the transaction being replaced, so only the
+        // latest one in the chain can be replaced again.
+        let history = self
+            .nonce_transactions(&payload.chain_id, &from, nonce)
+            .await
+            .map_err(|_| ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::FORBIDDEN,
+            })?;
+        if history
+            .iter()
+            .any(|record| record.replaces.as_deref() == Some(hash.as_str()))
+        {
+            return Err(ErrorResponse {
+                error: Some(String::from("USER_TX_ALREADY_REPLACED!")),
+                status: StatusCode::CONFLICT,
+            });
+        }
+
+        let signer = self
+            .chain_signer(
+                &user,
+                chain_data,
+                KeyScheme::Secp256k1,
+                &from,
+                &payload.client_share,
+                &payload.password,
+            )
+            .await?;
+
+        let sent = async {
+            let transaction = ethereum
+                .build_replacement(
+                    chain_data,
+                    &original,
+                    kind == TransactionKind::Cancel,
+                    &FeeStrategy::new(payload.speed),
+                )
+                .await?;
+            let transaction = ethereum.sign_transaction(transaction, &signer).await?;
+            let replacement = ethereum.broadcast(chain_data, &transaction).await?;
+
+            // Transactions sent before history was kept get their record here.
+            if history.is_empty() {
+                self.record_transaction(
+                    &payload.chain_id,
+                    &from,
+                    nonce,
+                    &hash,
+                    TransactionKind::Original,
+                    None,
+                )
+                .await;
+            }
+            self.record_transaction(
+                &payload.chain_id,
+                &from,
+                nonce,
+                &replacement,
+                kind,
+                Some(&hash),
+            )
+            .await;
+            if let Err(e) = self
+                .nonces
+                .mark_sent(&payload.chain_id, &from, nonce, &replacement)
+                .await
+            {
+                tracing::error!(chain_id = %payload.chain_id, from, nonce, error = %e, "marking the replacement nonce as sent failed");
+            }
+            Ok::<_, ChainError>(replacement)
+        };
+        let replacement = match sent.await {
+            Ok(replacement) => replacement,
+            Err(e) => {
+                tracing::warn!(chain_id = %payload.chain_id, hash, error = %e, "replacing the transaction failed");
+                return Err(ErrorResponse {
+                    error: Some(String::from("USER_TX_ERROR!")),
+                    status: StatusCode::BAD_GATEWAY,
+                });
+            }
+        };
+
+        // The replacement is out, so from here on only the submission worker
+        // waits for the nonce to be mined.
+        let hashes: Vec<String> = history
+            .iter()
+            .map(|record| record.hash.clone())
+            .chain([hash.clone(), replacement.clone()])
+            .collect();
+        let tracked = self
+            .track_replacement(session, &payload.chain_id, &original, &from, nonce, &hashes)
+            .await;
+        let id = match tracked {
+            Ok(Some(id)) => id,
+            Ok(None) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("DATABASE_ERROR!")),
+                    status: StatusCode::FORBIDDEN,
+                });
+            }
+            Err(e) => {
+                tracing::error!(chain_id = %payload.chain_id, hash = replacement, error = %e, "tracking the replacement failed");
                 return Err(ErrorResponse {
                     error: Some(String::from("DATABASE_ERROR!")),
                     status: StatusCode::FORBIDDEN,
                 });
             }
         };
+        Ok(SuccessResponse {
+            data: Some(ReplacementResponse {
+                id: id.to_hex(),
+                status: SubmissionStatus::Broadcast,
+                hash: replacement,
+            }),
+            message: Some(String::from("OK!")),
+            status: StatusCode::ACCEPTED,
+        })
+    }
+
+    // Puts the submission sending `nonce` back under the submission worker,
+    // restarting its drop timeout for the replacement. Transactions sent
+    // without a submission get one here.
+    async fn track_replacement(
+        &self,
+        session: &AuthUser,
+        chain_id: &str,
+        original: &ethers::types::Transaction,
+        from: &str,
+        nonce: u64,
+        hashes: &[String],
+    ) -> Result<Option<ObjectId>, mongodb::error::Error> {
+        let hashes: Vec<&str> = hashes.iter().map(String::as_str).collect();
+        let existing = self
+            .submissions
+            .find_one_and_update(
+                doc! {
+                    "email": &session.email,
+                    "chain_id": chain_id,
+                    "hash": {"$in": hashes.clone()},
+                },
+                doc! {"$set": {
+                    "status": to_bson(&SubmissionStatus::Broadcast)?,
+                    "nonce": nonce as i64,
+                    "broadcast_at": DateTime::now(),
+                }, "$unset": {"error": ""}},
+            )
+            .await?;
+        if let Some(id) = existing.and_then(|submission| submission.id) {
+            return Ok(Some(id));
+        }
+
+        let submission = SubmissionDocument {
+            id: None,
+            email: session.email.clone(),
+            chain_id: chain_id.to_string(),
+            from: from.to_string(),
+            to: original
+                .to
+                .map(|to| format!("{:#x}", to))
+                .unwrap_or_default(),
+            amount: original.value.to_string(),
+            status: SubmissionStatus::Broadcast,
+            hash: hashes.last().map(|hash| hash.to_string()),
+            nonce: Some(nonce as i64),
+            receipt: None,
+            error: None,
+            created_at: DateTime::now(),
+            broadcast_at: Some(DateTime::now()),
+            untracks: None,
+        };
+        let inserted = self.submissions.insert_one(submission).await?;
+        Ok(inserted.inserted_id.as_object_id())
+    }
+
+    // Records a submission and hands it to `submit`. The caller gets the
+    // submission id back right away and follows it with `transaction_status`.
+    // Contract calls record the contract as `to` and no native amount.
+    pub async fn queue_submission(
+        self: Arc<Self>,
+        session: &AuthUser,
+        chain_id: &str,
+        chain_data: ChainInfo,
+        chain: Arc<dyn ChainFeatures>,
+        transaction: QueuedTransaction,
+        signer: ChainSigner,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        let (to, amount, untracks) = match &transaction {
+            QueuedTransaction::Native(transfer) => {
+                (transfer.to.clone(), transfer.amount.to_string(), None)
+            }
+            QueuedTransaction::EvmCall {
+                contract, untracks, ..
+            } => (
+                format!("{:#x}", contract),
+                String::from("0"),
+                untracks.clone(),
+            ),
+        };
+        let submission = SubmissionDocument {
+            id: None,
+            email: session.email.clone(),
+            chain_id: chain_id.to_string(),
+            from: transaction.sender().to_string(),
+            to,
+            amount,
+            status: SubmissionStatus::Queued,
+            hash: None,
+            nonce: None,
+            receipt: None,
+            error: None,
+            created_at: DateTime::now(),
+            broadcast_at: None,
+            untracks,
+        };
+        let Some(id) = self
+            .submissions
+            .insert_one(submission)
+            .await
+            .ok()
+            .and_then(|inserted| inserted.inserted_id.as_object_id())
+        else {
+            return Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::FORBIDDEN,
+            });
+        };
+
+        tokio::spawn(self.submit(
+            id,
+            chain_id.to_string(),
+            chain_data,
+            chain,
+            transaction,
+            signer,
+        ));
+        Ok(SuccessResponse {
+            data: Some(SubmissionResponse {
+                id: id.to_hex(),
+                status: SubmissionStatus::Queued,
+            }),
+            message: Some(String::from("OK!")),
+            status: StatusCode::ACCEPTED,
+        })
+    }
+
+    // Signs and broadcasts a queued submission. From here on the submission
+    // worker follows it.
+    async fn submit(
+        self: Arc<Self>,
+        id: ObjectId,
+        chain_id: String,
+        chain_data: ChainInfo,
+        chain: Arc<dyn ChainFeatures>,
+        transaction: QueuedTransaction,
+        signer: ChainSigner,
+    ) {
+        let from = transaction.sender().to_string();
+        let sent = async {
+            let nonce = self
+                .reserve_nonce(&chain_id, chain.as_ref(), &chain_data, &from)
+                .await?;
+            let broadcast = async {
+                let transaction = match transaction {
+                    QueuedTransaction::Native(mut transfer) => {
+                        transfer.nonce = nonce;
+                        chain.build_transaction(&chain_data, &transfer).await?
+                    }
+                    QueuedTransaction::EvmCall {
+                        from,
+                        contract,
+                        data,
+                        fees,
+                        ..
+                    } => {
+                        Ethereum
+                            .build_call(&chain_data, &from, contract, data, &fees, nonce)
+                            .await?
+                    }
+                };
+                let transaction = chain.sign_transaction(transaction, &signer).await?;
+                chain.broadcast(&chain_data, &transaction).await
+            };
+            self.broadcast_with_nonce(&chain_id, &from, nonce, broadcast)
+                .await
+                .map(|hash| (hash, nonce))
+        };
+        let update = match sent.await {
+            Ok((hash, nonce)) => doc! {"$set": {
+                "status": to_bson(&SubmissionStatus::Broadcast).unwrap_or_default(),
+                "hash": hash,
+                "nonce": nonce.map(|nonce| nonce as i64),
+                "broadcast_at": DateTime::now(),
+            }},
+            Err(e) => {
+                tracing::warn!(submission = %id, chain_id, error = %e, "submission failed");
+                doc! {"$set": {
+                    "status": to_bson(&SubmissionStatus::Failed).unwrap_or_default(),
+                    "error": submission_error(&e),
+                }}
+            }
+        };
+        if let Err(e) = self.submissions.update_one(doc! {"_id": id}, update).await {
+            tracing::error!(submission = %id, error = %e, "updating the submission failed");
+        }
+    }
+
+    // Runs for the lifetime of the server, following every broadcast
+    // submission until it is mined, fails or is dropped.
+    pub async fn track_submissions(self: Arc<Self>) {
+        loop {
+            if let Err(e) = self.check_submissions().await {
+                tracing::error!(error = %e, "checking submissions failed");
+            }
+            tokio::time::sleep(SUBMISSION_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn check_submissions(&self) -> Result<(), mongodb::error::Error> {
+        let signing_deadline = DateTime::from_millis(
+            DateTime::now().timestamp_millis() - SUBMISSION_SIGNING_TIMEOUT.as_millis() as i64,
+        );
+        self.submissions
+            .update_many(
+                doc! {
+                    "status": to_bson(&SubmissionStatus::Queued)?,
+                    "created_at": {"$lt": signing_deadline},
+                },
+                doc! {"$set": {
+                    "status": to_bson(&SubmissionStatus::Failed)?,
+                    "error": "Signing did not finish",
+                }},
+            )
+            .await?;
+
+        let submissions: Vec<SubmissionDocument> = self
+            .submissions
+            .find(doc! {"status": to_bson(&SubmissionStatus::Broadcast)?})
+            .await?
+            .try_collect()
+            .await?;
+        for submission in submissions {
+            let id = submission.id;
+            if let Err(e) = self.check_submission(submission).await {
+                tracing::error!(submission = ?id, error = %e, "checking the submission failed");
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_submission(
+        &self,
+        submission: SubmissionDocument,
+    ) -> Result<(), mongodb::error::Error> {
+        let (Some(id), Some(hash)) = (submission.id, submission.hash.clone()) else {
+            return Ok(());
+        };
+        let chain_data = self
+            .user_wallet
+            .find_one(doc! {"email": &submission.email})
+            .await?
+            .and_then(|user| user.chains.get(&submission.chain_id).cloned());
+        let Some((chain_data, chain)) = chain_data.and_then(|chain_data| {
+            let chain = self.chains.get(chain_data.chain_type)?;
+            Some((chain_data, chain))
+        }) else {
+            return Ok(());
+        };
+
+        // A replacement sent through speed-up or cancel can be the one mined.
+        let nonce = submission.nonce.map(|nonce| nonce as u64);
+        let mut hashes = vec![hash];
+        if let Some(nonce) = nonce {
+            for record in self
+                .nonce_transactions(&submission.chain_id, &submission.from, nonce)
+                .await?
+            {
+                if !hashes.contains(&record.hash) {
+                    hashes.push(record.hash);
+                }
+            }
+        }
+
+        let mut update = None;
+        let mut confirmed = false;
+        for hash in &hashes {
+            match chain.receipt(&chain_data, hash).await {
+                Ok(Some(receipt)) => {
+                    let status = receipt_status(&receipt);
+                    if status == SubmissionStatus::Broadcast {
+                        continue;
+                    }
+                    if let Some(nonce) = nonce {
+                        self.settle_nonce(&submission.chain_id, &submission.from, nonce, hash)
+                            .await;
+                    }
+                    let mut fields = doc! {
+                        "status": to_bson(&status)?,
+                        "hash": hash,
+                        "receipt": to_bson(&receipt)?,
+                    };
+                    if status == SubmissionStatus::Failed {
+                        fields.insert("error", "Transaction reverted");
+                    }
+                    confirmed = status == SubmissionStatus::Confirmed;
+                    update = Some(fields);
+                    break;
+                }
+                Ok(None) => {}
+                Err(ChainError::TransactionFailed(_, reason)) => {
+                    update = Some(doc! {
+                        "status": to_bson(&SubmissionStatus::Failed)?,
+                        "hash": hash,
+                        "error": reason,
+                    });
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!(submission = %id, hash, error = %e, "receipt lookup failed");
+                    return Ok(());
+                }
+            }
+        }
+
+        // Past the chain's receipt timeout, a transaction no node knows about
+        // any more is not coming back.
+        let waited = DateTime::now().timestamp_millis()
+            - submission
+                .broadcast_at
+                .unwrap_or(submission.created_at)
+                .timestamp_millis();
+        if update.is_none() && waited > chain.receipt_timeout().as_millis() as i64 {
+            let mut known = false;
+            for hash in &hashes {
+                match chain.is_known(&chain_data, hash).await {
+                    Ok(is_known) => known |= is_known,
+                    Err(e) => {
+                        tracing::warn!(submission = %id, hash, error = %e, "transaction lookup failed");
+                        return Ok(());
+                    }
+                }
+            }
+            if !known {
+                update = Some(doc! {"status": to_bson(&SubmissionStatus::Dropped)?});
+            }
+        }
+
+        if let Some(update) = update {
+            self.submissions
+                .update_one(doc! {"_id": id}, doc! {"$set": update})
+                .await?;
+            if let Some(nft) = submission.untracks.filter(|_| confirmed) {
+                self.untrack_nft(&submission.email, &submission.chain_id, &nft)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Stops tracking an NFT whose transfer out of the wallet was mined.
+    async fn untrack_nft(
+        &self,
+        email: &str,
+        chain_id: &str,
+        nft: &NonFungibleTokenData,
+    ) -> Result<(), mongodb::error::Error> {
+        self.user_wallet
+            .update_one(
+                doc! {"email": email},
+                doc! {"$pull": {format!("chains.{}.nfts", chain_id): {
+                    "contract": &nft.contract,
+                    "token_id": &nft.token_id,
+                    "owner": &nft.owner,
+                }}},
+            )
+            .await?;
+        Ok(())
+    }
+
+    // Reserves the nonce of `from` on chains that order by account nonce.
+    async fn reserve_nonce(
+        &self,
+        chain_id: &str,
+        chain: &dyn ChainFeatures,
+        chain_data: &ChainInfo,
+        from: &str,
+    ) -> Result<Option<u64>, ChainError> {
+        match chain.account_nonces(chain_data, from).await? {
+            Some(nonces) => Ok(Some(self.nonces.reserve(chain_id, from, nonces).await?)),
+            None => Ok(None),
+        }
+    }
+
+    // Runs the build, sign and broadcast steps of a send holding `nonce`. The
+    // nonce ends up tied to the broadcast hash, or is handed back on failure.
+    async fn broadcast_with_nonce<F>(
+        &self,
+        chain_id: &str,
+        from: &str,
+        nonce: Option<u64>,
+        broadcast: F,
+    ) -> Result<String, ChainError>
+    where
+        F: Future<Output = Result<String, ChainError>> + Send,
+    {
+        let Some(nonce) = nonce else {
+            return broadcast.await;
+        };
+        let sent = broadcast.await;
+        let tracked = match &sent {
+            Ok(hash) => {
+                self.record_transaction(
+                    chain_id,
+                    from,
+                    nonce,
+                    hash,
+                    TransactionKind::Original,
+                    None,
+                )
+                .await;
+                self.nonces.mark_sent(chain_id, from, nonce, hash).await
+            }
+            Err(_) => self.nonces.release(chain_id, from, nonce).await,
+        };
+        if let Err(e) = tracked {
+            tracing::error!(chain_id, from, nonce, error = %e, "updating the nonce reservation failed");
+        }
+        sent
+    }
 
-        Ok(res)
+    // Adds a broadcast transaction to the history of its nonce. The send has
+    // already happened, so failures are only logged.
+    async fn record_transaction(
+        &self,
+        chain_id: &str,
+        from: &str,
+        nonce: u64,
+        hash: &str,
+        kind: TransactionKind,
+        replaces: Option<&str>,
+    ) {
+        let record = TransactionDocument {
+            id: None,
+            chain_id: chain_id.to_string(),
+            from: from.to_lowercase(),
+            nonce: nonce as i64,
+            hash: hash.to_string(),
+            kind,
+            replaces: replaces.map(String::from),
+            status: TransactionStatus::Pending,
+            created_at: DateTime::now(),
+        };
+        if let Err(e) = self.transactions.insert_one(record).await {
+            tracing::error!(chain_id, from, nonce, hash, error = %e, "recording the transaction failed");
+        }
     }
-}
\ No newline at end of file
+
+    // Every recorded transaction using `nonce` of `from`, oldest first.
+    async fn nonce_transactions(
+        &self,
+        chain_id: &str,
+        from: &str,
+        nonce: u64,
+    ) -> Result<Vec<TransactionDocument>, mongodb::error::Error> {
+        self.transactions
+            .find(doc! {"chain_id": chain_id, "from": from.to_lowercase(), "nonce": nonce as i64})
+            .sort(doc! {"created_at": 1})
+            .await?
+            .try_collect()
+            .await
+    }
+
+    // Marks `hash` as the transaction that used `nonce` and the rest of its
+    // replacement chain as replaced.
+    async fn settle_nonce(&self, chain_id: &str, from: &str, nonce: u64, hash: &str) {
+        let nonce_filter =
+            doc! {"chain_id": chain_id, "from": from.to_lowercase(), "nonce": nonce as i64};
+        let mut replaced = nonce_filter.clone();
+        replaced.insert("hash", doc! {"$ne": hash});
+        let mut confirmed = nonce_filter;
+        confirmed.insert("hash", hash);
+
+        let settled = async {
+            self.transactions
+                .update_many(
+                    replaced,
+                    doc! {"$set": {"status": to_bson(&TransactionStatus::Replaced)?}},
+                )
+                .await?;
+            self.transactions
+                .update_one(
+                    confirmed,
+                    doc! {"$set": {"status": to_bson(&TransactionStatus::Confirmed)?}},
+                )
+                .await?;
+            Ok::<_, mongodb::error::Error>(())
+        };
+        if let Err(e) = settled.await {
+            tracing::error!(chain_id, from, nonce, hash, error = %e, "settling the nonce history failed");
+        }
+    }
+
+    // Queues a contract call from `from` the same way as a native transfer.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_evm_call(
+        self: Arc<Self>,
+        session: &AuthUser,
+        chain_id: &str,
+        chain_data: &ChainInfo,
+        from: &str,
+        contract: Address,
+        data: Bytes,
+        fees: FeeStrategy,
+        untracks: Option<NonFungibleTokenData>,
+        signer: ChainSigner,
+    ) -> std::result::Result<SuccessResponse<SubmissionResponse>, ErrorResponse> {
+        let transaction = QueuedTransaction::EvmCall {
+            from: from.to_string(),
+            contract,
+            data,
+            fees,
+            untracks,
+        };
+        self.queue_submission(
+            session,
+            chain_id,
+            chain_data.clone(),
+            Arc::new(Ethereum),
+            transaction,
+            signer,
+        )
+        .await
+    }
+}
+
+// The reason stored on a failed submission, which its owner can read. Node and
+// database errors can carry endpoints and internals, so those are only logged.
+fn submission_error(e: &ChainError) -> String {
+    match e {
+        ChainError::Provider(_)
+        | ChainError::Request(_)
+        | ChainError::Rpc(_)
+        | ChainError::Nonce(_)
+        | ChainError::Bitcoin(BitcoinError::BackendError(_)) => {
+            String::from("Chain node or database unavailable")
+        }
+        e => e.to_string(),
+    }
+}
+
+// Reverted EVM calls are mined but failed, and Bitcoin reports transactions
+// still in the mempool.
+fn receipt_status(receipt: &TXChain) -> SubmissionStatus {
+    match receipt {
+        TXChain::EVM(response) if response.recepient.status == Some(0.into()) => {
+            SubmissionStatus::Failed
+        }
+        TXChain::BITCOIN(receipt) if !receipt.confirmed => SubmissionStatus::Broadcast,
+        _ => SubmissionStatus::Confirmed,
+    }
+}
+
+// Parses the optional fee caps, given in gwei, into a normal speed strategy.
+pub fn fee_caps(
+    max_fee_per_gas: &Option<String>,
+    max_priority_fee_per_gas: &Option<String>,
+) -> std::result::Result<FeeStrategy, ErrorResponse> {
+    let parse_gwei = |fee: &Option<String>| {
+        fee.as_deref()
+            .map(|fee| parse_units(fee, GWEI_DECIMALS))
+            .transpose()
+            .map_err(amount_error)
+    };
+    Ok(FeeStrategy {
+        max_fee_per_gas: parse_gwei(max_fee_per_gas)?,
+        max_priority_fee_per_gas: parse_gwei(max_priority_fee_per_gas)?,
+        ..Default::default()
+    })
+}
+
+pub fn amount_error(e: AmountError) -> ErrorResponse {
+    let error = match e {
+        AmountError::InvalidAmount(_) => "INVALID_AMOUNT!",
+        AmountError::TooManyDecimals(_) => "AMOUNT_TOO_PRECISE!",
+        AmountError::Overflow => "AMOUNT_OVERFLOW!",
+    };
+    ErrorResponse {
+        error: Some(String::from(error)),
+        status: StatusCode::BAD_REQUEST,
+    }
+}
+
+// Contract calls (tokens, NFTs) only exist on EVM chains.
+pub fn evm_chain<'a>(
+    user: &'a UserWalletSchema,
+    chain_id: &str,
+) -> std::result::Result<&'a ChainInfo, ErrorResponse> {
+    match user.chains.get(chain_id) {
+        Some(chain_data) if chain_data.chain_type == ChainType::EVM => Ok(chain_data),
+        Some(_) => Err(ErrorResponse {
+            error: Some(String::from("EVM_CHAIN_REQUIRED!")),
+            status: StatusCode::BAD_REQUEST,
+        }),
+        None => Err(ErrorResponse {
+            error: Some(String::from("USER_CHAIN_DATA_NOT_FOUND!")),
+            status: StatusCode::NOT_FOUND,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_errors_are_not_shown_on_submissions() {
+        let rpc = ChainError::Rpc(String::from("https://node.example/key-123 timed out"));
+        let backend = ChainError::Bitcoin(BitcoinError::BackendError(String::from("502")));
+
+        assert_eq!(submission_error(&rpc), "Chain node or database unavailable");
+        assert_eq!(
+            submission_error(&backend),
+            "Chain node or database unavailable"
+        );
+        assert_eq!(
+            submission_error(&ChainError::WrongSigner),
+            ChainError::WrongSigner.to_string()
+        );
+    }
+}
@@ -0,0 +1,754 @@
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use bcrypt::verify;
+use mongodb::bson::{DateTime, doc, to_bson};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    errors::two_factor_errors::TwoFactorError,
+    models::{
+        user_wallet_model::{PasskeyCredential, TotpSettings, UserWalletSchema},
+        webauthn_model::WebauthnCeremony,
+    },
+    services::{
+        database::Database,
+        session_services::STEP_UP_TOKEN_TTL,
+        totp_services::{self, generate_secret, otpauth_uri, recovery_codes, verify_totp},
+        webauthn_services::{CEREMONY_TIMEOUT, COSE_ALG_ES256},
+    },
+};
+
+use super::auth_handler::AuthUser;
+use super::response_handler::{ErrorResponse, SuccessResponse};
+
+pub const TOTP_CODE_HEADER: &str = "x-totp-code";
+pub const STEP_UP_TOKEN_HEADER: &str = "x-step-up-token";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TotpEnrollRequest {
+    pub password: String,
+}
+
+// `otpauth_uri` is meant for a QR code, `secret` for typing into the app.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TotpConfirmRequest {
+    pub code: String,
+}
+
+// Shown once, only their hashes are kept.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+// Either a code from the authenticator app or one of the recovery codes.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StepUpRequest {
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StepUpToken {
+    pub step_up_token: String,
+    pub expires_in: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasskeyRegisterRequest {
+    pub password: String,
+}
+
+// Options for `navigator.credentials.create()`, in the JSON form taken by
+// `PublicKeyCredential.parseCreationOptionsFromJSON()`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyCreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: PasskeyUser,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub timeout: u64,
+    pub attestation: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUser {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub alg: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+// Options for `navigator.credentials.get()`, in the JSON form taken by
+// `PublicKeyCredential.parseRequestOptionsFromJSON()`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: String,
+    pub timeout: u64,
+}
+
+// The browser's answer to the creation options. Binary fields are base64url.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasskeyRegistration {
+    pub id: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+    pub name: Option<String>,
+}
+
+// The browser's answer to the request options. Binary fields are base64url.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasskeyAssertion {
+    pub id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasskeyInfo {
+    pub credential_id: String,
+    pub name: String,
+}
+
+// Guards the routes that sign with the user's key. Once the user enabled TOTP
+// or registered a passkey, the request needs a step-up token in
+// `x-step-up-token`, or with TOTP a current code in `x-totp-code`. Must run
+// behind the `authenticate` or `authorize` layer.
+pub struct SecondFactor;
+
+impl<S> FromRequestParts<S> for SecondFactor
+where
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let (Some(db), Some(session)) = (
+            parts.extensions.get::<Arc<Database>>(),
+            parts.extensions.get::<AuthUser>(),
+        ) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("SERVER_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            });
+        };
+        // API keys can't answer a second factor, so they skip it. This is a
+        // deliberate trade-off: keys are only issued behind a second factor,
+        // and the ones that can move funds expire within
+        // `MAX_TRANSFER_KEY_DAYS` and can be revoked at any time.
+        if session.scopes.is_some() {
+            return Ok(SecondFactor);
+        }
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        db.verify_second_factor(
+            session,
+            header(TOTP_CODE_HEADER),
+            header(STEP_UP_TOKEN_HEADER),
+        )
+        .await?;
+        Ok(SecondFactor)
+    }
+}
+
+#[async_trait]
+pub trait UserTwoFactorServices {
+    async fn enroll_totp(
+        &self,
+        session: &AuthUser,
+        payload: TotpEnrollRequest,
+    ) -> std::result::Result<SuccessResponse<TotpEnrollment>, ErrorResponse>;
+
+    async fn confirm_totp(
+        &self,
+        session: &AuthUser,
+        payload: TotpConfirmRequest,
+    ) -> std::result::Result<SuccessResponse<RecoveryCodes>, ErrorResponse>;
+
+    async fn step_up(
+        &self,
+        session: &AuthUser,
+        payload: StepUpRequest,
+    ) -> std::result::Result<SuccessResponse<StepUpToken>, ErrorResponse>;
+
+    async fn start_passkey_registration(
+        &self,
+        session: &AuthUser,
+        payload: PasskeyRegisterRequest,
+    ) -> std::result::Result<SuccessResponse<PasskeyCreationOptions>, ErrorResponse>;
+
+    async fn finish_passkey_registration(
+        &self,
+        session: &AuthUser,
+        payload: PasskeyRegistration,
+    ) -> std::result::Result<SuccessResponse<PasskeyInfo>, ErrorResponse>;
+
+    async fn start_passkey_assertion(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<PasskeyRequestOptions>, ErrorResponse>;
+
+    async fn finish_passkey_assertion(
+        &self,
+        session: &AuthUser,
+        payload: PasskeyAssertion,
+    ) -> std::result::Result<SuccessResponse<StepUpToken>, ErrorResponse>;
+}
+
+#[async_trait]
+impl UserTwoFactorServices for Database {
+    // Starts TOTP enrollment with a new secret. Nothing is enforced until the
+    // secret is confirmed, enrolling again before that replaces it.
+    async fn enroll_totp(
+        &self,
+        session: &AuthUser,
+        payload: TotpEnrollRequest,
+    ) -> std::result::Result<SuccessResponse<TotpEnrollment>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        if totp_enabled(&user) {
+            return Err(ErrorResponse {
+                error: Some(String::from("TOTP_ALREADY_ENABLED!")),
+                status: StatusCode::CONFLICT,
+            });
+        }
+        let secret = generate_secret().map_err(two_factor_error)?;
+        let settings = TotpSettings {
+            secret: secret.clone(),
+            confirmed: false,
+            last_step: 0,
+            recovery_codes: Vec::new(),
+        };
+        let Ok(settings) = to_bson(&settings) else {
+            return Err(two_factor_error("TOTP settings are not valid BSON"));
+        };
+
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, "totp.confirmed": {"$ne": true}},
+                doc! {"$set": {"totp": settings}},
+            )
+            .await
+        {
+            Ok(updated) if updated.matched_count == 1 => Ok(SuccessResponse {
+                data: Some(TotpEnrollment {
+                    otpauth_uri: otpauth_uri(&user.email, &secret),
+                    secret,
+                }),
+                message: Some(String::from("TOTP ENROLLMENT STARTED")),
+                status: StatusCode::OK,
+            }),
+            Ok(_) => Err(ErrorResponse {
+                error: Some(String::from("TOTP_ALREADY_ENABLED!")),
+                status: StatusCode::CONFLICT,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    // Enables TOTP once the app shows a matching code, and hands out the
+    // recovery codes.
+    async fn confirm_totp(
+        &self,
+        session: &AuthUser,
+        payload: TotpConfirmRequest,
+    ) -> std::result::Result<SuccessResponse<RecoveryCodes>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let Some(totp) = user.totp.as_ref().filter(|totp| !totp.confirmed) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("TOTP_NOT_ENROLLED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+        let Some(step) = verify_totp(
+            &totp.secret,
+            &payload.code,
+            totp.last_step,
+            totp_services::unix_time(),
+        )
+        .map_err(two_factor_error)?
+        else {
+            return Err(invalid_second_factor());
+        };
+        let (codes, hashes) = recovery_codes().map_err(two_factor_error)?;
+
+        // Only the secret the code was checked against is confirmed.
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, "totp.secret": &totp.secret, "totp.confirmed": false},
+                doc! {"$set": {
+                    "totp.confirmed": true,
+                    "totp.last_step": step,
+                    "totp.recovery_codes": hashes,
+                }},
+            )
+            .await
+        {
+            Ok(updated) if updated.matched_count == 1 => Ok(SuccessResponse {
+                data: Some(RecoveryCodes {
+                    recovery_codes: codes,
+                }),
+                message: Some(String::from("TOTP ENABLED")),
+                status: StatusCode::OK,
+            }),
+            Ok(_) => Err(ErrorResponse {
+                error: Some(String::from("TOTP_NOT_ENROLLED!")),
+                status: StatusCode::CONFLICT,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    // Trades a code for a short-lived token, so a client can sign several
+    // times without asking for a new code each time.
+    async fn step_up(
+        &self,
+        session: &AuthUser,
+        payload: StepUpRequest,
+    ) -> std::result::Result<SuccessResponse<StepUpToken>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        if !totp_enabled(&user) {
+            return Err(ErrorResponse {
+                error: Some(String::from("TOTP_NOT_ENABLED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+        let accepted = match (&payload.code, &payload.recovery_code) {
+            (Some(code), _) => self.accept_totp_code(&user, code).await?,
+            (None, Some(recovery_code)) => self.accept_recovery_code(&user, recovery_code).await?,
+            (None, None) => false,
+        };
+        if !accepted {
+            return Err(invalid_second_factor());
+        }
+        self.step_up_token(session)
+    }
+
+    async fn start_passkey_registration(
+        &self,
+        session: &AuthUser,
+        payload: PasskeyRegisterRequest,
+    ) -> std::result::Result<SuccessResponse<PasskeyCreationOptions>, ErrorResponse> {
+        let user = self
+            .authenticate_session(session, &payload.password)
+            .await?;
+        let challenge = self
+            .webauthn
+            .challenge(&session.user_id, WebauthnCeremony::Registration)
+            .await
+            .map_err(passkey_error)?;
+
+        Ok(SuccessResponse {
+            data: Some(PasskeyCreationOptions {
+                challenge,
+                rp: RelyingParty {
+                    id: self.webauthn.rp_id().to_string(),
+                    name: self.webauthn.rp_name().to_string(),
+                },
+                user: PasskeyUser {
+                    id: URL_SAFE_NO_PAD.encode(&session.user_id),
+                    name: user.email.clone(),
+                    display_name: user.email,
+                },
+                pub_key_cred_params: vec![CredentialParameters {
+                    kind: String::from("public-key"),
+                    alg: COSE_ALG_ES256 as i64,
+                }],
+                exclude_credentials: credential_descriptors(&user.passkeys),
+                timeout: CEREMONY_TIMEOUT.as_millis() as u64,
+                attestation: String::from("none"),
+            }),
+            message: Some(String::from("OK!")),
+            status: StatusCode::OK,
+        })
+    }
+
+    async fn finish_passkey_registration(
+        &self,
+        session: &AuthUser,
+        payload: PasskeyRegistration,
+    ) -> std::result::Result<SuccessResponse<PasskeyInfo>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let (Some(client_data_json), Some(attestation_object)) = (
+            decode_base64url(&payload.client_data_json),
+            decode_base64url(&payload.attestation_object),
+        ) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("PASSKEY_RESPONSE_INVALID!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+        let registered = self
+            .webauthn
+            .verify_registration(&session.user_id, &client_data_json, &attestation_object)
+            .await
+            .map_err(passkey_error)?;
+        if registered.credential_id != payload.id.trim_end_matches('=') {
+            return Err(ErrorResponse {
+                error: Some(String::from("PASSKEY_RESPONSE_INVALID!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+
+        let passkey = PasskeyCredential {
+            credential_id: registered.credential_id,
+            public_key: registered.public_key,
+            sign_count: registered.sign_count as i64,
+            name: payload
+                .name
+                .unwrap_or_else(|| format!("Passkey {}", user.passkeys.len() + 1)),
+            created_at: DateTime::now(),
+        };
+        let Ok(passkey_document) = to_bson(&passkey) else {
+            return Err(two_factor_error("Passkey is not valid BSON"));
+        };
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, "passkeys.credential_id": {"$ne": &passkey.credential_id}},
+                doc! {"$push": {"passkeys": passkey_document}},
+            )
+            .await
+        {
+            Ok(updated) if updated.matched_count == 1 => Ok(SuccessResponse {
+                data: Some(PasskeyInfo {
+                    credential_id: passkey.credential_id,
+                    name: passkey.name,
+                }),
+                message: Some(String::from("PASSKEY REGISTERED")),
+                status: StatusCode::OK,
+            }),
+            Ok(_) => Err(ErrorResponse {
+                error: Some(String::from("PASSKEY_ALREADY_REGISTERED!")),
+                status: StatusCode::CONFLICT,
+            }),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    async fn start_passkey_assertion(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<PasskeyRequestOptions>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        if user.passkeys.is_empty() {
+            return Err(ErrorResponse {
+                error: Some(String::from("PASSKEY_NOT_REGISTERED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        }
+        let challenge = self
+            .webauthn
+            .challenge(&session.user_id, WebauthnCeremony::Authentication)
+            .await
+            .map_err(passkey_error)?;
+
+        Ok(SuccessResponse {
+            data: Some(PasskeyRequestOptions {
+                challenge,
+                rp_id: self.webauthn.rp_id().to_string(),
+                allow_credentials: credential_descriptors(&user.passkeys),
+                user_verification: String::from("preferred"),
+                timeout: CEREMONY_TIMEOUT.as_millis() as u64,
+            }),
+            message: Some(String::from("OK!")),
+            status: StatusCode::OK,
+        })
+    }
+
+    // A verified assertion is traded for a step-up token, the same one TOTP
+    // codes give.
+    async fn finish_passkey_assertion(
+        &self,
+        session: &AuthUser,
+        payload: PasskeyAssertion,
+    ) -> std::result::Result<SuccessResponse<StepUpToken>, ErrorResponse> {
+        let user = self.session_user(session).await?;
+        let credential_id = payload.id.trim_end_matches('=');
+        let Some(passkey) = user
+            .passkeys
+            .iter()
+            .find(|passkey| passkey.credential_id == credential_id)
+        else {
+            return Err(ErrorResponse {
+                error: Some(String::from("PASSKEY_NOT_REGISTERED!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+        let (Some(client_data_json), Some(authenticator_data), Some(signature)) = (
+            decode_base64url(&payload.client_data_json),
+            decode_base64url(&payload.authenticator_data),
+            decode_base64url(&payload.signature),
+        ) else {
+            return Err(ErrorResponse {
+                error: Some(String::from("PASSKEY_RESPONSE_INVALID!")),
+                status: StatusCode::BAD_REQUEST,
+            });
+        };
+        let sign_count = self
+            .webauthn
+            .verify_assertion(
+                &session.user_id,
+                passkey,
+                &client_data_json,
+                &authenticator_data,
+                &signature,
+            )
+            .await
+            .map_err(passkey_error)?;
+
+        // The counter only moves from the value the assertion was checked
+        // against, so of two assertions racing each other one fails.
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, "passkeys": {"$elemMatch": {
+                    "credential_id": &passkey.credential_id,
+                    "sign_count": passkey.sign_count,
+                }}},
+                doc! {"$set": {"passkeys.$.sign_count": sign_count as i64}},
+            )
+            .await
+        {
+            Ok(updated) if updated.matched_count == 1 => self.step_up_token(session),
+            Ok(_) => Err(invalid_second_factor()),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+}
+
+impl Database {
+    fn step_up_token(
+        &self,
+        session: &AuthUser,
+    ) -> std::result::Result<SuccessResponse<StepUpToken>, ErrorResponse> {
+        match self.sessions.step_up_token(&session.user_id) {
+            Ok(step_up_token) => Ok(SuccessResponse {
+                data: Some(StepUpToken {
+                    step_up_token,
+                    expires_in: STEP_UP_TOKEN_TTL.as_secs(),
+                }),
+                message: Some(String::from("OK!")),
+                status: StatusCode::OK,
+            }),
+            Err(e) => Err(two_factor_error(e)),
+        }
+    }
+
+    // Passes users without a second factor. Everyone else needs a step-up
+    // token or a TOTP code that wasn't used before.
+    pub async fn verify_second_factor(
+        &self,
+        session: &AuthUser,
+        code: Option<&str>,
+        step_up_token: Option<&str>,
+    ) -> std::result::Result<(), ErrorResponse> {
+        let user = self.session_user(session).await?;
+        if !totp_enabled(&user) && user.passkeys.is_empty() {
+            return Ok(());
+        }
+        let accepted = match (step_up_token, code) {
+            (Some(token), _) => self
+                .sessions
+                .verify_step_up_token(token, &session.user_id)
+                .is_ok(),
+            (None, Some(code)) => self.accept_totp_code(&user, code).await?,
+            (None, None) => {
+                return Err(ErrorResponse {
+                    error: Some(String::from("SECOND_FACTOR_REQUIRED!")),
+                    status: StatusCode::FORBIDDEN,
+                });
+            }
+        };
+        match accepted {
+            true => Ok(()),
+            false => Err(invalid_second_factor()),
+        }
+    }
+
+    // Accepts `code` at most once. The last step only moves forward, so a
+    // request racing us with the same code finds it already taken.
+    async fn accept_totp_code(
+        &self,
+        user: &UserWalletSchema,
+        code: &str,
+    ) -> std::result::Result<bool, ErrorResponse> {
+        let Some(totp) = user.totp.as_ref().filter(|totp| totp.confirmed) else {
+            return Ok(false);
+        };
+        let Some(step) = verify_totp(
+            &totp.secret,
+            code,
+            totp.last_step,
+            totp_services::unix_time(),
+        )
+        .map_err(two_factor_error)?
+        else {
+            return Ok(false);
+        };
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, "totp.last_step": {"$lt": step}},
+                doc! {"$set": {"totp.last_step": step}},
+            )
+            .await
+        {
+            Ok(updated) => Ok(updated.modified_count == 1),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+
+    // Removes the matching recovery code, which fails if another request used
+    // it first.
+    async fn accept_recovery_code(
+        &self,
+        user: &UserWalletSchema,
+        recovery_code: &str,
+    ) -> std::result::Result<bool, ErrorResponse> {
+        let Some(totp) = &user.totp else {
+            return Ok(false);
+        };
+        let recovery_code = recovery_code.trim().to_lowercase();
+        let Some(code_hash) = totp
+            .recovery_codes
+            .iter()
+            .find(|code_hash| verify(&recovery_code, code_hash).unwrap_or(false))
+        else {
+            return Ok(false);
+        };
+        match self
+            .user_wallet
+            .update_one(
+                doc! {"_id": user.id, "totp.recovery_codes": code_hash},
+                doc! {"$pull": {"totp.recovery_codes": code_hash}},
+            )
+            .await
+        {
+            Ok(updated) => Ok(updated.modified_count == 1),
+            Err(_) => Err(ErrorResponse {
+                error: Some(String::from("DATABASE_ERROR!")),
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+            }),
+        }
+    }
+}
+
+fn totp_enabled(user: &UserWalletSchema) -> bool {
+    user.totp.as_ref().is_some_and(|totp| totp.confirmed)
+}
+
+fn credential_descriptors(passkeys: &[PasskeyCredential]) -> Vec<CredentialDescriptor> {
+    passkeys
+        .iter()
+        .map(|passkey| CredentialDescriptor {
+            kind: String::from("public-key"),
+            id: passkey.credential_id.clone(),
+        })
+        .collect()
+}
+
+// Browsers differ on padding, so both forms are accepted.
+fn decode_base64url(value: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok()
+}
+
+fn invalid_second_factor() -> ErrorResponse {
+    ErrorResponse {
+        error: Some(String::from("INVALID_SECOND_FACTOR!")),
+        status: StatusCode::FORBIDDEN,
+    }
+}
+
+fn two_factor_error(e: impl std::fmt::Debug) -> ErrorResponse {
+    tracing::error!(error = ?e, "second factor check failed");
+    ErrorResponse {
+        error: Some(String::from("TWO_FACTOR_ERROR!")),
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn passkey_error(e: TwoFactorError) -> ErrorResponse {
+    match e {
+        TwoFactorError::InvalidChallenge => ErrorResponse {
+            error: Some(String::from("PASSKEY_CHALLENGE_INVALID!")),
+            status: StatusCode::BAD_REQUEST,
+        },
+        TwoFactorError::InvalidSignature | TwoFactorError::CounterRegression(..) => {
+            tracing::warn!(error = %e, "passkey assertion rejected");
+            invalid_second_factor()
+        }
+        TwoFactorError::MalformedResponse(_)
+        | TwoFactorError::WrongOrigin(_)
+        | TwoFactorError::WrongRelyingParty
+        | TwoFactorError::UserNotPresent
+        | TwoFactorError::UnsupportedAlgorithm => {
+            tracing::warn!(error = %e, "malformed passkey response");
+            ErrorResponse {
+                error: Some(String::from("PASSKEY_RESPONSE_INVALID!")),
+                status: StatusCode::BAD_REQUEST,
+            }
+        }
+        _ => two_factor_error(e),
+    }
+}
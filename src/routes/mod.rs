@@ -1,4 +1,9 @@
 pub mod chain;
 pub mod auth;
 pub mod transaction;
+pub mod token;
+pub mod nft;
+pub mod two_factor;
+pub mod api_key;
+pub mod organization;
 pub mod handler;
\ No newline at end of file
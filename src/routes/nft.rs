@@ -0,0 +1,83 @@
+use axum::{Extension, Json, Router, middleware, response::IntoResponse, routing::post};
+use std::sync::Arc;
+
+use crate::models::api_key_model::ApiKeyScope;
+use crate::routes::handler::auth_handler::{AuthUser, authenticate, authorize};
+use crate::routes::handler::nft_handler::{
+    NftBatchTransferRequest, NftListRequest, NftRequest, NftTransferRequest, UserNftServices,
+};
+use crate::routes::handler::two_factor_handler::SecondFactor;
+use crate::services::database::Database;
+
+pub fn nft_routes() -> Router {
+    let reads = Router::new()
+        .route(
+            "/user/nft/list",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<NftListRequest>| async move {
+                    match db.list_nfts(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            ApiKeyScope::ReadBalances,
+            authorize,
+        ));
+
+    let transfers = Router::new()
+        .route(
+            "/user/nft/transfer",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<NftTransferRequest>| async move {
+                    match db.transfer_nft(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/nft/transfer/batch",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<NftBatchTransferRequest>| async move {
+                    match db.batch_transfer_nfts(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            ApiKeyScope::Transfer,
+            authorize,
+        ));
+
+    Router::new()
+        .route(
+            "/user/nft/add",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<NftRequest>| async move {
+                    match db.add_nft(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn(authenticate))
+        .merge(reads)
+        .merge(transfers)
+}
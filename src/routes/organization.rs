@@ -0,0 +1,70 @@
+use axum::{
+    Extension, Json, Router,
+    extract::Path,
+    middleware,
+    response::IntoResponse,
+    routing::{delete, post},
+};
+use std::sync::Arc;
+
+use crate::routes::handler::auth_handler::{AuthUser, authenticate};
+use crate::routes::handler::organization_handler::{
+    OrganizationMemberRequest, OrganizationRequest, UserOrganizationServices,
+};
+use crate::services::database::Database;
+
+// Organizations are managed from a user session only. Their keys are issued and
+// revoked through the API key routes.
+pub fn organization_routes() -> Router {
+    Router::new()
+        .route(
+            "/user/organizations",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<OrganizationRequest>| async move {
+                    match db.create_organization(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            )
+            .get(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>| async move {
+                    match db.list_organizations(&session).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/organizations/{id}/members",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Path(id): Path<String>,
+                 Json(payload): Json<OrganizationMemberRequest>| async move {
+                    match db.add_organization_member(&session, id, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/organizations/{id}/members/{member_id}",
+            delete(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Path((id, member_id)): Path<(String, String)>| async move {
+                    match db.remove_organization_member(&session, id, member_id).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn(authenticate))
+}
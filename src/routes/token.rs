@@ -0,0 +1,83 @@
+use axum::{Extension, Json, Router, middleware, response::IntoResponse, routing::post};
+use std::sync::Arc;
+
+use crate::models::api_key_model::ApiKeyScope;
+use crate::routes::handler::auth_handler::{AuthUser, authenticate, authorize};
+use crate::routes::handler::token_handler::{
+    TokenBalanceRequest, TokenRequest, TokenTransferRequest, UserTokenServices,
+};
+use crate::routes::handler::two_factor_handler::SecondFactor;
+use crate::services::database::Database;
+
+pub fn token_routes() -> Router {
+    let reads = Router::new()
+        .route(
+            "/user/token/balance",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<TokenBalanceRequest>| async move {
+                    match db.token_balance(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            ApiKeyScope::ReadBalances,
+            authorize,
+        ));
+
+    let transfers = Router::new()
+        .route(
+            "/user/token/transfer",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<TokenTransferRequest>| async move {
+                    match db.transfer_token(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/token/approve",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<TokenTransferRequest>| async move {
+                    match db.approve_token(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            ApiKeyScope::Transfer,
+            authorize,
+        ));
+
+    Router::new()
+        .route(
+            "/user/token/add",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<TokenRequest>| async move {
+                    match db.add_token(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn(authenticate))
+        .merge(reads)
+        .merge(transfers)
+}
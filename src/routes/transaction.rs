@@ -1,24 +1,160 @@
 use axum::{
     Extension, Json, Router,
+    extract::Path,
+    middleware,
     response::IntoResponse,
     routing::{get, post},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::routes::handler::transaction_handler::{Transaction, UserTransactionServices};
+use crate::models::api_key_model::ApiKeyScope;
+use crate::routes::handler::auth_handler::{AuthUser, authenticate, authorize};
+use crate::routes::handler::key_handler::{
+    KeyRecoveryRequest, KeyRefreshRequest, KeyReshareRequest, UserKeyServices,
+};
+use crate::routes::handler::transaction_handler::{
+    FeeEstimateRequest, ReplaceTransactionRequest, SignMessageRequest, Transaction,
+    UserTransactionServices,
+};
+use crate::routes::handler::two_factor_handler::SecondFactor;
 use crate::services::database::Database;
 
 pub fn transaction_routes() -> Router {
-    Router::new().route(
-        "/user/native/transfer",
-        post(
-            |Extension(db): Extension<Arc<Database>>, Json(payload): Json<Transaction>| async move {
-                match db.send_native_funds(payload).await {
-                    Ok(success) => success.into_response(),
-                    Err(error) => error.into_response(),
-                }
-            },
-        ),
-    )
+    // API keys with the transfer scope can send and follow transactions.
+    let transfers = Router::new()
+        .route(
+            "/user/native/transfer",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<Transaction>| async move {
+                    match db.send_native_funds(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/tx/{id}",
+            get(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Path(id): Path<String>| async move {
+                    match db.transaction_status(&session, id).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/tx/{hash}/speedup",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Path(hash): Path<String>,
+                 Json(payload): Json<ReplaceTransactionRequest>| async move {
+                    match db.speed_up_transaction(&session, hash, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/tx/{hash}/cancel",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Path(hash): Path<String>,
+                 Json(payload): Json<ReplaceTransactionRequest>| async move {
+                    match db.cancel_transaction(&session, hash, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/fees/estimate",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<FeeEstimateRequest>| async move {
+                    match db.estimate_fees(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/solana/message/sign",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<SignMessageRequest>| async move {
+                    match db.sign_solana_message(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            ApiKeyScope::Transfer,
+            authorize,
+        ));
+
+    Router::new()
+        .route(
+            "/user/key/refresh",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<KeyRefreshRequest>| async move {
+                    match db.refresh_key_shares(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/key/reshare",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<KeyReshareRequest>| async move {
+                    match db.reshare_key_shares(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/key/recover",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<KeyRecoveryRequest>| async move {
+                    match db.recover_key_shares(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn(authenticate))
+        .merge(transfers)
 }
@@ -0,0 +1,108 @@
+use axum::{Extension, Json, Router, middleware, response::IntoResponse, routing::post};
+use std::sync::Arc;
+
+use crate::routes::handler::auth_handler::{AuthUser, authenticate};
+use crate::routes::handler::two_factor_handler::{
+    PasskeyAssertion, PasskeyRegisterRequest, PasskeyRegistration, SecondFactor, StepUpRequest,
+    TotpConfirmRequest, TotpEnrollRequest, UserTwoFactorServices,
+};
+use crate::services::database::Database;
+
+pub fn two_factor_routes() -> Router {
+    // Adding a factor needs one of the existing ones, so a stolen password
+    // can't be used to enroll a new one.
+    Router::new()
+        .route(
+            "/user/2fa/totp/enroll",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<TotpEnrollRequest>| async move {
+                    match db.enroll_totp(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/2fa/totp/confirm",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<TotpConfirmRequest>| async move {
+                    match db.confirm_totp(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/2fa/step-up",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<StepUpRequest>| async move {
+                    match db.step_up(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/2fa/passkey/register/start",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 SecondFactor,
+                 Json(payload): Json<PasskeyRegisterRequest>| async move {
+                    match db.start_passkey_registration(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/2fa/passkey/register/finish",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<PasskeyRegistration>| async move {
+                    match db.finish_passkey_registration(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/2fa/passkey/assert/start",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>| async move {
+                    match db.start_passkey_assertion(&session).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route(
+            "/user/2fa/passkey/assert/finish",
+            post(
+                |Extension(db): Extension<Arc<Database>>,
+                 Extension(session): Extension<AuthUser>,
+                 Json(payload): Json<PasskeyAssertion>| async move {
+                    match db.finish_passkey_assertion(&session, payload).await {
+                        Ok(success) => success.into_response(),
+                        Err(error) => error.into_response(),
+                    }
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn(authenticate))
+}
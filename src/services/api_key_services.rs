@@ -0,0 +1,137 @@
+use futures::TryStreamExt;
+use mongodb::{
+    Collection,
+    bson::{Bson, DateTime, Document, doc, oid::ObjectId},
+    options::ReturnDocument,
+};
+use rand::{TryRngCore, rngs::OsRng};
+use sha2::{Digest, Sha256};
+
+use crate::errors::api_key_errors::ApiKeyError;
+use crate::models::api_key_model::{ApiKeyDocument, ApiKeyScope};
+
+// Every key starts with this, which is how the `Authorization` header tells
+// them apart from access tokens.
+pub const API_KEY_PREFIX: &str = "mpcw_";
+const KEY_LENGTH: usize = 32;
+const VISIBLE_PREFIX_LENGTH: usize = 12;
+
+// Issues and checks API keys for services that call the wallet without a
+// user session. Only a hash of each key is stored.
+pub struct ApiKeyManager {
+    api_keys: Collection<ApiKeyDocument>,
+}
+
+impl ApiKeyManager {
+    pub fn new(api_keys: Collection<ApiKeyDocument>) -> Self {
+        ApiKeyManager { api_keys }
+    }
+
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(API_KEY_PREFIX)
+    }
+
+    // A new key for `user_id`, or for `organization_id` when set. The key
+    // itself is only returned here.
+    pub async fn issue(
+        &self,
+        user_id: &str,
+        email: &str,
+        organization_id: Option<&str>,
+        name: &str,
+        scopes: Vec<ApiKeyScope>,
+        expires_at: Option<DateTime>,
+    ) -> Result<(String, ApiKeyDocument), ApiKeyError> {
+        let mut secret = [0u8; KEY_LENGTH];
+        OsRng
+            .try_fill_bytes(&mut secret)
+            .map_err(|_| ApiKeyError::RandomnessError)?;
+        let key = format!("{}{}", API_KEY_PREFIX, hex::encode(secret));
+
+        let mut document = ApiKeyDocument {
+            id: None,
+            user_id: user_id.to_string(),
+            email: email.to_string(),
+            organization_id: organization_id.map(str::to_string),
+            name: name.to_string(),
+            prefix: key[..VISIBLE_PREFIX_LENGTH].to_string(),
+            key_hash: hash_key(&key),
+            scopes,
+            created_at: DateTime::now(),
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        let inserted = self
+            .api_keys
+            .insert_one(&document)
+            .await
+            .map_err(|e| ApiKeyError::Database(e.to_string()))?;
+        document.id = inserted.inserted_id.as_object_id();
+        Ok((key, document))
+    }
+
+    // The key's document if it is neither expired nor revoked. Its last use is
+    // recorded on the way.
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKeyDocument, ApiKeyError> {
+        let now = DateTime::now();
+        self.api_keys
+            .find_one_and_update(
+                doc! {
+                    "key_hash": hash_key(key),
+                    "revoked_at": null,
+                    "$or": [{"expires_at": null}, {"expires_at": {"$gt": now}}],
+                },
+                doc! {"$set": {"last_used_at": now}},
+            )
+            .await
+            .map_err(|e| ApiKeyError::Database(e.to_string()))?
+            .ok_or(ApiKeyError::InvalidKey)
+    }
+
+    // The keys of `user_id` and of the organizations in `organization_ids`.
+    pub async fn list(
+        &self,
+        user_id: &str,
+        organization_ids: &[String],
+    ) -> Result<Vec<ApiKeyDocument>, ApiKeyError> {
+        self.api_keys
+            .find(owned_by(user_id, organization_ids))
+            .sort(doc! {"created_at": -1})
+            .await
+            .map_err(|e| ApiKeyError::Database(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| ApiKeyError::Database(e.to_string()))
+    }
+
+    // Revokes one of the keys `list` returns. Revoked keys are kept so their
+    // last use stays visible.
+    pub async fn revoke(
+        &self,
+        user_id: &str,
+        organization_ids: &[String],
+        id: ObjectId,
+    ) -> Result<ApiKeyDocument, ApiKeyError> {
+        let mut filter = owned_by(user_id, organization_ids);
+        filter.insert("_id", id);
+        filter.insert("revoked_at", Bson::Null);
+        self.api_keys
+            .find_one_and_update(filter, doc! {"$set": {"revoked_at": DateTime::now()}})
+            .return_document(ReturnDocument::After)
+            .await
+            .map_err(|e| ApiKeyError::Database(e.to_string()))?
+            .ok_or(ApiKeyError::InvalidKey)
+    }
+}
+
+fn owned_by(user_id: &str, organization_ids: &[String]) -> Document {
+    doc! {"$or": [
+        {"user_id": user_id, "organization_id": null},
+        {"organization_id": {"$in": organization_ids}},
+    ]}
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
@@ -6,27 +6,45 @@ use ethers::{
     core::types::Address,
     signers::{LocalWallet, Signer},
 };
+use ethers::utils::keccak256;
 use hex;
+use secp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+use crate::chains::{
+    bitcoin::{BitcoinReceipt, p2wpkh_address},
+    solana::SolanaReceipt,
+};
+use crate::errors::bitcoin_errors::BitcoinError;
 use crate::routes::handler::response_handler::{AxumApiResponse, JsonApiResponse};
 use crate::{
     models::user_wallet_model::ChainInfo, routes::handler::transaction_handler::Transaction,
 };
 
-pub fn generate_chain_data(secret_key: &str) -> (String, String) {
-    let wallet: LocalWallet = secret_key.parse().unwrap();
-    let address = wallet.address();
+pub fn generate_chain_data(public_key: &PublicKey) -> (String, String) {
+    let uncompressed_public_key = public_key.serialize_uncompressed();
+    let address = Address::from_slice(&keccak256(&uncompressed_public_key[1..])[12..]);
     let address_str = format!("{:#x}", address);
-    let uncompressed_public_key = wallet.signer().verifying_key().to_encoded_point(false);
-    let public_key = hex::encode(uncompressed_public_key.as_bytes());
+    let public_key = hex::encode(uncompressed_public_key);
     (address_str, public_key)
 }
 
+// Bitcoin addresses are native segwit (P2WPKH) and commit to the compressed key.
+pub fn generate_bitcoin_chain_data(
+    public_key: &PublicKey,
+    network: bitcoin::Network,
+) -> Result<(String, String), BitcoinError> {
+    let address = p2wpkh_address(public_key, network)?;
+    Ok((address.to_string(), hex::encode(public_key.serialize())))
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ChainTypeTxn {
     EVM,
+    SOLANA,
+    BITCOIN,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,9 +52,12 @@ pub struct ChainResponse {
     pub chain: TXChain,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Serialize, Deserialize)]
 pub enum TXChain {
-    EVM(EVMResponse),
+    EVM(Box<EVMResponse>),
+    SOLANA(SolanaReceipt),
+    BITCOIN(BitcoinReceipt),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
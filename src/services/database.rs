@@ -1,4 +1,21 @@
-use crate::models::{chain_model::WalletChainDataSchema, user_wallet_model::UserWalletSchema};
+use crate::chains::registry::ChainRegistry;
+use crate::models::{
+    api_key_model::ApiKeyDocument, chain_model::WalletChainDataSchema,
+    key_share_model::KeyShareDocument,
+    nonce_model::NonceDocument, organization_model::OrganizationDocument,
+    session_model::RefreshTokenDocument,
+    submission_model::SubmissionDocument,
+    transaction_model::TransactionDocument, user_wallet_model::UserWalletSchema,
+    webauthn_model::WebauthnChallengeDocument,
+};
+use crate::services::{
+    api_key_services::ApiKeyManager,
+    encryption_services::{EnvelopeEncryption, LocalKeyProvider},
+    key_store::KeyVault,
+    nonce_services::NonceManager,
+    session_services::SessionManager,
+    webauthn_services::WebauthnManager,
+};
 use dotenv::dotenv;
 use mongodb::{Client, Collection, IndexModel, bson::doc, options::IndexOptions};
 use serde::{Deserialize, Serialize};
@@ -7,6 +24,15 @@ use std::env;
 pub struct Database {
     pub wallet_chain_data: Collection<WalletChainDataSchema>,
     pub user_wallet: Collection<UserWalletSchema>,
+    pub key_vault: KeyVault,
+    pub chains: ChainRegistry,
+    pub nonces: NonceManager,
+    pub transactions: Collection<TransactionDocument>,
+    pub submissions: Collection<SubmissionDocument>,
+    pub sessions: SessionManager,
+    pub webauthn: WebauthnManager,
+    pub api_keys: ApiKeyManager,
+    pub organizations: Collection<OrganizationDocument>,
 }
 
 impl Database {
@@ -38,12 +64,128 @@ impl Database {
             .await
             .expect("INDEX ERROR: USERNAME DUPLICATE!");
 
+        let key_shares: Collection<KeyShareDocument> = database.collection("key_shares");
+        key_shares
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"owner": 1, "epoch": 1, "share.index": 1})
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .expect("INDEX ERROR: KEY SHARE DUPLICATE!");
+
+        let nonces: Collection<NonceDocument> = database.collection("nonces");
+        nonces
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"chain_id": 1, "address": 1})
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .expect("INDEX ERROR: NONCE ACCOUNT DUPLICATE!");
+
+        let transactions: Collection<TransactionDocument> = database.collection("transactions");
+        transactions
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"chain_id": 1, "hash": 1})
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await
+            .expect("INDEX ERROR: TRANSACTION HASH DUPLICATE!");
+
+        let submissions: Collection<SubmissionDocument> = database.collection("submissions");
+        submissions
+            .create_index(IndexModel::builder().keys(doc! {"status": 1}).build())
+            .await
+            .expect("INDEX ERROR: SUBMISSION STATUS!");
+
+        let refresh_tokens: Collection<RefreshTokenDocument> =
+            database.collection("refresh_tokens");
+        refresh_tokens
+            .create_index(Self::create_unique(String::from("token_hash")))
+            .await
+            .expect("INDEX ERROR: REFRESH TOKEN DUPLICATE!");
+        refresh_tokens
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"expires_at": 1})
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(std::time::Duration::ZERO)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .expect("INDEX ERROR: REFRESH TOKEN EXPIRY!");
+        let sessions =
+            SessionManager::from_env(refresh_tokens).expect("FAILED TO CONFIGURE SESSIONS!");
+
+        let webauthn_challenges: Collection<WebauthnChallengeDocument> =
+            database.collection("webauthn_challenges");
+        webauthn_challenges
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! {"expires_at": 1})
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(std::time::Duration::ZERO)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .expect("INDEX ERROR: WEBAUTHN CHALLENGE EXPIRY!");
+        let webauthn =
+            WebauthnManager::from_env(webauthn_challenges).expect("FAILED TO CONFIGURE WEBAUTHN!");
+
+        let api_keys: Collection<ApiKeyDocument> = database.collection("api_keys");
+        api_keys
+            .create_index(Self::create_unique(String::from("key_hash")))
+            .await
+            .expect("INDEX ERROR: API KEY DUPLICATE!");
+        api_keys
+            .create_index(IndexModel::builder().keys(doc! {"user_id": 1}).build())
+            .await
+            .expect("INDEX ERROR: API KEY USER!");
+        api_keys
+            .create_index(IndexModel::builder().keys(doc! {"organization_id": 1}).build())
+            .await
+            .expect("INDEX ERROR: API KEY ORGANIZATION!");
+
+        let organizations: Collection<OrganizationDocument> = database.collection("organizations");
+        organizations
+            .create_index(IndexModel::builder().keys(doc! {"members": 1}).build())
+            .await
+            .expect("INDEX ERROR: ORGANIZATION MEMBERS!");
+
+        let key_provider = LocalKeyProvider::from_env().expect("FAILED TO LOAD MASTER KEYS!");
+        let key_vault = KeyVault::from_env(
+            key_shares,
+            EnvelopeEncryption::new(Box::new(key_provider)),
+        )
+        .expect("FAILED TO CONFIGURE KEY STORES!");
+
         Database {
             user_wallet,
             wallet_chain_data,
+            key_vault,
+            chains: ChainRegistry::from_env(),
+            nonces: NonceManager::new(nonces),
+            transactions,
+            submissions,
+            sessions,
+            webauthn,
+            api_keys: ApiKeyManager::new(api_keys),
+            organizations,
         }
     }
 
+    #[allow(clippy::let_and_return)]
     fn create_unique(key: String) -> IndexModel {
         let opt = IndexOptions::builder().unique(true).build();
         let index = IndexModel::builder()
@@ -0,0 +1,229 @@
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use secp256k1::{PublicKey, Scalar, Secp256k1};
+use sha2::Sha512;
+use std::{fmt, str::FromStr};
+
+use crate::errors::key_errors::KeyShareError;
+use crate::models::user_wallet_model::KeyShare;
+use crate::services::key_services::KeyServices;
+
+// Path numbers of the EVM chains, borrowed from BIP44 (44) and SLIP-44 (60).
+pub const EVM_PURPOSE: u32 = 44;
+pub const EVM_COIN_TYPE: u32 = 60;
+// Path numbers of Bitcoin P2WPKH addresses, borrowed from BIP84 (84) and
+// SLIP-44 (0 on mainnet, 1 on the test networks). Every step is unhardened, so
+// these are not BIP84 paths and other BIP84 wallets derive different addresses.
+pub const BITCOIN_PATH_PURPOSE: u32 = 84;
+pub const BITCOIN_PATH_COIN_TYPE: u32 = 0;
+pub const BITCOIN_TESTNET_PATH_COIN_TYPE: u32 = 1;
+
+const HARDENED_OFFSET: u32 = 1 << 31;
+
+// A path below the MPC root key, i.e. the joint public key with the DKG chain
+// code as its extended public key. Hardened steps would need the private key, so
+// all five levels are derived without hardening and written without the '
+// marker: the string is exactly the derivation that was applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivationPath {
+    pub purpose: u32,
+    pub coin_type: u32,
+    pub account: u32,
+    pub change: u32,
+    pub index: u32,
+}
+
+impl DerivationPath {
+    pub fn new(purpose: u32, coin_type: u32, account: u32, index: u32) -> Self {
+        DerivationPath {
+            purpose,
+            coin_type,
+            account,
+            change: 0,
+            index,
+        }
+    }
+
+    fn steps(&self) -> [u32; 5] {
+        [
+            self.purpose,
+            self.coin_type,
+            self.account,
+            self.change,
+            self.index,
+        ]
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "m/{}/{}/{}/{}/{}",
+            self.purpose, self.coin_type, self.account, self.change, self.index
+        )
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = KeyShareError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || KeyShareError::InvalidDerivationPath(path.to_string());
+        let steps = path
+            .strip_prefix("m/")
+            .ok_or_else(invalid)?
+            .split('/')
+            .map(|step| match step.parse::<u32>() {
+                Ok(step) if step < HARDENED_OFFSET => Ok(step),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let [purpose, coin_type, account, change, index] = steps[..] else {
+            return Err(invalid());
+        };
+        Ok(DerivationPath {
+            purpose,
+            coin_type,
+            account,
+            change,
+            index,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DerivedKey {
+    pub public_key: PublicKey,
+    pub chain_code: [u8; 32],
+    // Sum of the I_L values along the path: child secret = master secret + tweak.
+    pub tweak: BigUint,
+}
+
+pub fn derive_public_key(
+    root: &PublicKey,
+    chain_code: &[u8; 32],
+    path: &DerivationPath,
+) -> Result<DerivedKey, KeyShareError> {
+    let order = KeyServices::curve_order();
+    let mut derived = DerivedKey {
+        public_key: *root,
+        chain_code: *chain_code,
+        tweak: BigUint::ZERO,
+    };
+    for step in path.steps() {
+        let (public_key, chain_code, tweak) =
+            derive_child(&derived.public_key, &derived.chain_code, step)?;
+        derived = DerivedKey {
+            public_key,
+            chain_code,
+            tweak: (derived.tweak + tweak) % &order,
+        };
+    }
+    Ok(derived)
+}
+
+// Same as `derive_public_key`, from the hex encoded root key and chain code kept
+// on the user document.
+pub fn derive_user_key(
+    public_key: &str,
+    chain_code: &str,
+    path: &DerivationPath,
+) -> Result<DerivedKey, KeyShareError> {
+    let root = public_key
+        .parse::<PublicKey>()
+        .map_err(|_| KeyShareError::InvalidPoint)?;
+    let chain_code = hex::decode(chain_code)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(KeyShareError::MalformedChainCode)?;
+    derive_public_key(&root, &chain_code, path)
+}
+
+// BIP32 CKDpub for a single non-hardened step.
+fn derive_child(
+    parent: &PublicKey,
+    chain_code: &[u8; 32],
+    index: u32,
+) -> Result<(PublicKey, [u8; 32], BigUint), KeyShareError> {
+    if index >= HARDENED_OFFSET {
+        return Err(KeyShareError::InvalidChildIndex(index));
+    }
+    let mut mac = Hmac::<Sha512>::new_from_slice(chain_code)
+        .map_err(|_| KeyShareError::InvalidChildIndex(index))?;
+    mac.update(&parent.serialize());
+    mac.update(&index.to_be_bytes());
+    let output = mac.finalize().into_bytes();
+    let (left, right) = output.split_at(32);
+
+    let left: [u8; 32] = left
+        .try_into()
+        .map_err(|_| KeyShareError::InvalidChildIndex(index))?;
+    let tweak = Scalar::from_be_bytes(left).map_err(|_| KeyShareError::InvalidChildIndex(index))?;
+    let child = parent
+        .add_exp_tweak(&Secp256k1::verification_only(), &tweak)
+        .map_err(|_| KeyShareError::InvalidChildIndex(index))?;
+    let child_chain_code = right
+        .try_into()
+        .map_err(|_| KeyShareError::InvalidChildIndex(index))?;
+    Ok((child, child_chain_code, BigUint::from_bytes_be(&left)))
+}
+
+// Adding the same constant to every share shifts the shared secret by exactly
+// that constant, because the Lagrange coefficients at zero always sum to one.
+pub fn tweak_shares(shares: &[KeyShare], tweak: &BigUint) -> Result<Vec<KeyShare>, KeyShareError> {
+    let order = KeyServices::curve_order();
+    shares
+        .iter()
+        .map(|share| {
+            let value = (KeyServices::decode_scalar(&share.value)? + tweak) % &order;
+            Ok(KeyShare {
+                index: share.index,
+                value: KeyServices::encode_scalar(&value),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::key_services::SharePolicy;
+
+    #[test]
+    fn path_string_is_the_applied_derivation() {
+        let path = DerivationPath::new(EVM_PURPOSE, EVM_COIN_TYPE, 2, 7);
+        assert_eq!(path.to_string(), "m/44/60/2/0/7");
+        assert_eq!(path.to_string().parse::<DerivationPath>().unwrap(), path);
+
+        for invalid in [
+            "m/44'/60'/0/0/0",
+            "m/44/60/0/0",
+            "44/60/0/0/0",
+            "m/44/60/0/0/x",
+        ] {
+            assert!(matches!(
+                invalid.parse::<DerivationPath>(),
+                Err(KeyShareError::InvalidDerivationPath(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn tweaked_shares_sign_for_the_derived_key() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let root = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+
+        let path = DerivationPath::new(EVM_PURPOSE, EVM_COIN_TYPE, 0, 3);
+        let derived = derive_public_key(&root, &[7u8; 32], &path).unwrap();
+        let tweaked = tweak_shares(&shares[1..], &derived.tweak).unwrap();
+
+        assert_ne!(derived.public_key, root);
+        assert_eq!(
+            KeyServices::shares_public_key(&tweaked, policy.threshold).unwrap(),
+            derived.public_key
+        );
+    }
+}
@@ -0,0 +1,729 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::errors::key_errors::{DkgError, KeyShareError};
+use crate::models::user_wallet_model::KeyShare;
+use crate::services::key_services::{ShareGroup, SharePolicy};
+
+#[derive(Debug, Clone)]
+pub struct DkgRoundOneMessage<G: ShareGroup> {
+    pub from: u32,
+    pub commitments: Vec<G::Point>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DkgRoundTwoMessage<G: ShareGroup> {
+    pub from: u32,
+    pub to: u32,
+    pub share: G::Scalar,
+}
+
+#[derive(Debug, Clone)]
+pub struct DkgOutput<G: ShareGroup> {
+    pub share: KeyShare,
+    pub public_key: G::Point,
+    pub chain_code: [u8; 32],
+}
+
+// The shares a set of parties finished the DKG with, and the key they agree on.
+#[derive(Debug)]
+pub struct JointKey<G: ShareGroup> {
+    pub shares: Vec<KeyShare>,
+    pub public_key: G::Point,
+    pub chain_code: [u8; 32],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DkgState {
+    AwaitingCommitments,
+    AwaitingShares,
+    Finished,
+}
+
+// One party of a Feldman verifiable secret sharing based DKG. Every party deals a
+// random polynomial; its share of the joint key is the sum of what it was dealt,
+// so no single party (and no dealer) ever learns the joint secret. The same
+// session runs over secp256k1 for the EVM key and over Ed25519 for Solana.
+pub struct DkgSession<G: ShareGroup> {
+    index: u32,
+    policy: SharePolicy,
+    state: DkgState,
+    coefficients: Vec<G::Scalar>,
+    commitments: BTreeMap<u32, Vec<G::Point>>,
+    shares: BTreeMap<u32, G::Scalar>,
+}
+
+impl<G: ShareGroup> DkgSession<G> {
+    pub fn new(index: u32, policy: SharePolicy) -> Result<Self, DkgError> {
+        let coefficients = G::random_polynomial(G::random_scalar()?, policy.threshold)?;
+        Ok(DkgSession {
+            index,
+            policy,
+            state: DkgState::AwaitingCommitments,
+            coefficients,
+            commitments: BTreeMap::new(),
+            shares: BTreeMap::new(),
+        })
+    }
+
+    pub fn state(&self) -> DkgState {
+        self.state
+    }
+
+    pub fn round_one(&self) -> Result<DkgRoundOneMessage<G>, DkgError> {
+        Ok(DkgRoundOneMessage {
+            from: self.index,
+            commitments: self
+                .coefficients
+                .iter()
+                .map(G::base_mul)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+
+    pub fn receive_commitments(&mut self, message: DkgRoundOneMessage<G>) -> Result<(), DkgError> {
+        if self.state != DkgState::AwaitingCommitments
+            || message.commitments.len() != self.policy.threshold as usize
+        {
+            return Err(DkgError::UnexpectedMessage("commitment"));
+        }
+        self.check_sender(message.from)?;
+        if self.commitments.contains_key(&message.from) {
+            return Err(DkgError::DuplicateMessage("commitment", message.from));
+        }
+        self.commitments.insert(message.from, message.commitments);
+        if self.commitments.len() == self.policy.total as usize {
+            self.state = DkgState::AwaitingShares;
+        }
+        Ok(())
+    }
+
+    // The dealt shares only depend on this party's own polynomial, so a remote
+    // party can send them along with its commitments.
+    pub fn round_two(&self) -> Result<Vec<DkgRoundTwoMessage<G>>, DkgError> {
+        Ok((1..=self.policy.total)
+            .map(|to| DkgRoundTwoMessage {
+                from: self.index,
+                to,
+                share: G::evaluate_polynomial(&self.coefficients, to),
+            })
+            .collect())
+    }
+
+    pub fn receive_share(&mut self, message: DkgRoundTwoMessage<G>) -> Result<(), DkgError> {
+        if self.state != DkgState::AwaitingShares || message.to != self.index {
+            return Err(DkgError::UnexpectedMessage("share"));
+        }
+        self.check_sender(message.from)?;
+        if self.shares.contains_key(&message.from) {
+            return Err(DkgError::DuplicateMessage("share", message.from));
+        }
+        let commitments = self
+            .commitments
+            .get(&message.from)
+            .ok_or(DkgError::MissingMessage("commitment", message.from))?;
+        let expected = G::evaluate_commitments(commitments, self.index)?;
+        if G::base_mul(&message.share)? != expected {
+            return Err(DkgError::InvalidShare(message.from));
+        }
+        self.shares.insert(message.from, message.share);
+        if self.shares.len() == self.policy.total as usize {
+            self.state = DkgState::Finished;
+        }
+        Ok(())
+    }
+
+    // Each party of the policy sends one message per round. Anything else would
+    // replace a message already counted towards completing the round.
+    fn check_sender(&self, from: u32) -> Result<(), DkgError> {
+        match (1..=self.policy.total).contains(&from) {
+            true => Ok(()),
+            false => Err(DkgError::UnknownParty(from)),
+        }
+    }
+
+    pub fn finish(&self) -> Result<DkgOutput<G>, DkgError> {
+        if self.state != DkgState::Finished {
+            return Err(DkgError::UnexpectedMessage("finish"));
+        }
+        let share = self
+            .shares
+            .values()
+            .fold(G::scalar(0), |acc, share| G::add(&acc, share));
+        Ok(DkgOutput {
+            share: KeyShare {
+                index: self.index,
+                value: G::encode_scalar(&share),
+            },
+            public_key: joint_public_key::<G>(&self.commitments)?,
+            chain_code: joint_chain_code::<G>(&self.commitments),
+        })
+    }
+}
+
+fn joint_public_key<G: ShareGroup>(
+    commitments: &BTreeMap<u32, Vec<G::Point>>,
+) -> Result<G::Point, DkgError> {
+    let constant_terms = commitments
+        .values()
+        .filter_map(|commitments| commitments.first().cloned())
+        .collect::<Vec<_>>();
+    Ok(G::sum_points(&constant_terms)?)
+}
+
+// The BIP32 chain code every party agrees on. It hashes all dealt commitments,
+// so it is as unpredictable as the most honest party's polynomial.
+fn joint_chain_code<G: ShareGroup>(commitments: &BTreeMap<u32, Vec<G::Point>>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"mpc-wallet chain code");
+    for (index, commitments) in commitments {
+        hasher.update(index.to_be_bytes());
+        for commitment in commitments {
+            hasher.update(G::point_bytes(commitment));
+        }
+    }
+    hasher.finalize().into()
+}
+
+// Delivers DKG messages between parties running in the same process.
+pub struct InProcessTransport<G: ShareGroup> {
+    broadcasts: VecDeque<DkgRoundOneMessage<G>>,
+    direct: VecDeque<DkgRoundTwoMessage<G>>,
+}
+
+impl<G: ShareGroup> Default for InProcessTransport<G> {
+    fn default() -> Self {
+        InProcessTransport {
+            broadcasts: VecDeque::new(),
+            direct: VecDeque::new(),
+        }
+    }
+}
+
+impl<G: ShareGroup> InProcessTransport<G> {
+    pub fn broadcast(&mut self, message: DkgRoundOneMessage<G>) {
+        self.broadcasts.push_back(message);
+    }
+
+    pub fn send(&mut self, message: DkgRoundTwoMessage<G>) {
+        self.direct.push_back(message);
+    }
+
+    pub fn deliver(&mut self, sessions: &mut [DkgSession<G>]) -> Result<(), DkgError> {
+        while let Some(message) = self.broadcasts.pop_front() {
+            for session in sessions.iter_mut() {
+                session.receive_commitments(message.clone())?;
+            }
+        }
+        while let Some(message) = self.direct.pop_front() {
+            let session = sessions
+                .iter_mut()
+                .find(|session| session.index == message.to)
+                .ok_or(DkgError::MissingMessage("session", message.to))?;
+            session.receive_share(message)?;
+        }
+        Ok(())
+    }
+}
+
+// One party's messages on the wire: its Feldman commitments and the shares it
+// dealt to the parties on the other side. Points are hex of `point_bytes`,
+// shares hex of `encode_scalar`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DkgPartyMessage {
+    pub from: u32,
+    pub commitments: Vec<String>,
+    pub shares: Vec<DealtShare>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DealtShare {
+    pub to: u32,
+    pub share: String,
+}
+
+impl DkgPartyMessage {
+    // `session`'s commitments with the shares it deals to `recipients`.
+    pub fn from_session<G: ShareGroup>(
+        session: &DkgSession<G>,
+        recipients: &[u32],
+    ) -> Result<Self, DkgError> {
+        Ok(DkgPartyMessage {
+            from: session.index,
+            commitments: session
+                .round_one()?
+                .commitments
+                .iter()
+                .map(|commitment| hex::encode(G::point_bytes(commitment)))
+                .collect(),
+            shares: session
+                .round_two()?
+                .into_iter()
+                .filter(|message| recipients.contains(&message.to))
+                .map(|message| DealtShare {
+                    to: message.to,
+                    share: G::encode_scalar(&message.share),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn decode<G: ShareGroup>(
+        &self,
+    ) -> Result<(DkgRoundOneMessage<G>, Vec<DkgRoundTwoMessage<G>>), DkgError> {
+        let commitments = self
+            .commitments
+            .iter()
+            .map(|commitment| {
+                hex::decode(commitment)
+                    .map_err(|_| KeyShareError::InvalidPoint)
+                    .and_then(|bytes| G::decode_point(&bytes))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let shares = self
+            .shares
+            .iter()
+            .map(|dealt| {
+                Ok(DkgRoundTwoMessage {
+                    from: self.from,
+                    to: dealt.to,
+                    share: G::decode_scalar(&dealt.share)?,
+                })
+            })
+            .collect::<Result<Vec<_>, KeyShareError>>()?;
+        Ok((
+            DkgRoundOneMessage {
+                from: self.from,
+                commitments,
+            },
+            shares,
+        ))
+    }
+}
+
+// The server's side of a DKG with parties it does not run, e.g. the client's
+// device holding the client and recovery indices. Only the `local` parties run
+// here, and the remote parties only send what they deal to them, so the shares
+// of the remote parties never reach the server. `replies` carry the local
+// parties' commitments and the shares they dealt to the remote parties.
+#[derive(Debug)]
+pub struct RemoteDkg<G: ShareGroup> {
+    pub shares: Vec<KeyShare>,
+    pub public_key: G::Point,
+    pub chain_code: [u8; 32],
+    pub replies: Vec<DkgPartyMessage>,
+}
+
+pub fn run_with_remote<G: ShareGroup>(
+    policy: SharePolicy,
+    local: &[u32],
+    remote: &[DkgPartyMessage],
+) -> Result<RemoteDkg<G>, DkgError> {
+    // Every party of the policy is either local or remote, exactly once.
+    let remote_indices = remote
+        .iter()
+        .map(|message| message.from)
+        .collect::<Vec<_>>();
+    let parties = local
+        .iter()
+        .chain(&remote_indices)
+        .copied()
+        .collect::<Vec<_>>();
+    if let Some(&party) = parties
+        .iter()
+        .find(|&&party| !(1..=policy.total).contains(&party))
+    {
+        return Err(DkgError::UnknownParty(party));
+    }
+    for party in 1..=policy.total {
+        match parties.iter().filter(|&&other| other == party).count() {
+            0 => return Err(DkgError::MissingMessage("commitment", party)),
+            1 => {}
+            _ => return Err(DkgError::DuplicateMessage("commitment", party)),
+        }
+    }
+    if local.is_empty() {
+        return Err(DkgError::MissingMessage("session", 0));
+    }
+
+    let mut sessions = local
+        .iter()
+        .map(|&index| DkgSession::<G>::new(index, policy))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut transport = InProcessTransport::default();
+    for session in sessions.iter() {
+        transport.broadcast(session.round_one()?);
+    }
+    let mut remote_shares = Vec::new();
+    for message in remote {
+        let (commitments, shares) = message.decode::<G>()?;
+        transport.broadcast(commitments);
+        for &to in local {
+            let share = shares
+                .iter()
+                .find(|share| share.to == to)
+                .ok_or(DkgError::MissingMessage("share", message.from))?;
+            remote_shares.push(share.clone());
+        }
+    }
+    transport.deliver(&mut sessions)?;
+
+    let mut replies = Vec::with_capacity(sessions.len());
+    for session in sessions.iter() {
+        for message in session.round_two()? {
+            if local.contains(&message.to) {
+                transport.send(message);
+            }
+        }
+        replies.push(DkgPartyMessage::from_session(session, &remote_indices)?);
+    }
+    for share in remote_shares {
+        transport.send(share);
+    }
+    transport.deliver(&mut sessions)?;
+
+    let joint_key = joint_key(&sessions)?;
+    Ok(RemoteDkg {
+        shares: joint_key.shares,
+        public_key: joint_key.public_key,
+        chain_code: joint_key.chain_code,
+        replies,
+    })
+}
+
+// The shares of `sessions` once they all finished on the same joint key.
+fn joint_key<G: ShareGroup>(sessions: &[DkgSession<G>]) -> Result<JointKey<G>, DkgError> {
+    let outputs = sessions
+        .iter()
+        .map(DkgSession::finish)
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = outputs.first() else {
+        return Err(DkgError::MissingMessage("session", 0));
+    };
+    let (public_key, chain_code) = (first.public_key.clone(), first.chain_code);
+    if outputs
+        .iter()
+        .any(|output| output.public_key != public_key || output.chain_code != chain_code)
+    {
+        return Err(DkgError::PublicKeyMismatch);
+    }
+    Ok(JointKey {
+        shares: outputs.into_iter().map(|output| output.share).collect(),
+        public_key,
+        chain_code,
+    })
+}
+
+// Runs every party of the DKG in-process and returns each party's share along
+// with the joint public key. This simulates the DKG, the caller learns every
+// share, so it is only used by tests.
+#[cfg(test)]
+pub fn run_in_process<G: ShareGroup>(policy: SharePolicy) -> Result<JointKey<G>, DkgError> {
+    let mut sessions = (1..=policy.total)
+        .map(|index| DkgSession::<G>::new(index, policy))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut transport = InProcessTransport::default();
+
+    for session in sessions.iter() {
+        transport.broadcast(session.round_one()?);
+    }
+    transport.deliver(&mut sessions)?;
+
+    for session in sessions.iter() {
+        for message in session.round_two()? {
+            transport.send(message);
+        }
+    }
+    transport.deliver(&mut sessions)?;
+
+    joint_key(&sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{eddsa_services::Ed25519Group, key_services::Secp256k1Group};
+
+    fn sessions<G: ShareGroup>(policy: SharePolicy) -> Vec<DkgSession<G>> {
+        (1..=policy.total)
+            .map(|index| DkgSession::new(index, policy))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn parties_agree_on_the_joint_public_key() {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let mut sessions = sessions::<Secp256k1Group>(policy);
+        let mut transport = InProcessTransport::default();
+
+        for session in sessions.iter() {
+            transport.broadcast(session.round_one().unwrap());
+        }
+        transport.deliver(&mut sessions).unwrap();
+        assert!(
+            sessions
+                .iter()
+                .all(|session| session.state() == DkgState::AwaitingShares)
+        );
+
+        for session in sessions.iter() {
+            for message in session.round_two().unwrap() {
+                transport.send(message);
+            }
+        }
+        transport.deliver(&mut sessions).unwrap();
+        assert!(
+            sessions
+                .iter()
+                .all(|session| session.state() == DkgState::Finished)
+        );
+
+        let outputs = sessions
+            .iter()
+            .map(|session| session.finish().unwrap())
+            .collect::<Vec<_>>();
+        let public_key = outputs[0].public_key;
+        assert!(
+            outputs.iter().all(|output| output.public_key == public_key
+                && output.chain_code == outputs[0].chain_code)
+        );
+
+        let shares = outputs
+            .into_iter()
+            .map(|output| output.share)
+            .collect::<Vec<_>>();
+        for pair in [[0, 1], [0, 2], [1, 2]] {
+            let subset = pair.map(|i| shares[i].clone());
+            assert_eq!(
+                Secp256k1Group::shares_public_key(&subset, policy.threshold).unwrap(),
+                public_key
+            );
+        }
+    }
+
+    #[test]
+    fn run_in_process_matches_the_shares() {
+        let policy = SharePolicy::new(3, 5).unwrap();
+        let joint_key = run_in_process::<Secp256k1Group>(policy).unwrap();
+
+        assert_eq!(joint_key.shares.len(), 5);
+        assert_eq!(
+            Secp256k1Group::shares_public_key(&joint_key.shares[2..], policy.threshold).unwrap(),
+            joint_key.public_key
+        );
+    }
+
+    #[test]
+    fn share_that_breaks_the_commitments_is_rejected() {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let mut sessions = sessions::<Secp256k1Group>(policy);
+        let mut transport = InProcessTransport::default();
+        for session in sessions.iter() {
+            transport.broadcast(session.round_one().unwrap());
+        }
+        transport.deliver(&mut sessions).unwrap();
+
+        let mut message = sessions[0]
+            .round_two()
+            .unwrap()
+            .into_iter()
+            .find(|message| message.to == 2)
+            .unwrap();
+        message.share = Secp256k1Group::add(&message.share, &Secp256k1Group::scalar(1));
+
+        assert!(matches!(
+            sessions[1].receive_share(message),
+            Err(DkgError::InvalidShare(1))
+        ));
+        assert_eq!(sessions[1].state(), DkgState::AwaitingShares);
+    }
+
+    #[test]
+    fn duplicate_and_unknown_senders_are_rejected() {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let mut sessions = sessions::<Secp256k1Group>(policy);
+        let first = sessions[0].round_one().unwrap();
+
+        sessions[1].receive_commitments(first.clone()).unwrap();
+        assert!(matches!(
+            sessions[1].receive_commitments(first.clone()),
+            Err(DkgError::DuplicateMessage("commitment", 1))
+        ));
+        for from in [0, 4] {
+            let mut message = first.clone();
+            message.from = from;
+            assert!(matches!(
+                sessions[1].receive_commitments(message),
+                Err(DkgError::UnknownParty(_))
+            ));
+        }
+        assert_eq!(sessions[1].state(), DkgState::AwaitingCommitments);
+
+        let messages = sessions
+            .iter()
+            .map(|session| session.round_one().unwrap())
+            .collect::<Vec<_>>();
+        for message in messages.iter().cloned() {
+            sessions[0].receive_commitments(message).unwrap();
+        }
+        for message in messages[1..].iter().cloned() {
+            sessions[1].receive_commitments(message).unwrap();
+        }
+        let share = sessions[0]
+            .round_two()
+            .unwrap()
+            .into_iter()
+            .find(|message| message.to == 2)
+            .unwrap();
+        sessions[1].receive_share(share.clone()).unwrap();
+        assert!(matches!(
+            sessions[1].receive_share(share),
+            Err(DkgError::DuplicateMessage("share", 1))
+        ));
+    }
+
+    // Plays the client's device: runs the remote parties, sends what they deal
+    // to the server and finishes them with the server's replies.
+    fn client_round<G: ShareGroup>(
+        policy: SharePolicy,
+        local: &[u32],
+        remote: &[u32],
+    ) -> (RemoteDkg<G>, Vec<DkgOutput<G>>) {
+        let mut client = remote
+            .iter()
+            .map(|&index| DkgSession::<G>::new(index, policy).unwrap())
+            .collect::<Vec<_>>();
+        let messages = client
+            .iter()
+            .map(|session| DkgPartyMessage::from_session(session, local).unwrap())
+            .collect::<Vec<_>>();
+        let server = run_with_remote::<G>(policy, local, &messages).unwrap();
+
+        let mut transport = InProcessTransport::default();
+        for session in client.iter() {
+            transport.broadcast(session.round_one().unwrap());
+        }
+        let mut dealt = Vec::new();
+        for reply in server.replies.iter() {
+            let (commitments, shares) = reply.decode::<G>().unwrap();
+            transport.broadcast(commitments);
+            dealt.extend(shares);
+        }
+        transport.deliver(&mut client).unwrap();
+        for session in client.iter() {
+            for message in session.round_two().unwrap() {
+                if remote.contains(&message.to) {
+                    transport.send(message);
+                }
+            }
+        }
+        for message in dealt {
+            transport.send(message);
+        }
+        transport.deliver(&mut client).unwrap();
+
+        let outputs = client
+            .iter()
+            .map(|session| session.finish().unwrap())
+            .collect();
+        (server, outputs)
+    }
+
+    fn remote_parties_share_one_key<G: ShareGroup>() {
+        let policy = SharePolicy::new(3, 5).unwrap();
+        let (server, client) = client_round::<G>(policy, &[1, 2], &[3, 4, 5]);
+
+        assert_eq!(server.shares.len(), 2);
+        assert!(
+            client
+                .iter()
+                .all(|output| output.public_key == server.public_key
+                    && output.chain_code == server.chain_code)
+        );
+        // The server's shares alone are one short of the threshold.
+        assert!(G::shares_public_key(&server.shares, policy.threshold).is_err());
+
+        let client_shares = client
+            .into_iter()
+            .map(|output| output.share)
+            .collect::<Vec<_>>();
+        for subset in [
+            vec![
+                server.shares[0].clone(),
+                server.shares[1].clone(),
+                client_shares[2].clone(),
+            ],
+            vec![
+                server.shares[1].clone(),
+                client_shares[0].clone(),
+                client_shares[1].clone(),
+            ],
+            client_shares.clone(),
+        ] {
+            assert_eq!(
+                G::shares_public_key(&subset, policy.threshold).unwrap(),
+                server.public_key
+            );
+        }
+    }
+
+    #[test]
+    fn remote_secp256k1_parties_share_one_key() {
+        remote_parties_share_one_key::<Secp256k1Group>();
+    }
+
+    #[test]
+    fn remote_ed25519_parties_share_one_key() {
+        remote_parties_share_one_key::<Ed25519Group>();
+    }
+
+    #[test]
+    fn remote_parties_must_cover_the_policy() {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let session = DkgSession::<Secp256k1Group>::new(3, policy).unwrap();
+        let message = DkgPartyMessage::from_session(&session, &[1]).unwrap();
+
+        assert!(matches!(
+            run_with_remote::<Secp256k1Group>(policy, &[1], std::slice::from_ref(&message)),
+            Err(DkgError::MissingMessage("commitment", 2))
+        ));
+        assert!(matches!(
+            run_with_remote::<Secp256k1Group>(policy, &[1, 2], &[message.clone(), message.clone()]),
+            Err(DkgError::DuplicateMessage("commitment", 3))
+        ));
+        let mut unknown = message.clone();
+        unknown.from = 4;
+        assert!(matches!(
+            run_with_remote::<Secp256k1Group>(policy, &[1, 2], &[message.clone(), unknown]),
+            Err(DkgError::UnknownParty(4))
+        ));
+        // Party 3 dealt nothing to party 2.
+        assert!(matches!(
+            run_with_remote::<Secp256k1Group>(policy, &[1, 2], std::slice::from_ref(&message)),
+            Err(DkgError::MissingMessage("share", 3))
+        ));
+
+        let mut tampered = DkgPartyMessage::from_session(&session, &[1, 2]).unwrap();
+        tampered.shares[0].share = Secp256k1Group::encode_scalar(&Secp256k1Group::scalar(7));
+        assert!(matches!(
+            run_with_remote::<Secp256k1Group>(policy, &[1, 2], &[tampered]),
+            Err(DkgError::InvalidShare(3))
+        ));
+    }
+
+    #[test]
+    fn ed25519_parties_share_one_key() {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let joint_key = run_in_process::<Ed25519Group>(policy).unwrap();
+
+        for pair in [[0, 1], [0, 2], [1, 2]] {
+            let subset = pair.map(|i| joint_key.shares[i].clone());
+            assert_eq!(
+                Ed25519Group::shares_public_key(&subset, policy.threshold).unwrap(),
+                joint_key.public_key
+            );
+        }
+    }
+}
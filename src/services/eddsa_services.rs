@@ -0,0 +1,397 @@
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_POINT,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::{TryRngCore, rngs::OsRng};
+use sha2::{Digest, Sha512};
+use std::collections::BTreeMap;
+
+use crate::errors::{key_errors::KeyShareError, signing_errors::SigningError};
+use crate::models::user_wallet_model::KeyShare;
+use crate::services::key_services::{KeyServices, ShareGroup};
+
+// Ed25519 has no public child derivation, so every Solana address is the joint
+// key itself. Its path is the root, not a BIP44 path that was never derived.
+pub const EDDSA_ROOT_PATH: &str = "m";
+
+// Ed25519 arithmetic for the shared Shamir and DKG code in key_services and
+// dkg_services. Scalars are canonical and reduced mod l.
+#[derive(Debug, Clone, Copy)]
+pub struct Ed25519Group;
+
+impl ShareGroup for Ed25519Group {
+    type Scalar = Scalar;
+    type Point = EdwardsPoint;
+
+    fn random_scalar() -> Result<Scalar, KeyShareError> {
+        let mut bytes = [0u8; 64];
+        OsRng
+            .try_fill_bytes(&mut bytes)
+            .map_err(|_| KeyShareError::RandomScalarError)?;
+        Ok(Scalar::from_bytes_mod_order_wide(&bytes))
+    }
+
+    fn scalar(value: u32) -> Scalar {
+        Scalar::from(value)
+    }
+
+    fn add(a: &Scalar, b: &Scalar) -> Scalar {
+        a + b
+    }
+
+    fn sub(a: &Scalar, b: &Scalar) -> Scalar {
+        a - b
+    }
+
+    fn mul(a: &Scalar, b: &Scalar) -> Scalar {
+        a * b
+    }
+
+    fn invert(value: &Scalar) -> Scalar {
+        value.invert()
+    }
+
+    fn encode_scalar(value: &Scalar) -> String {
+        hex::encode(value.to_bytes())
+    }
+
+    fn decode_scalar(value: &str) -> Result<Scalar, KeyShareError> {
+        let bytes: [u8; 32] = hex::decode(value)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(KeyShareError::MalformedShare)?;
+        Option::from(Scalar::from_canonical_bytes(bytes)).ok_or(KeyShareError::MalformedShare)
+    }
+
+    fn base_mul(scalar: &Scalar) -> Result<EdwardsPoint, KeyShareError> {
+        Ok(ED25519_BASEPOINT_POINT * scalar)
+    }
+
+    fn point_mul(point: &EdwardsPoint, scalar: &Scalar) -> Result<EdwardsPoint, KeyShareError> {
+        Ok(point * scalar)
+    }
+
+    fn sum_points(points: &[EdwardsPoint]) -> Result<EdwardsPoint, KeyShareError> {
+        Ok(points.iter().sum())
+    }
+
+    fn point_bytes(point: &EdwardsPoint) -> Vec<u8> {
+        point.compress().to_bytes().to_vec()
+    }
+
+    // Points from other parties must lie in the prime order subgroup, a small
+    // torsion component would survive into the joint key.
+    fn decode_point(bytes: &[u8]) -> Result<EdwardsPoint, KeyShareError> {
+        CompressedEdwardsY::from_slice(bytes)
+            .ok()
+            .and_then(|point| point.decompress())
+            .filter(EdwardsPoint::is_torsion_free)
+            .ok_or(KeyShareError::InvalidPoint)
+    }
+}
+
+pub struct EddsaKeyServices;
+
+impl EddsaKeyServices {
+    pub fn decode_point(value: &str) -> Result<EdwardsPoint, KeyShareError> {
+        hex::decode(value)
+            .ok()
+            .and_then(|bytes| CompressedEdwardsY::from_slice(&bytes).ok())
+            .and_then(|point| point.decompress())
+            .ok_or(KeyShareError::InvalidPoint)
+    }
+
+    pub fn solana_address(public_key: &EdwardsPoint) -> String {
+        bs58::encode(public_key.compress().as_bytes()).into_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FrostCommitment {
+    pub from: u32,
+    pub hiding: EdwardsPoint,
+    pub binding: EdwardsPoint,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrostPartialSignature {
+    pub from: u32,
+    pub z: Scalar,
+}
+
+// One share holder in a two round FROST session. Round one publishes nonce
+// commitments, round two answers with z_i = d_i + e_i * rho_i + lambda_i * s_i * c.
+pub struct FrostSigningParty {
+    index: u32,
+    share: Scalar,
+    hiding_nonce: Scalar,
+    binding_nonce: Scalar,
+}
+
+impl FrostSigningParty {
+    pub fn new(share: &KeyShare) -> Result<Self, SigningError> {
+        Ok(FrostSigningParty {
+            index: share.index,
+            share: Ed25519Group::decode_scalar(&share.value)?,
+            hiding_nonce: Ed25519Group::random_scalar()?,
+            binding_nonce: Ed25519Group::random_scalar()?,
+        })
+    }
+
+    pub fn round_one(&self) -> FrostCommitment {
+        FrostCommitment {
+            from: self.index,
+            hiding: ED25519_BASEPOINT_POINT * self.hiding_nonce,
+            binding: ED25519_BASEPOINT_POINT * self.binding_nonce,
+        }
+    }
+
+    pub fn round_two(
+        &self,
+        session: &FrostSession,
+        signers: &[u32],
+    ) -> Result<FrostPartialSignature, SigningError> {
+        let rho = session.binding_factor(self.index)?;
+        let lambda = Ed25519Group::lagrange_coefficient(self.index, signers);
+        Ok(FrostPartialSignature {
+            from: self.index,
+            z: self.hiding_nonce
+                + self.binding_nonce * rho
+                + lambda * self.share * session.challenge,
+        })
+    }
+}
+
+// The values every signer derives the same way from the round one commitments.
+pub struct FrostSession {
+    binding_factors: BTreeMap<u32, Scalar>,
+    group_commitment: EdwardsPoint,
+    challenge: Scalar,
+}
+
+impl FrostSession {
+    pub fn new(public_key: &EdwardsPoint, message: &[u8], commitments: &[FrostCommitment]) -> Self {
+        let mut transcript = Sha512::new();
+        for commitment in commitments {
+            transcript.update(commitment.from.to_be_bytes());
+            transcript.update(commitment.hiding.compress().as_bytes());
+            transcript.update(commitment.binding.compress().as_bytes());
+        }
+        let transcript = transcript.finalize();
+        let message_hash = Sha512::digest(message);
+
+        let binding_factors = commitments
+            .iter()
+            .map(|commitment| {
+                let rho = Scalar::from_hash(
+                    Sha512::new()
+                        .chain_update(b"FROST-ED25519-SHA512 rho")
+                        .chain_update(public_key.compress().as_bytes())
+                        .chain_update(message_hash)
+                        .chain_update(transcript)
+                        .chain_update(commitment.from.to_be_bytes()),
+                );
+                (commitment.from, rho)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let group_commitment =
+            commitments
+                .iter()
+                .fold(EdwardsPoint::identity(), |acc, commitment| {
+                    acc + commitment.hiding + commitment.binding * binding_factors[&commitment.from]
+                });
+        // The plain Ed25519 challenge, so the result verifies as a normal signature.
+        let challenge = Scalar::from_hash(
+            Sha512::new()
+                .chain_update(group_commitment.compress().as_bytes())
+                .chain_update(public_key.compress().as_bytes())
+                .chain_update(message),
+        );
+
+        FrostSession {
+            binding_factors,
+            group_commitment,
+            challenge,
+        }
+    }
+
+    fn binding_factor(&self, index: u32) -> Result<Scalar, SigningError> {
+        self.binding_factors
+            .get(&index)
+            .copied()
+            .ok_or_else(|| SigningError::SessionAborted(format!("no commitment from {}", index)))
+    }
+}
+
+#[derive(Clone)]
+pub struct EddsaThresholdSigner {
+    shares: Vec<KeyShare>,
+    threshold: u32,
+    public_key: EdwardsPoint,
+}
+
+impl EddsaThresholdSigner {
+    pub fn new(
+        shares: Vec<KeyShare>,
+        threshold: u32,
+        public_key: &str,
+    ) -> Result<Self, SigningError> {
+        let public_key = EddsaKeyServices::decode_point(public_key)
+            .map_err(|_| SigningError::InvalidPublicKey)?;
+        if shares.len() < threshold as usize {
+            return Err(KeyShareError::NotEnoughShares(threshold, shares.len()).into());
+        }
+        Ok(EddsaThresholdSigner {
+            shares,
+            threshold,
+            public_key,
+        })
+    }
+
+    pub fn public_key(&self) -> &EdwardsPoint {
+        &self.public_key
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<[u8; 64], SigningError> {
+        let shares = &self.shares[..self.threshold as usize];
+        let signers = KeyServices::share_indices(shares)?;
+        let parties = shares
+            .iter()
+            .map(FrostSigningParty::new)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let commitments = parties
+            .iter()
+            .map(FrostSigningParty::round_one)
+            .collect::<Vec<_>>();
+        let session = FrostSession::new(&self.public_key, message, &commitments);
+
+        let mut z = Scalar::ZERO;
+        for (party, share) in parties.iter().zip(shares) {
+            let partial = party.round_two(&session, &signers)?;
+            self.verify_partial(&session, &commitments, share, &signers, &partial)?;
+            z += partial.z;
+        }
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(session.group_commitment.compress().as_bytes());
+        signature[32..].copy_from_slice(z.as_bytes());
+        self.verify(message, &signature)?;
+        Ok(signature)
+    }
+
+    // z_i * G must equal D_i + rho_i * E_i + c * lambda_i * Y_i, which pins a bad
+    // result on the party that produced it.
+    fn verify_partial(
+        &self,
+        session: &FrostSession,
+        commitments: &[FrostCommitment],
+        share: &KeyShare,
+        signers: &[u32],
+        partial: &FrostPartialSignature,
+    ) -> Result<(), SigningError> {
+        let commitment = commitments
+            .iter()
+            .find(|commitment| commitment.from == partial.from)
+            .ok_or_else(|| {
+                SigningError::SessionAborted(format!("no commitment from {}", partial.from))
+            })?;
+        let verification_share =
+            ED25519_BASEPOINT_POINT * Ed25519Group::decode_scalar(&share.value)?;
+        let lambda = Ed25519Group::lagrange_coefficient(partial.from, signers);
+        let expected = commitment.hiding
+            + commitment.binding * session.binding_factor(partial.from)?
+            + verification_share * (session.challenge * lambda);
+        if ED25519_BASEPOINT_POINT * partial.z != expected {
+            return Err(SigningError::SessionAborted(format!(
+                "invalid partial signature from {}",
+                partial.from
+            )));
+        }
+        Ok(())
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8; 64]) -> Result<(), SigningError> {
+        VerifyingKey::from_bytes(self.public_key.compress().as_bytes())
+            .and_then(|key| key.verify_strict(message, &Signature::from_bytes(signature)))
+            .map_err(|_| SigningError::InvalidSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{dkg_services, key_services::SharePolicy};
+
+    // The shares and hex public key of a 2-of-3 Ed25519 wallet key.
+    fn joint_key() -> (Vec<KeyShare>, EdwardsPoint, String) {
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let joint_key = dkg_services::run_in_process::<Ed25519Group>(policy).unwrap();
+        let public_key = hex::encode(joint_key.public_key.compress().as_bytes());
+        (joint_key.shares, joint_key.public_key, public_key)
+    }
+
+    #[test]
+    fn every_threshold_subset_signs_under_the_joint_key() {
+        let (shares, joint_public_key, public_key) = joint_key();
+        let verifying_key =
+            VerifyingKey::from_bytes(joint_public_key.compress().as_bytes()).unwrap();
+        let message = b"solana transaction message";
+
+        for subset in [[0, 1], [0, 2], [1, 2]] {
+            let subset: Vec<KeyShare> = subset.iter().map(|&i| shares[i].clone()).collect();
+            let signer = EddsaThresholdSigner::new(subset, 2, &public_key).unwrap();
+
+            let signature = signer.sign(message).unwrap();
+
+            verifying_key
+                .verify_strict(message, &Signature::from_bytes(&signature))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn fewer_shares_than_the_threshold_are_refused() {
+        let (shares, _, public_key) = joint_key();
+
+        assert!(EddsaThresholdSigner::new(shares[..1].to_vec(), 2, &public_key).is_err());
+    }
+
+    #[test]
+    fn tampered_partial_signatures_are_pinned_on_their_signer() {
+        let (shares, public_key, hex_public_key) = joint_key();
+        let shares = &shares[1..];
+        let signer = EddsaThresholdSigner::new(shares.to_vec(), 2, &hex_public_key).unwrap();
+        let signers = KeyServices::share_indices(shares).unwrap();
+        let parties: Vec<FrostSigningParty> = shares
+            .iter()
+            .map(|share| FrostSigningParty::new(share).unwrap())
+            .collect();
+        let commitments: Vec<FrostCommitment> =
+            parties.iter().map(FrostSigningParty::round_one).collect();
+        let session = FrostSession::new(&public_key, b"message", &commitments);
+
+        let honest = parties[0].round_two(&session, &signers).unwrap();
+        signer
+            .verify_partial(&session, &commitments, &shares[0], &signers, &honest)
+            .unwrap();
+
+        let tampered = FrostPartialSignature {
+            from: honest.from,
+            z: honest.z + Scalar::ONE,
+        };
+        assert!(matches!(
+            signer.verify_partial(&session, &commitments, &shares[0], &signers, &tampered),
+            Err(SigningError::SessionAborted(reason)) if reason.contains(&honest.from.to_string())
+        ));
+        // A valid partial checked against another signer's share fails as well.
+        assert!(
+            signer
+                .verify_partial(&session, &commitments, &shares[1], &signers, &honest)
+                .is_err()
+        );
+    }
+}
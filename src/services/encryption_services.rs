@@ -0,0 +1,363 @@
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use bcrypt::bcrypt;
+use rand::{TryRngCore, rngs::OsRng};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{ErrorKind, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use crate::errors::encryption_errors::EncryptionError;
+use crate::models::user_wallet_model::{EncryptedKeyShare, KeyShare, SealedKeyShare};
+
+const CLIENT_SHARE_COST: u32 = 10;
+
+pub trait MasterKeyProvider: Send + Sync {
+    fn current_version(&self) -> u32;
+    fn master_key(&self, version: u32) -> Result<[u8; 32], EncryptionError>;
+    fn rotate(&self) -> Result<u32, EncryptionError>;
+}
+
+// Keeps versioned master keys as hex in a local JSON file, e.g. {"1": "ab01..."}.
+pub struct LocalKeyProvider {
+    path: PathBuf,
+    keys: RwLock<BTreeMap<u32, String>>,
+}
+
+impl LocalKeyProvider {
+    pub fn from_env() -> Result<Self, EncryptionError> {
+        let path = match env::var("MASTER_KEY_FILE") {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => PathBuf::from("master_keys.json"),
+        };
+        Self::open(path)
+    }
+
+    // Only a missing file starts a new set of keys. Any other read error would
+    // otherwise replace the keys every stored share is sealed with.
+    pub fn open(path: PathBuf) -> Result<Self, EncryptionError> {
+        let keys = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| EncryptionError::KeyStoreError(e.to_string()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(EncryptionError::KeyStoreError(e.to_string())),
+        };
+        let provider = LocalKeyProvider {
+            path,
+            keys: RwLock::new(keys),
+        };
+        if provider.current_version() == 0 {
+            provider.rotate()?;
+        }
+        Ok(provider)
+    }
+}
+
+impl MasterKeyProvider for LocalKeyProvider {
+    fn current_version(&self) -> u32 {
+        self.keys
+            .read()
+            .map(|keys| keys.keys().last().copied().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    fn master_key(&self, version: u32) -> Result<[u8; 32], EncryptionError> {
+        let keys = self
+            .keys
+            .read()
+            .map_err(|e| EncryptionError::KeyStoreError(e.to_string()))?;
+        keys.get(&version)
+            .and_then(|key| hex::decode(key).ok())
+            .and_then(|key| key.try_into().ok())
+            .ok_or(EncryptionError::UnknownKeyVersion(version))
+    }
+
+    // The new key only goes into use once it is on disk. The file is written
+    // next to the old one, readable by the owner only, and renamed over it, so
+    // a failed write never loses the existing keys.
+    fn rotate(&self) -> Result<u32, EncryptionError> {
+        let mut keys = self
+            .keys
+            .write()
+            .map_err(|e| EncryptionError::KeyStoreError(e.to_string()))?;
+        let mut rotated = keys.clone();
+        let version = rotated.keys().last().copied().unwrap_or(0) + 1;
+        rotated.insert(version, hex::encode(random_bytes::<32>()?));
+
+        let contents = serde_json::to_string_pretty(&rotated)
+            .map_err(|e| EncryptionError::KeyStoreError(e.to_string()))?;
+        write_private(&self.path, contents.as_bytes())
+            .map_err(|e| EncryptionError::KeyStoreError(e.to_string()))?;
+        *keys = rotated;
+        Ok(version)
+    }
+}
+
+// Replaces `path` with a 0600 file holding `contents`. The permissions are set
+// on the open file as well, in case a staging file was left behind earlier.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let staging = path.with_extension("json.tmp");
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&staging)?;
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(staging, path)
+}
+
+// Envelope encryption for key shares: each share is sealed with its own random
+// data key, and only the data key is wrapped with the versioned master key.
+pub struct EnvelopeEncryption {
+    provider: Box<dyn MasterKeyProvider>,
+}
+
+impl EnvelopeEncryption {
+    pub fn new(provider: Box<dyn MasterKeyProvider>) -> Self {
+        EnvelopeEncryption { provider }
+    }
+
+    pub fn rotate_master_key(&self) -> Result<u32, EncryptionError> {
+        self.provider.rotate()
+    }
+
+    pub fn seal_share(&self, share: &KeyShare) -> Result<EncryptedKeyShare, EncryptionError> {
+        let data_key = random_bytes::<32>()?;
+        let (ciphertext, nonce) =
+            encrypt(&data_key, share.value.as_bytes(), &share_aad(share.index))?;
+        let key_version = self.provider.current_version();
+        let (wrapped_key, key_nonce) = encrypt(
+            &self.provider.master_key(key_version)?,
+            &data_key,
+            &key_version.to_be_bytes(),
+        )?;
+
+        Ok(EncryptedKeyShare {
+            index: share.index,
+            ciphertext: hex::encode(ciphertext),
+            nonce: hex::encode(nonce),
+            wrapped_key: hex::encode(wrapped_key),
+            key_nonce: hex::encode(key_nonce),
+            key_version,
+        })
+    }
+
+    pub fn seal_shares(
+        &self,
+        shares: &[KeyShare],
+    ) -> Result<Vec<EncryptedKeyShare>, EncryptionError> {
+        shares.iter().map(|share| self.seal_share(share)).collect()
+    }
+
+    pub fn open_share(&self, share: &EncryptedKeyShare) -> Result<KeyShare, EncryptionError> {
+        let data_key = self.unwrap_data_key(share)?;
+        let value = decrypt(
+            &data_key,
+            &decode(&share.ciphertext)?,
+            &decode(&share.nonce)?,
+            &share_aad(share.index),
+        )?;
+
+        Ok(KeyShare {
+            index: share.index,
+            value: String::from_utf8(value).map_err(|_| EncryptionError::DecryptionFailed)?,
+        })
+    }
+
+    pub fn open_shares(
+        &self,
+        shares: &[EncryptedKeyShare],
+    ) -> Result<Vec<KeyShare>, EncryptionError> {
+        shares.iter().map(|share| self.open_share(share)).collect()
+    }
+
+    // Re-wraps the data key under the current master key. The share ciphertext
+    // itself is left untouched and never decrypted.
+    pub fn rewrap_share(
+        &self,
+        share: &EncryptedKeyShare,
+    ) -> Result<EncryptedKeyShare, EncryptionError> {
+        let key_version = self.provider.current_version();
+        if share.key_version == key_version {
+            return Ok(share.clone());
+        }
+
+        let data_key = self.unwrap_data_key(share)?;
+        let (wrapped_key, key_nonce) = encrypt(
+            &self.provider.master_key(key_version)?,
+            &data_key,
+            &key_version.to_be_bytes(),
+        )?;
+
+        Ok(EncryptedKeyShare {
+            wrapped_key: hex::encode(wrapped_key),
+            key_nonce: hex::encode(key_nonce),
+            key_version,
+            ..share.clone()
+        })
+    }
+
+    fn unwrap_data_key(&self, share: &EncryptedKeyShare) -> Result<Vec<u8>, EncryptionError> {
+        decrypt(
+            &self.provider.master_key(share.key_version)?,
+            &decode(&share.wrapped_key)?,
+            &decode(&share.key_nonce)?,
+            &share.key_version.to_be_bytes(),
+        )
+    }
+}
+
+// The client share is sealed under a key derived from the user's password. The
+// server never stores it and can only open it while a request carries it along.
+pub fn seal_client_share(
+    share: &KeyShare,
+    password: &str,
+) -> Result<SealedKeyShare, EncryptionError> {
+    let salt = random_bytes::<16>()?;
+    let (ciphertext, nonce) = encrypt(
+        &client_share_key(password, salt)?,
+        share.value.as_bytes(),
+        &share_aad(share.index),
+    )?;
+    Ok(SealedKeyShare {
+        index: share.index,
+        salt: hex::encode(salt),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+pub fn open_client_share(
+    share: &SealedKeyShare,
+    password: &str,
+) -> Result<KeyShare, EncryptionError> {
+    let salt = decode(&share.salt)?
+        .try_into()
+        .map_err(|_| EncryptionError::DecryptionFailed)?;
+    let value = decrypt(
+        &client_share_key(password, salt)?,
+        &decode(&share.ciphertext)?,
+        &decode(&share.nonce)?,
+        &share_aad(share.index),
+    )?;
+    Ok(KeyShare {
+        index: share.index,
+        value: String::from_utf8(value).map_err(|_| EncryptionError::DecryptionFailed)?,
+    })
+}
+
+fn client_share_key(password: &str, salt: [u8; 16]) -> Result<[u8; 32], EncryptionError> {
+    // Same input rules as bcrypt::hash: NUL terminated and at most 72 bytes.
+    let mut input = password.as_bytes().to_vec();
+    input.push(0);
+    input.truncate(72);
+    if input.len() < 2 {
+        return Err(EncryptionError::EncryptionFailed);
+    }
+    Ok(Sha256::digest(bcrypt(CLIENT_SHARE_COST, salt, &input)).into())
+}
+
+fn share_aad(index: u32) -> [u8; 4] {
+    index.to_be_bytes()
+}
+
+fn encrypt(
+    key: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, [u8; 12]), EncryptionError> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = random_bytes::<12>()?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| EncryptionError::EncryptionFailed)?;
+    Ok((ciphertext, nonce))
+}
+
+fn decrypt(
+    key: &[u8],
+    ciphertext: &[u8],
+    nonce: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, EncryptionError> {
+    if key.len() != 32 || nonce.len() != 12 {
+        return Err(EncryptionError::DecryptionFailed);
+    }
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| EncryptionError::DecryptionFailed)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, EncryptionError> {
+    hex::decode(value).map_err(|_| EncryptionError::DecryptionFailed)
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], EncryptionError> {
+    let mut bytes = [0u8; N];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|_| EncryptionError::RandomnessError)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("master-keys-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotation_keeps_earlier_keys_in_a_private_file() {
+        let path = key_dir("rotation").join("master_keys.json");
+        let provider = LocalKeyProvider::open(path.clone()).unwrap();
+        let first = provider.master_key(1).unwrap();
+
+        assert_eq!(provider.rotate().unwrap(), 2);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let reopened = LocalKeyProvider::open(path).unwrap();
+        assert_eq!(reopened.current_version(), 2);
+        assert_eq!(reopened.master_key(1).unwrap(), first);
+    }
+
+    #[test]
+    fn unreadable_key_file_is_not_replaced() {
+        // A directory where the file should be fails to read, but is not missing.
+        let path = key_dir("unreadable");
+
+        assert!(matches!(
+            LocalKeyProvider::open(path),
+            Err(EncryptionError::KeyStoreError(_))
+        ));
+    }
+}
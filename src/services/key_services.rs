@@ -1,13 +1,299 @@
-use rand::{RngCore, TryRngCore, rngs::OsRng};
-use secp256k1::SecretKey;
-use sss_rs::basic_sharing;
-use std::{error::Error, result::Result};
 use hex;
+use num_bigint::BigUint;
+use rand::{RngCore, TryRngCore, rngs::OsRng};
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey, constants::CURVE_ORDER};
+use std::{collections::HashSet, env, fmt, result::Result};
 
-use crate::errors::auth_errors::KeyGenerationError;
+use crate::errors::{auth_errors::KeyGenerationError, key_errors::KeyShareError};
+use crate::models::user_wallet_model::KeyShare;
 
 pub struct KeyServices;
 
+#[derive(Debug, Clone, Copy)]
+pub struct SharePolicy {
+    pub threshold: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareHolder {
+    Server,
+    Client,
+    Recovery,
+}
+
+impl SharePolicy {
+    pub fn new(threshold: u32, total: u32) -> Result<Self, KeyShareError> {
+        if threshold == 0 || threshold > total || total > 255 {
+            return Err(KeyShareError::InvalidPolicy(threshold, total));
+        }
+        Ok(SharePolicy { threshold, total })
+    }
+
+    pub fn from_env() -> Result<Self, KeyShareError> {
+        let threshold = env::var("KEY_SHARE_THRESHOLD")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2);
+        let total = env::var("KEY_SHARE_TOTAL")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(3);
+        Self::new(threshold, total)
+    }
+
+    // Who keeps share `index`. The server keeps threshold - 1 shares, so even all
+    // of its key stores together cannot sign. The client device keeps the last
+    // share and the ones in between go to the user as offline recovery shares.
+    pub fn holder(&self, index: u32) -> ShareHolder {
+        if index == self.total {
+            ShareHolder::Client
+        } else if index < self.threshold {
+            ShareHolder::Server
+        } else {
+            ShareHolder::Recovery
+        }
+    }
+
+    // The indices the server runs and keeps, in order.
+    pub fn server_indices(&self) -> Vec<u32> {
+        (1..self.threshold).collect()
+    }
+}
+
+// The prime order group a key is shared over. Implementations supply the field
+// and point arithmetic; Shamir sharing, interpolation and resharing are written
+// once on top of it and used by both the secp256k1 and the Ed25519 keys.
+pub trait ShareGroup: Clone + fmt::Debug {
+    type Scalar: Clone + PartialEq + fmt::Debug + Send + Sync;
+    type Point: Clone + PartialEq + fmt::Debug + Send + Sync;
+
+    fn random_scalar() -> Result<Self::Scalar, KeyShareError>;
+    fn scalar(value: u32) -> Self::Scalar;
+    fn add(a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+    fn sub(a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+    fn mul(a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+    fn invert(value: &Self::Scalar) -> Self::Scalar;
+    fn encode_scalar(value: &Self::Scalar) -> String;
+    fn decode_scalar(value: &str) -> Result<Self::Scalar, KeyShareError>;
+    fn base_mul(scalar: &Self::Scalar) -> Result<Self::Point, KeyShareError>;
+    fn point_mul(point: &Self::Point, scalar: &Self::Scalar) -> Result<Self::Point, KeyShareError>;
+    fn sum_points(points: &[Self::Point]) -> Result<Self::Point, KeyShareError>;
+    fn point_bytes(point: &Self::Point) -> Vec<u8>;
+    fn decode_point(bytes: &[u8]) -> Result<Self::Point, KeyShareError>;
+
+    fn random_polynomial(
+        constant: Self::Scalar,
+        threshold: u32,
+    ) -> Result<Vec<Self::Scalar>, KeyShareError> {
+        let mut coefficients = vec![constant];
+        for _ in 1..threshold {
+            coefficients.push(Self::random_scalar()?);
+        }
+        Ok(coefficients)
+    }
+
+    fn evaluate_polynomial(coefficients: &[Self::Scalar], at: u32) -> Self::Scalar {
+        let x = Self::scalar(at);
+        coefficients
+            .iter()
+            .rev()
+            .fold(Self::scalar(0), |acc, coefficient| {
+                Self::add(&Self::mul(&acc, &x), coefficient)
+            })
+    }
+
+    // The public image of f(x), computed from Feldman commitments to the
+    // coefficients of f.
+    fn evaluate_commitments(
+        commitments: &[Self::Point],
+        at: u32,
+    ) -> Result<Self::Point, KeyShareError> {
+        let x = Self::scalar(at);
+        let mut power = Self::scalar(1);
+        let mut terms = Vec::with_capacity(commitments.len());
+        for commitment in commitments {
+            terms.push(Self::point_mul(commitment, &power)?);
+            power = Self::mul(&power, &x);
+        }
+        Self::sum_points(&terms)
+    }
+
+    // Lagrange basis polynomial for `index` over `indices`, evaluated at zero.
+    fn lagrange_coefficient(index: u32, indices: &[u32]) -> Self::Scalar {
+        let mut numerator = Self::scalar(1);
+        let mut denominator = Self::scalar(1);
+        for &other in indices.iter().filter(|&&other| other != index) {
+            numerator = Self::mul(&numerator, &Self::scalar(other));
+            let difference = Self::sub(&Self::scalar(other), &Self::scalar(index));
+            denominator = Self::mul(&denominator, &difference);
+        }
+        Self::mul(&numerator, &Self::invert(&denominator))
+    }
+
+    // Shamir sharing: the secret is the constant term of a random polynomial of
+    // degree threshold - 1, share i is f(i).
+    fn split(secret: Self::Scalar, policy: &SharePolicy) -> Result<Vec<KeyShare>, KeyShareError> {
+        let coefficients = Self::random_polynomial(secret, policy.threshold)?;
+        Ok((1..=policy.total)
+            .map(|index| KeyShare {
+                index,
+                value: Self::encode_scalar(&Self::evaluate_polynomial(&coefficients, index)),
+            })
+            .collect())
+    }
+
+    // Any `threshold` distinct shares interpolate the polynomial back at zero.
+    fn combine(shares: &[KeyShare], threshold: u32) -> Result<Self::Scalar, KeyShareError> {
+        let shares = threshold_shares(shares, threshold)?;
+        let indices = KeyServices::share_indices(shares)?;
+        shares.iter().try_fold(Self::scalar(0), |secret, share| {
+            let value = Self::decode_scalar(&share.value)?;
+            let lambda = Self::lagrange_coefficient(share.index, &indices);
+            Ok(Self::add(&secret, &Self::mul(&value, &lambda)))
+        })
+    }
+
+    // Interpolates the public key "in the exponent" from share public images,
+    // without ever combining the secret shares themselves.
+    fn shares_public_key(
+        shares: &[KeyShare],
+        threshold: u32,
+    ) -> Result<Self::Point, KeyShareError> {
+        let shares = threshold_shares(shares, threshold)?;
+        let indices = KeyServices::share_indices(shares)?;
+        let terms = shares
+            .iter()
+            .map(|share| {
+                let weighted = Self::mul(
+                    &Self::decode_scalar(&share.value)?,
+                    &Self::lagrange_coefficient(share.index, &indices),
+                );
+                Self::base_mul(&weighted)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::sum_points(&terms)
+    }
+
+    // Resharing: `threshold` old holders each deal a fresh sharing of their
+    // Lagrange-weighted share under the new policy. The new shares interpolate to
+    // the same secret, so the public key and every derived address stay put.
+    fn reshare_shares(
+        shares: &[KeyShare],
+        threshold: u32,
+        policy: &SharePolicy,
+    ) -> Result<Vec<KeyShare>, KeyShareError> {
+        let shares = threshold_shares(shares, threshold)?;
+        let indices = KeyServices::share_indices(shares)?;
+
+        let mut reshared = vec![Self::scalar(0); policy.total as usize];
+        for share in shares {
+            let weighted = Self::mul(
+                &Self::decode_scalar(&share.value)?,
+                &Self::lagrange_coefficient(share.index, &indices),
+            );
+            let sub_sharing = Self::random_polynomial(weighted, policy.threshold)?;
+            for (value, index) in reshared.iter_mut().zip(1..=policy.total) {
+                *value = Self::add(value, &Self::evaluate_polynomial(&sub_sharing, index));
+            }
+        }
+
+        let reshared = (1..=policy.total)
+            .zip(reshared)
+            .map(|(index, value)| KeyShare {
+                index,
+                value: Self::encode_scalar(&value),
+            })
+            .collect::<Vec<_>>();
+        if Self::shares_public_key(shares, threshold)?
+            != Self::shares_public_key(&reshared, policy.threshold)?
+        {
+            return Err(KeyShareError::PublicKeyMismatch);
+        }
+        Ok(reshared)
+    }
+
+    // Proactive refresh: a resharing under the same policy. The secret stays the
+    // same but every share changes, and shares from different epochs no longer
+    // interpolate together. Any threshold of holders can run it, so the offline
+    // recovery shares are replaced as well.
+    fn refresh_shares(
+        shares: &[KeyShare],
+        policy: &SharePolicy,
+    ) -> Result<Vec<KeyShare>, KeyShareError> {
+        Self::reshare_shares(shares, policy.threshold, policy)
+    }
+}
+
+fn threshold_shares(shares: &[KeyShare], threshold: u32) -> Result<&[KeyShare], KeyShareError> {
+    if shares.len() < threshold as usize {
+        return Err(KeyShareError::NotEnoughShares(threshold, shares.len()));
+    }
+    Ok(&shares[..threshold as usize])
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Secp256k1Group;
+
+impl ShareGroup for Secp256k1Group {
+    type Scalar = BigUint;
+    type Point = PublicKey;
+
+    fn random_scalar() -> Result<BigUint, KeyShareError> {
+        KeyServices::random_scalar()
+    }
+
+    fn scalar(value: u32) -> BigUint {
+        BigUint::from(value)
+    }
+
+    fn add(a: &BigUint, b: &BigUint) -> BigUint {
+        (a + b) % KeyServices::curve_order()
+    }
+
+    fn sub(a: &BigUint, b: &BigUint) -> BigUint {
+        let order = KeyServices::curve_order();
+        (&order + a - b % &order) % &order
+    }
+
+    fn mul(a: &BigUint, b: &BigUint) -> BigUint {
+        a * b % KeyServices::curve_order()
+    }
+
+    fn invert(value: &BigUint) -> BigUint {
+        KeyServices::invert_scalar(value)
+    }
+
+    fn encode_scalar(value: &BigUint) -> String {
+        KeyServices::encode_scalar(value)
+    }
+
+    fn decode_scalar(value: &str) -> Result<BigUint, KeyShareError> {
+        KeyServices::decode_scalar(value)
+    }
+
+    fn base_mul(scalar: &BigUint) -> Result<PublicKey, KeyShareError> {
+        KeyServices::scalar_base_mul(scalar)
+    }
+
+    fn point_mul(point: &PublicKey, scalar: &BigUint) -> Result<PublicKey, KeyShareError> {
+        KeyServices::point_mul(point, scalar)
+    }
+
+    fn sum_points(points: &[PublicKey]) -> Result<PublicKey, KeyShareError> {
+        PublicKey::combine_keys(&points.iter().collect::<Vec<_>>())
+            .map_err(|_| KeyShareError::InvalidPoint)
+    }
+
+    fn point_bytes(point: &PublicKey) -> Vec<u8> {
+        point.serialize().to_vec()
+    }
+
+    fn decode_point(bytes: &[u8]) -> Result<PublicKey, KeyShareError> {
+        PublicKey::from_slice(bytes).map_err(|_| KeyShareError::InvalidPoint)
+    }
+}
+
 impl KeyServices {
     pub fn generate_secret_key() -> Result<SecretKey, KeyGenerationError> {
         let mut rng = OsRng;
@@ -18,7 +304,301 @@ impl KeyServices {
         SecretKey::from_byte_array(&random_bytes).map_err(|_| KeyGenerationError::SecretKeyError)
     }
 
-    pub fn split_secret_key(secret_key: &[u8]) -> Result<Vec<Vec<(u8, u8)>>, basic_sharing::Error> {
-        basic_sharing::from_secrets(secret_key, 3, 3, None)
+    pub fn split_secret_key(
+        secret_key: &SecretKey,
+        policy: &SharePolicy,
+    ) -> Result<Vec<KeyShare>, KeyShareError> {
+        Secp256k1Group::split(BigUint::from_bytes_be(&secret_key.secret_bytes()), policy)
+    }
+
+    pub fn refresh_shares(
+        shares: &[KeyShare],
+        policy: &SharePolicy,
+    ) -> Result<Vec<KeyShare>, KeyShareError> {
+        Secp256k1Group::refresh_shares(shares, policy)
+    }
+
+    pub fn reshare_shares(
+        shares: &[KeyShare],
+        threshold: u32,
+        policy: &SharePolicy,
+    ) -> Result<Vec<KeyShare>, KeyShareError> {
+        Secp256k1Group::reshare_shares(shares, threshold, policy)
+    }
+
+    pub fn shares_public_key(
+        shares: &[KeyShare],
+        threshold: u32,
+    ) -> Result<PublicKey, KeyShareError> {
+        Secp256k1Group::shares_public_key(shares, threshold)
+    }
+
+    pub fn combine_shares(shares: &[KeyShare], threshold: u32) -> Result<SecretKey, KeyShareError> {
+        let secret = Secp256k1Group::combine(shares, threshold)?;
+        SecretKey::from_byte_array(&Self::scalar_bytes(&secret))
+            .map_err(|_| KeyShareError::MalformedShare)
+    }
+
+    pub fn share_indices(shares: &[KeyShare]) -> Result<Vec<u32>, KeyShareError> {
+        let mut seen = HashSet::new();
+        shares
+            .iter()
+            .map(|share| {
+                if share.index == 0 || !seen.insert(share.index) {
+                    return Err(KeyShareError::DuplicateIndex(share.index));
+                }
+                Ok(share.index)
+            })
+            .collect()
+    }
+
+    pub fn lagrange_coefficient(index: u32, indices: &[u32]) -> BigUint {
+        Secp256k1Group::lagrange_coefficient(index, indices)
+    }
+
+    pub fn scalar_base_mul(scalar: &BigUint) -> Result<PublicKey, KeyShareError> {
+        let secret = SecretKey::from_byte_array(&Self::scalar_bytes(scalar))
+            .map_err(|_| KeyShareError::InvalidPoint)?;
+        Ok(PublicKey::from_secret_key(
+            &Secp256k1::signing_only(),
+            &secret,
+        ))
+    }
+
+    pub fn point_mul(point: &PublicKey, scalar: &BigUint) -> Result<PublicKey, KeyShareError> {
+        let tweak = Scalar::from_be_bytes(Self::scalar_bytes(scalar))
+            .map_err(|_| KeyShareError::InvalidPoint)?;
+        point
+            .mul_tweak(&Secp256k1::verification_only(), &tweak)
+            .map_err(|_| KeyShareError::InvalidPoint)
+    }
+
+    pub fn curve_order() -> BigUint {
+        BigUint::from_bytes_be(&CURVE_ORDER)
+    }
+
+    pub fn random_scalar() -> Result<BigUint, KeyShareError> {
+        let key = Self::generate_secret_key().map_err(|_| KeyShareError::RandomScalarError)?;
+        Ok(BigUint::from_bytes_be(&key.secret_bytes()))
+    }
+
+    pub fn invert_scalar(value: &BigUint) -> BigUint {
+        let order = Self::curve_order();
+        value.modpow(&(&order - BigUint::from(2u32)), &order)
+    }
+
+    pub fn scalar_bytes(value: &BigUint) -> [u8; 32] {
+        let bytes = value.to_bytes_be();
+        let mut out = [0u8; 32];
+        out[32 - bytes.len()..].copy_from_slice(&bytes);
+        out
+    }
+
+    pub fn encode_scalar(value: &BigUint) -> String {
+        hex::encode(Self::scalar_bytes(value))
+    }
+
+    pub fn decode_scalar(value: &str) -> Result<BigUint, KeyShareError> {
+        let bytes = hex::decode(value).map_err(|_| KeyShareError::MalformedShare)?;
+        if bytes.len() != 32 {
+            return Err(KeyShareError::MalformedShare);
+        }
+        let scalar = BigUint::from_bytes_be(&bytes);
+        if scalar >= Self::curve_order() {
+            return Err(KeyShareError::MalformedShare);
+        }
+        Ok(scalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::chains_services::generate_chain_data;
+
+    fn secret_scalar(secret_key: &SecretKey) -> BigUint {
+        BigUint::from_bytes_be(&secret_key.secret_bytes())
+    }
+
+    #[test]
+    fn threshold_and_all_shares_recover_the_secret() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        assert_eq!(shares.len(), 3);
+
+        for pair in [[0, 1], [0, 2], [1, 2]] {
+            let subset = pair.map(|i| shares[i].clone());
+            assert_eq!(
+                KeyServices::combine_shares(&subset, policy.threshold).unwrap(),
+                secret_key
+            );
+        }
+        assert_eq!(
+            KeyServices::combine_shares(&shares, policy.threshold).unwrap(),
+            secret_key
+        );
+    }
+
+    #[test]
+    fn fewer_than_threshold_shares_are_rejected() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(3, 5).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+
+        assert!(matches!(
+            KeyServices::combine_shares(&shares[..2], policy.threshold),
+            Err(KeyShareError::NotEnoughShares(3, 2))
+        ));
+    }
+
+    // With t - 1 shares every secret is still possible: for any candidate there
+    // is a t-th share that completes the sharing to it, so the shares alone say
+    // nothing about which secret was dealt.
+    #[test]
+    fn fewer_than_threshold_shares_are_consistent_with_any_secret() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(3, 5).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        let known = &shares[..2];
+
+        let order = KeyServices::curve_order();
+        let candidate = KeyServices::generate_secret_key().unwrap();
+        let indices = [known[0].index, known[1].index, 5];
+        let partial = known.iter().fold(BigUint::ZERO, |acc, share| {
+            let value = KeyServices::decode_scalar(&share.value).unwrap();
+            (acc + value * KeyServices::lagrange_coefficient(share.index, &indices)) % &order
+        });
+        let missing = (&order + secret_scalar(&candidate) - partial) % &order
+            * KeyServices::invert_scalar(&KeyServices::lagrange_coefficient(5, &indices))
+            % &order;
+
+        let mut forged = known.to_vec();
+        forged.push(KeyShare {
+            index: 5,
+            value: KeyServices::encode_scalar(&missing),
+        });
+        assert_eq!(
+            KeyServices::combine_shares(&forged, policy.threshold).unwrap(),
+            candidate
+        );
+        assert_ne!(candidate, secret_key);
+    }
+
+    #[test]
+    fn duplicate_share_indices_are_rejected() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        let duplicated = [shares[0].clone(), shares[0].clone()];
+
+        assert!(matches!(
+            KeyServices::combine_shares(&duplicated, policy.threshold),
+            Err(KeyShareError::DuplicateIndex(1))
+        ));
+    }
+
+    #[test]
+    fn refreshed_shares_keep_the_secret() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        let refreshed = KeyServices::refresh_shares(&shares[1..], &policy).unwrap();
+
+        assert!(
+            shares
+                .iter()
+                .zip(&refreshed)
+                .all(|(old, new)| old.index == new.index && old.value != new.value)
+        );
+        for pair in [[0, 1], [0, 2], [1, 2]] {
+            let subset = pair.map(|i| refreshed[i].clone());
+            assert_eq!(
+                KeyServices::combine_shares(&subset, policy.threshold).unwrap(),
+                secret_key
+            );
+        }
+    }
+
+    #[test]
+    fn shares_from_different_epochs_do_not_combine() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        let refreshed = KeyServices::refresh_shares(&shares[..2], &policy).unwrap();
+
+        for (old, new) in [(0, 1), (0, 2), (1, 0), (2, 1)] {
+            let mixed = [shares[old].clone(), refreshed[new].clone()];
+            assert_ne!(
+                KeyServices::combine_shares(&mixed, policy.threshold).unwrap(),
+                secret_key
+            );
+        }
+    }
+
+    #[test]
+    fn resharing_keeps_the_evm_address() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+        let (address, _) = generate_chain_data(&public_key);
+
+        let mut policy = SharePolicy::new(2, 3).unwrap();
+        let mut shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        for (threshold, total) in [(3, 5), (2, 3), (2, 2), (4, 7)] {
+            let new_policy = SharePolicy::new(threshold, total).unwrap();
+            // The last holders take part, so a lost first share does not matter.
+            let old_holders = &shares[shares.len() - policy.threshold as usize..];
+            let reshared =
+                KeyServices::reshare_shares(old_holders, policy.threshold, &new_policy).unwrap();
+            assert_eq!(reshared.len(), total as usize);
+
+            let tail = &reshared[reshared.len() - threshold as usize..];
+            for subset in [&reshared[..threshold as usize], tail] {
+                let combined = KeyServices::combine_shares(subset, threshold).unwrap();
+                let public_key = KeyServices::shares_public_key(subset, threshold).unwrap();
+                assert_eq!(combined, secret_key);
+                assert_eq!(generate_chain_data(&public_key).0, address);
+            }
+            assert!(matches!(
+                KeyServices::combine_shares(&reshared[..threshold as usize - 1], threshold),
+                Err(KeyShareError::NotEnoughShares(_, _))
+            ));
+
+            policy = new_policy;
+            shares = reshared;
+        }
+    }
+
+    #[test]
+    fn resharing_needs_a_threshold_of_old_shares() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let policy = SharePolicy::new(3, 5).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+        let new_policy = SharePolicy::new(2, 3).unwrap();
+
+        assert!(matches!(
+            KeyServices::reshare_shares(&shares[..2], policy.threshold, &new_policy),
+            Err(KeyShareError::NotEnoughShares(3, 2))
+        ));
+    }
+
+    #[test]
+    fn server_holds_one_share_short_of_the_threshold() {
+        use ShareHolder::{Client, Recovery, Server};
+        let holders = |policy: SharePolicy| {
+            (1..=policy.total)
+                .map(|index| policy.holder(index))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            holders(SharePolicy::new(2, 3).unwrap()),
+            [Server, Recovery, Client]
+        );
+        assert_eq!(
+            holders(SharePolicy::new(3, 5).unwrap()),
+            [Server, Server, Recovery, Recovery, Client]
+        );
+        assert_eq!(holders(SharePolicy::new(2, 2).unwrap()), [Server, Client]);
     }
 }
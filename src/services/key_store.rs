@@ -0,0 +1,613 @@
+use async_trait::async_trait;
+use mongodb::{Collection, bson::doc};
+use std::{collections::HashMap, env, path::PathBuf, sync::Arc};
+use tokio::{fs, sync::RwLock};
+
+use crate::errors::key_store_errors::KeyStoreError;
+use crate::models::{
+    key_share_model::KeyShareDocument,
+    user_wallet_model::{EncryptedKeyShare, KeyShare},
+};
+use crate::services::encryption_services::EnvelopeEncryption;
+
+// Where encrypted key shares live. Shares are addressed by owner, share epoch and
+// share index, so a new epoch can be written in full before the old one is dropped.
+#[async_trait]
+pub trait KeyStore: Send + Sync {
+    async fn put_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        share: &EncryptedKeyShare,
+    ) -> Result<(), KeyStoreError>;
+
+    async fn get_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        index: u32,
+    ) -> Result<Option<EncryptedKeyShare>, KeyStoreError>;
+
+    async fn delete_share(&self, owner: &str, epoch: u32, index: u32) -> Result<(), KeyStoreError>;
+
+    // Swaps `current` for `share` only while `current` is still what is stored.
+    // Never creates a share, so a share deleted in the meantime stays deleted.
+    async fn replace_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        current: &EncryptedKeyShare,
+        share: &EncryptedKeyShare,
+    ) -> Result<bool, KeyStoreError>;
+}
+
+pub struct MongoKeyStore {
+    key_shares: Collection<KeyShareDocument>,
+}
+
+impl MongoKeyStore {
+    pub fn new(key_shares: Collection<KeyShareDocument>) -> Self {
+        MongoKeyStore { key_shares }
+    }
+}
+
+#[async_trait]
+impl KeyStore for MongoKeyStore {
+    async fn put_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        share: &EncryptedKeyShare,
+    ) -> Result<(), KeyStoreError> {
+        self.key_shares
+            .replace_one(
+                doc! {"owner": owner, "epoch": epoch, "share.index": share.index},
+                KeyShareDocument {
+                    id: None,
+                    owner: owner.to_string(),
+                    epoch,
+                    share: share.clone(),
+                },
+            )
+            .upsert(true)
+            .await
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        index: u32,
+    ) -> Result<Option<EncryptedKeyShare>, KeyStoreError> {
+        self.key_shares
+            .find_one(doc! {"owner": owner, "epoch": epoch, "share.index": index})
+            .await
+            .map(|document| document.map(|document| document.share))
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))
+    }
+
+    async fn delete_share(&self, owner: &str, epoch: u32, index: u32) -> Result<(), KeyStoreError> {
+        self.key_shares
+            .delete_one(doc! {"owner": owner, "epoch": epoch, "share.index": index})
+            .await
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn replace_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        current: &EncryptedKeyShare,
+        share: &EncryptedKeyShare,
+    ) -> Result<bool, KeyStoreError> {
+        self.key_shares
+            .replace_one(
+                doc! {
+                    "owner": owner,
+                    "epoch": epoch,
+                    "share.index": current.index,
+                    "share.key_version": current.key_version,
+                    "share.wrapped_key": &current.wrapped_key,
+                },
+                KeyShareDocument {
+                    id: None,
+                    owner: owner.to_string(),
+                    epoch,
+                    share: share.clone(),
+                },
+            )
+            .await
+            .map(|result| result.matched_count == 1)
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))
+    }
+}
+
+// One JSON file per share under `<root>/<owner>/`. The files only ever hold
+// envelope encrypted shares, so the directory is encrypted at rest as well.
+pub struct FileKeyStore {
+    root: PathBuf,
+}
+
+impl FileKeyStore {
+    pub fn new(root: PathBuf) -> Self {
+        FileKeyStore { root }
+    }
+
+    fn share_path(&self, owner: &str, epoch: u32, index: u32) -> PathBuf {
+        self.root
+            .join(owner)
+            .join(format!("{}-{}.json", epoch, index))
+    }
+}
+
+#[async_trait]
+impl KeyStore for FileKeyStore {
+    async fn put_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        share: &EncryptedKeyShare,
+    ) -> Result<(), KeyStoreError> {
+        let path = self.share_path(owner, epoch, share.index);
+        let contents =
+            serde_json::to_vec(share).map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        fs::create_dir_all(self.root.join(owner))
+            .await
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        fs::write(path, contents)
+            .await
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))
+    }
+
+    async fn get_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        index: u32,
+    ) -> Result<Option<EncryptedKeyShare>, KeyStoreError> {
+        match fs::read(self.share_path(owner, epoch, index)).await {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .map_err(|e| KeyStoreError::BackendError(e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(KeyStoreError::BackendError(e.to_string())),
+        }
+    }
+
+    async fn delete_share(&self, owner: &str, epoch: u32, index: u32) -> Result<(), KeyStoreError> {
+        match fs::remove_file(self.share_path(owner, epoch, index)).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(KeyStoreError::BackendError(e.to_string())),
+        }
+    }
+
+    // The new file is written next to the old one and renamed over it, so a
+    // reader never sees half a share.
+    async fn replace_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        current: &EncryptedKeyShare,
+        share: &EncryptedKeyShare,
+    ) -> Result<bool, KeyStoreError> {
+        match self.get_share(owner, epoch, current.index).await? {
+            Some(stored) if same_share(&stored, current) => {}
+            _ => return Ok(false),
+        }
+        let path = self.share_path(owner, epoch, share.index);
+        let staging = path.with_extension("json.tmp");
+        let contents =
+            serde_json::to_vec(share).map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        fs::write(&staging, contents)
+            .await
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        fs::rename(staging, path)
+            .await
+            .map_err(|e| KeyStoreError::BackendError(e.to_string()))?;
+        Ok(true)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryKeyStore {
+    shares: RwLock<HashMap<(String, u32, u32), EncryptedKeyShare>>,
+}
+
+#[async_trait]
+impl KeyStore for MemoryKeyStore {
+    async fn put_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        share: &EncryptedKeyShare,
+    ) -> Result<(), KeyStoreError> {
+        self.shares
+            .write()
+            .await
+            .insert((owner.to_string(), epoch, share.index), share.clone());
+        Ok(())
+    }
+
+    async fn get_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        index: u32,
+    ) -> Result<Option<EncryptedKeyShare>, KeyStoreError> {
+        Ok(self
+            .shares
+            .read()
+            .await
+            .get(&(owner.to_string(), epoch, index))
+            .cloned())
+    }
+
+    async fn delete_share(&self, owner: &str, epoch: u32, index: u32) -> Result<(), KeyStoreError> {
+        self.shares
+            .write()
+            .await
+            .remove(&(owner.to_string(), epoch, index));
+        Ok(())
+    }
+
+    async fn replace_share(
+        &self,
+        owner: &str,
+        epoch: u32,
+        current: &EncryptedKeyShare,
+        share: &EncryptedKeyShare,
+    ) -> Result<bool, KeyStoreError> {
+        let mut shares = self.shares.write().await;
+        match shares.get_mut(&(owner.to_string(), epoch, current.index)) {
+            Some(stored) if same_share(stored, current) => {
+                *stored = share.clone();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+fn same_share(stored: &EncryptedKeyShare, current: &EncryptedKeyShare) -> bool {
+    stored.key_version == current.key_version && stored.wrapped_key == current.wrapped_key
+}
+
+// Routes every share index to its own backend and takes care of envelope
+// encryption, so handlers only ever deal in plaintext shares.
+pub struct KeyVault {
+    backends: Vec<Arc<dyn KeyStore>>,
+    encryption: EnvelopeEncryption,
+}
+
+impl KeyVault {
+    pub fn new(backends: Vec<Arc<dyn KeyStore>>, encryption: EnvelopeEncryption) -> Self {
+        KeyVault {
+            backends,
+            encryption,
+        }
+    }
+
+    // KEY_STORE_BACKENDS lists one backend per share index, e.g. "mongo,file,memory"
+    // puts share 1 in MongoDB, share 2 on disk and share 3 in memory. Indices past
+    // the end of the list wrap around.
+    pub fn from_env(
+        key_shares: Collection<KeyShareDocument>,
+        encryption: EnvelopeEncryption,
+    ) -> Result<Self, KeyStoreError> {
+        let backends = env::var("KEY_STORE_BACKENDS").unwrap_or_else(|_| String::from("mongo"));
+        let root = match env::var("KEY_STORE_DIR") {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => PathBuf::from("key_shares"),
+        };
+
+        let mongo: Arc<dyn KeyStore> = Arc::new(MongoKeyStore::new(key_shares));
+        let file: Arc<dyn KeyStore> = Arc::new(FileKeyStore::new(root));
+        let memory: Arc<dyn KeyStore> = Arc::new(MemoryKeyStore::default());
+        let backends = backends
+            .split(',')
+            .map(|backend| match backend.trim() {
+                "mongo" => Ok(mongo.clone()),
+                "file" => Ok(file.clone()),
+                "memory" => Ok(memory.clone()),
+                other => Err(KeyStoreError::UnknownBackend(other.to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(backends, encryption))
+    }
+
+    pub fn encryption(&self) -> &EnvelopeEncryption {
+        &self.encryption
+    }
+
+    fn backend(&self, index: u32) -> &Arc<dyn KeyStore> {
+        &self.backends[(index as usize).saturating_sub(1) % self.backends.len()]
+    }
+
+    pub async fn store_shares(
+        &self,
+        owner: &str,
+        epoch: u32,
+        shares: &[KeyShare],
+    ) -> Result<(), KeyStoreError> {
+        for share in self.encryption.seal_shares(shares)? {
+            self.backend(share.index)
+                .put_share(owner, epoch, &share)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Loads the stored shares of indices 1..=total. An unreachable backend only
+    // costs its own share while the rest still add up to `required`; otherwise
+    // its error is returned, so an outage is not mistaken for missing shares.
+    pub async fn load_shares(
+        &self,
+        owner: &str,
+        epoch: u32,
+        total: u32,
+        required: u32,
+    ) -> Result<Vec<KeyShare>, KeyStoreError> {
+        let mut shares = Vec::new();
+        let mut backend_error = None;
+        for index in 1..=total {
+            match self.backend(index).get_share(owner, epoch, index).await {
+                Ok(Some(share)) => shares.push(self.encryption.open_share(&share)?),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(owner, epoch, index, error = %e, "key share backend failed");
+                    backend_error = Some(e);
+                }
+            }
+        }
+        if shares.len() < required as usize {
+            return Err(backend_error.unwrap_or(KeyStoreError::SharesUnavailable(
+                shares.len(),
+                required as usize,
+            )));
+        }
+        Ok(shares)
+    }
+
+    pub async fn delete_shares(
+        &self,
+        owner: &str,
+        epoch: u32,
+        total: u32,
+    ) -> Result<(), KeyStoreError> {
+        for index in 1..=total {
+            self.backend(index)
+                .delete_share(owner, epoch, index)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Re-wraps the data keys of every stored share under the current master key.
+    // A share that was retired or rewrapped since it was read is left alone, so
+    // racing a refresh never brings back an old epoch.
+    pub async fn rewrap_shares(
+        &self,
+        owner: &str,
+        epoch: u32,
+        total: u32,
+    ) -> Result<(), KeyStoreError> {
+        for index in 1..=total {
+            let backend = self.backend(index);
+            if let Some(share) = backend.get_share(owner, epoch, index).await? {
+                let rewrapped = self.encryption.rewrap_share(&share)?;
+                backend
+                    .replace_share(owner, epoch, &share, &rewrapped)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::encryption_errors::EncryptionError;
+    use crate::services::encryption_services::MasterKeyProvider;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct TestKeys {
+        versions: Mutex<u32>,
+    }
+
+    impl MasterKeyProvider for TestKeys {
+        fn current_version(&self) -> u32 {
+            *self.versions.lock().unwrap()
+        }
+
+        fn master_key(&self, version: u32) -> Result<[u8; 32], EncryptionError> {
+            Ok([version as u8; 32])
+        }
+
+        fn rotate(&self) -> Result<u32, EncryptionError> {
+            let mut versions = self.versions.lock().unwrap();
+            *versions += 1;
+            Ok(*versions)
+        }
+    }
+
+    fn encryption() -> EnvelopeEncryption {
+        let keys = TestKeys::default();
+        keys.rotate().unwrap();
+        EnvelopeEncryption::new(Box::new(keys))
+    }
+
+    fn shares(total: u32) -> Vec<KeyShare> {
+        (1..=total)
+            .map(|index| KeyShare {
+                index,
+                value: format!("{:064x}", index),
+            })
+            .collect()
+    }
+
+    struct UnreachableKeyStore;
+
+    #[async_trait]
+    impl KeyStore for UnreachableKeyStore {
+        async fn put_share(
+            &self,
+            _owner: &str,
+            _epoch: u32,
+            _share: &EncryptedKeyShare,
+        ) -> Result<(), KeyStoreError> {
+            Err(KeyStoreError::BackendError(String::from("unreachable")))
+        }
+
+        async fn get_share(
+            &self,
+            _owner: &str,
+            _epoch: u32,
+            _index: u32,
+        ) -> Result<Option<EncryptedKeyShare>, KeyStoreError> {
+            Err(KeyStoreError::BackendError(String::from("unreachable")))
+        }
+
+        async fn delete_share(
+            &self,
+            _owner: &str,
+            _epoch: u32,
+            _index: u32,
+        ) -> Result<(), KeyStoreError> {
+            Err(KeyStoreError::BackendError(String::from("unreachable")))
+        }
+
+        async fn replace_share(
+            &self,
+            _owner: &str,
+            _epoch: u32,
+            _current: &EncryptedKeyShare,
+            _share: &EncryptedKeyShare,
+        ) -> Result<bool, KeyStoreError> {
+            Err(KeyStoreError::BackendError(String::from("unreachable")))
+        }
+    }
+
+    // Drops every share right after it was read, like a refresh retiring the
+    // epoch while a rewrap is in flight.
+    #[derive(Default)]
+    struct RetiringKeyStore {
+        inner: MemoryKeyStore,
+    }
+
+    #[async_trait]
+    impl KeyStore for RetiringKeyStore {
+        async fn put_share(
+            &self,
+            owner: &str,
+            epoch: u32,
+            share: &EncryptedKeyShare,
+        ) -> Result<(), KeyStoreError> {
+            self.inner.put_share(owner, epoch, share).await
+        }
+
+        async fn get_share(
+            &self,
+            owner: &str,
+            epoch: u32,
+            index: u32,
+        ) -> Result<Option<EncryptedKeyShare>, KeyStoreError> {
+            let share = self.inner.get_share(owner, epoch, index).await?;
+            self.inner.delete_share(owner, epoch, index).await?;
+            Ok(share)
+        }
+
+        async fn delete_share(
+            &self,
+            owner: &str,
+            epoch: u32,
+            index: u32,
+        ) -> Result<(), KeyStoreError> {
+            self.inner.delete_share(owner, epoch, index).await
+        }
+
+        async fn replace_share(
+            &self,
+            owner: &str,
+            epoch: u32,
+            current: &EncryptedKeyShare,
+            share: &EncryptedKeyShare,
+        ) -> Result<bool, KeyStoreError> {
+            self.inner.replace_share(owner, epoch, current, share).await
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable_backend_is_not_reported_as_missing_shares() {
+        let memory: Arc<dyn KeyStore> = Arc::new(MemoryKeyStore::default());
+        let vault = KeyVault::new(vec![memory, Arc::new(UnreachableKeyStore)], encryption());
+        vault.store_shares("owner", 0, &shares(1)).await.unwrap();
+
+        assert!(matches!(
+            vault.load_shares("owner", 0, 2, 2).await,
+            Err(KeyStoreError::BackendError(_))
+        ));
+        // One reachable share is still enough when only one is needed.
+        assert_eq!(vault.load_shares("owner", 0, 2, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn missing_shares_are_reported_as_unavailable() {
+        let vault = KeyVault::new(vec![Arc::new(MemoryKeyStore::default())], encryption());
+        vault.store_shares("owner", 0, &shares(1)).await.unwrap();
+
+        assert!(matches!(
+            vault.load_shares("owner", 0, 3, 2).await,
+            Err(KeyStoreError::SharesUnavailable(1, 2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rewrap_moves_shares_to_the_new_master_key() {
+        let vault = KeyVault::new(vec![Arc::new(MemoryKeyStore::default())], encryption());
+        vault.store_shares("owner", 0, &shares(3)).await.unwrap();
+
+        let key_version = vault.encryption().rotate_master_key().unwrap();
+        vault.rewrap_shares("owner", 0, 3).await.unwrap();
+
+        for index in 1..=3 {
+            let share = vault
+                .backend(index)
+                .get_share("owner", 0, index)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(share.key_version, key_version);
+        }
+        assert_eq!(
+            vault.load_shares("owner", 0, 3, 3).await.unwrap()[2].value,
+            shares(3)[2].value
+        );
+    }
+
+    #[tokio::test]
+    async fn rewrap_does_not_restore_retired_shares() {
+        let store = Arc::new(RetiringKeyStore::default());
+        let vault = KeyVault::new(vec![store.clone()], encryption());
+        vault.store_shares("owner", 0, &shares(2)).await.unwrap();
+
+        vault.encryption().rotate_master_key().unwrap();
+        vault.rewrap_shares("owner", 0, 2).await.unwrap();
+
+        for index in 1..=2 {
+            assert!(
+                store
+                    .inner
+                    .get_share("owner", 0, index)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+}
@@ -1,3 +1,16 @@
 pub mod database;
 pub mod key_services;
-pub mod chains_services;
\ No newline at end of file
+pub mod chains_services;
+pub mod paillier;
+pub mod signing_services;
+pub mod dkg_services;
+pub mod encryption_services;
+pub mod key_store;
+pub mod derivation_services;
+pub mod eddsa_services;
+pub mod units_services;
+pub mod nonce_services;
+pub mod session_services;
+pub mod totp_services;
+pub mod webauthn_services;
+pub mod api_key_services;
@@ -0,0 +1,271 @@
+use mongodb::{
+    Collection,
+    bson::{DateTime, doc},
+    error::{ErrorKind, WriteFailure},
+};
+use std::time::Duration;
+
+use crate::chains::features::AccountNonces;
+use crate::errors::nonce_errors::NonceError;
+use crate::models::nonce_model::{NonceDocument, PendingNonce};
+
+// How long a reservation counts as in flight without the node knowing it. The
+// threshold signing rounds run between reserving and broadcasting.
+const NONCE_RESERVATION_TIMEOUT: Duration = Duration::from_secs(300);
+const NONCE_UPDATE_ATTEMPTS: usize = 10;
+const DUPLICATE_KEY: i32 = 11000;
+
+// Hands out nonces per (chain, address) so concurrent sends from one account
+// never share one. Every change is a compare-and-swap on the document version.
+pub struct NonceManager {
+    nonces: Collection<NonceDocument>,
+}
+
+impl NonceManager {
+    pub fn new(nonces: Collection<NonceDocument>) -> Self {
+        NonceManager { nonces }
+    }
+
+    // The next free nonce for `address`, given the node's view of the account.
+    // Any nonce the node is missing below our own counter is reused, lowest
+    // first, so a dropped or never broadcast transaction doesn't leave every
+    // later one stuck.
+    pub async fn reserve(
+        &self,
+        chain_id: &str,
+        address: &str,
+        chain: AccountNonces,
+    ) -> Result<u64, NonceError> {
+        self.update(chain_id, address, |document| {
+            reserve_nonce(document, chain, DateTime::now())
+        })
+        .await
+    }
+
+    pub async fn mark_sent(
+        &self,
+        chain_id: &str,
+        address: &str,
+        nonce: u64,
+        hash: &str,
+    ) -> Result<(), NonceError> {
+        self.update(chain_id, address, |document| {
+            if let Some(pending) = document
+                .pending
+                .iter_mut()
+                .find(|pending| pending.nonce == nonce as i64)
+            {
+                pending.hash = Some(hash.to_string());
+            }
+        })
+        .await
+    }
+
+    // Gives back a nonce whose transaction never reached the node. The latest
+    // reservation just rolls the counter back, older ones are left as a gap
+    // for `reserve` to fill.
+    pub async fn release(
+        &self,
+        chain_id: &str,
+        address: &str,
+        nonce: u64,
+    ) -> Result<(), NonceError> {
+        self.update(chain_id, address, |document| release_nonce(document, nonce))
+            .await
+    }
+
+    async fn update<T, F>(
+        &self,
+        chain_id: &str,
+        address: &str,
+        mut change: F,
+    ) -> Result<T, NonceError>
+    where
+        F: FnMut(&mut NonceDocument) -> T,
+    {
+        // EVM addresses are case insensitive.
+        let address = address.to_lowercase();
+        for _ in 0..NONCE_UPDATE_ATTEMPTS {
+            let mut document = self
+                .nonces
+                .find_one(doc! {"chain_id": chain_id, "address": &address})
+                .await
+                .map_err(|e| NonceError::Database(e.to_string()))?
+                .unwrap_or_else(|| NonceDocument {
+                    id: None,
+                    chain_id: chain_id.to_string(),
+                    address: address.clone(),
+                    version: 0,
+                    next_nonce: 0,
+                    pending: Vec::new(),
+                });
+            let version = document.version;
+            let result = change(&mut document);
+            document.version += 1;
+
+            // A missing document is inserted by the upsert. If another request
+            // inserted it first, the unique index rejects ours and we retry.
+            let saved = self
+                .nonces
+                .replace_one(
+                    doc! {"chain_id": chain_id, "address": &address, "version": version},
+                    &document,
+                )
+                .upsert(true)
+                .await;
+            match saved {
+                Ok(saved) if saved.matched_count == 1 || saved.upserted_id.is_some() => {
+                    return Ok(result);
+                }
+                Ok(_) => continue,
+                Err(e) if is_duplicate_key(&e) => continue,
+                Err(e) => return Err(NonceError::Database(e.to_string())),
+            }
+        }
+        Err(NonceError::Contention(address))
+    }
+}
+
+fn reserve_nonce(document: &mut NonceDocument, chain: AccountNonces, now: DateTime) -> u64 {
+    let confirmed = chain.confirmed as i64;
+    let first_unknown = chain.pending as i64;
+    document
+        .pending
+        .retain(|pending| pending.nonce >= confirmed);
+
+    // Between what the node knows and our counter, a nonce is only taken while
+    // its reservation is fresh. Released and long lost ones are gaps.
+    let in_flight = |nonce: i64| {
+        document.pending.iter().any(|pending| {
+            pending.nonce == nonce
+                && now.timestamp_millis() - pending.reserved_at.timestamp_millis()
+                    < NONCE_RESERVATION_TIMEOUT.as_millis() as i64
+        })
+    };
+    let gap = (first_unknown..document.next_nonce).find(|nonce| !in_flight(*nonce));
+    let nonce = match gap {
+        Some(gap) => {
+            document.pending.retain(|pending| pending.nonce != gap);
+            gap
+        }
+        None => {
+            let nonce = document.next_nonce.max(first_unknown);
+            document.next_nonce = nonce + 1;
+            nonce
+        }
+    };
+    document.pending.push(PendingNonce {
+        nonce,
+        hash: None,
+        reserved_at: now,
+    });
+    nonce as u64
+}
+
+fn release_nonce(document: &mut NonceDocument, nonce: u64) {
+    document
+        .pending
+        .retain(|pending| pending.nonce != nonce as i64);
+    if document.next_nonce == nonce as i64 + 1 {
+        document.next_nonce -= 1;
+    }
+}
+
+fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == DUPLICATE_KEY
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> NonceDocument {
+        NonceDocument {
+            id: None,
+            chain_id: String::from("1"),
+            address: String::from("0xabc"),
+            version: 0,
+            next_nonce: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    fn chain(confirmed: u64, pending: u64) -> AccountNonces {
+        AccountNonces { confirmed, pending }
+    }
+
+    fn reserved(document: &NonceDocument) -> Vec<i64> {
+        document
+            .pending
+            .iter()
+            .map(|pending| pending.nonce)
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_reservations_get_distinct_nonces() {
+        // The version check serializes concurrent reservations, so each one
+        // sees the document the one before it saved.
+        let mut document = document();
+        let now = DateTime::now();
+
+        let nonces: Vec<u64> = (0..3)
+            .map(|_| reserve_nonce(&mut document, chain(5, 5), now))
+            .collect();
+
+        assert_eq!(nonces, [5, 6, 7]);
+        assert_eq!(document.next_nonce, 8);
+        assert_eq!(reserved(&document), [5, 6, 7]);
+    }
+
+    #[test]
+    fn released_nonces_are_reused_before_the_chain_advances() {
+        let mut document = document();
+        let now = DateTime::now();
+        for _ in 0..3 {
+            reserve_nonce(&mut document, chain(5, 5), now);
+        }
+
+        // 5 is still being signed and 6 failed to broadcast.
+        release_nonce(&mut document, 6);
+        assert_eq!(reserve_nonce(&mut document, chain(5, 5), now), 6);
+
+        // Releasing the latest reservation rolls the counter back instead.
+        release_nonce(&mut document, 7);
+        assert_eq!(document.next_nonce, 7);
+        assert_eq!(reserve_nonce(&mut document, chain(5, 5), now), 7);
+        assert_eq!(reserved(&document), [5, 6, 7]);
+    }
+
+    #[test]
+    fn lost_reservations_are_reused_after_the_timeout() {
+        let mut document = document();
+        let then = DateTime::from_millis(0);
+        for _ in 0..3 {
+            reserve_nonce(&mut document, chain(5, 5), then);
+        }
+        // The node only ever saw 5, then 6 and 7 went missing.
+        let now = DateTime::from_millis(NONCE_RESERVATION_TIMEOUT.as_millis() as i64 + 1);
+
+        assert_eq!(reserve_nonce(&mut document, chain(5, 6), now), 6);
+        assert_eq!(reserve_nonce(&mut document, chain(5, 6), now), 7);
+        assert_eq!(reserve_nonce(&mut document, chain(5, 6), now), 8);
+    }
+
+    #[test]
+    fn chain_nonce_ahead_of_local_state_wins() {
+        // Transactions sent from elsewhere moved the account on.
+        let mut document = document();
+        let now = DateTime::now();
+        reserve_nonce(&mut document, chain(0, 0), now);
+        reserve_nonce(&mut document, chain(0, 0), now);
+
+        assert_eq!(reserve_nonce(&mut document, chain(10, 12), now), 12);
+        assert_eq!(document.next_nonce, 13);
+        // Reservations the chain has mined past are dropped.
+        assert_eq!(reserved(&document), [12]);
+    }
+}
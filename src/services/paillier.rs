@@ -0,0 +1,141 @@
+use num_bigint::BigUint;
+use rand::{TryRngCore, rngs::OsRng};
+
+use crate::errors::signing_errors::SigningError;
+
+const PRIME_BITS: usize = 1024;
+const MILLER_RABIN_ROUNDS: usize = 40;
+const SMALL_PRIMES: [u32; 24] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+#[derive(Debug, Clone)]
+pub struct PaillierPublicKey {
+    pub n: BigUint,
+    pub n_squared: BigUint,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaillierKeyPair {
+    pub public_key: PaillierPublicKey,
+    phi: BigUint,
+    mu: BigUint,
+}
+
+impl PaillierKeyPair {
+    pub fn generate() -> Result<Self, SigningError> {
+        loop {
+            let p = generate_prime(PRIME_BITS)?;
+            let q = generate_prime(PRIME_BITS)?;
+            if p == q {
+                continue;
+            }
+            let n = &p * &q;
+            let phi = (&p - 1u32) * (&q - 1u32);
+            // With g = n + 1, decryption only needs phi^-1 mod n.
+            let Some(mu) = phi.modinv(&n) else {
+                continue;
+            };
+            return Ok(PaillierKeyPair {
+                public_key: PaillierPublicKey {
+                    n_squared: &n * &n,
+                    n,
+                },
+                phi,
+                mu,
+            });
+        }
+    }
+
+    pub fn decrypt(&self, ciphertext: &BigUint) -> BigUint {
+        let n = &self.public_key.n;
+        let u = ciphertext.modpow(&self.phi, &self.public_key.n_squared);
+        ((u - 1u32) / n) * &self.mu % n
+    }
+}
+
+impl PaillierPublicKey {
+    pub fn encrypt(&self, message: &BigUint) -> Result<BigUint, SigningError> {
+        let r = loop {
+            let r = random_below(&self.n)?;
+            if r > BigUint::ZERO {
+                break r;
+            }
+        };
+        let g_m = (message * &self.n + 1u32) % &self.n_squared;
+        Ok(g_m * r.modpow(&self.n, &self.n_squared) % &self.n_squared)
+    }
+
+    pub fn add(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        left * right % &self.n_squared
+    }
+
+    pub fn mul_plain(&self, ciphertext: &BigUint, scalar: &BigUint) -> BigUint {
+        ciphertext.modpow(scalar, &self.n_squared)
+    }
+}
+
+pub fn random_bits(bits: usize) -> Result<BigUint, SigningError> {
+    let mut bytes = vec![0u8; bits.div_ceil(8)];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|_| SigningError::RandomnessError)?;
+    let extra = bytes.len() * 8 - bits;
+    bytes[0] &= 0xff >> extra;
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
+pub fn random_below(bound: &BigUint) -> Result<BigUint, SigningError> {
+    let bits = bound.bits() as usize;
+    loop {
+        let candidate = random_bits(bits)?;
+        if &candidate < bound {
+            return Ok(candidate);
+        }
+    }
+}
+
+fn generate_prime(bits: usize) -> Result<BigUint, SigningError> {
+    loop {
+        let mut candidate = random_bits(bits)?;
+        candidate.set_bit(bits as u64 - 1, true);
+        candidate.set_bit(bits as u64 - 2, true);
+        candidate.set_bit(0, true);
+        if is_probable_prime(&candidate)? {
+            return Ok(candidate);
+        }
+    }
+}
+
+fn is_probable_prime(candidate: &BigUint) -> Result<bool, SigningError> {
+    for small in SMALL_PRIMES {
+        if candidate % small == BigUint::ZERO {
+            return Ok(candidate == &BigUint::from(small));
+        }
+    }
+
+    let one = BigUint::from(1u32);
+    let minus_one = candidate - 1u32;
+    let rounds = minus_one.trailing_zeros().unwrap_or(0);
+    let odd = &minus_one >> rounds;
+
+    for _ in 0..MILLER_RABIN_ROUNDS {
+        let base = random_below(&(candidate - 3u32))? + 2u32;
+        let mut x = base.modpow(&odd, candidate);
+        if x == one || x == minus_one {
+            continue;
+        }
+        let mut composite = true;
+        for _ in 1..rounds {
+            x = x.modpow(&BigUint::from(2u32), candidate);
+            if x == minus_one {
+                composite = false;
+                break;
+            }
+        }
+        if composite {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
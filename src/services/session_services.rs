@@ -0,0 +1,399 @@
+use async_trait::async_trait;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use mongodb::{Collection, bson::DateTime, bson::doc};
+use rand::{TryRngCore, rngs::OsRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::RwLock;
+
+use crate::errors::session_errors::SessionError;
+use crate::models::session_model::RefreshTokenDocument;
+
+pub const ACCESS_TOKEN_TTL: Duration = Duration::from_secs(15 * 60);
+const REFRESH_TOKEN_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+pub const STEP_UP_TOKEN_TTL: Duration = Duration::from_secs(5 * 60);
+const STEP_UP_PURPOSE: &str = "step_up";
+const MIN_SECRET_LENGTH: usize = 32;
+
+// Claims of an access token. `sub` is the user id.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccessClaims {
+    pub sub: String,
+    pub email: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+// Claims of a step-up token, proof that `sub` passed a second factor.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StepUpClaims {
+    pub sub: String,
+    pub purpose: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+// Where refresh tokens live, addressed by the hash of the token.
+#[async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    async fn insert(&self, token: RefreshTokenDocument) -> Result<(), SessionError>;
+
+    // Marks an unused, unexpired token as used and returns it. Only one caller
+    // can consume a given token.
+    async fn consume(&self, token_hash: &str)
+    -> Result<Option<RefreshTokenDocument>, SessionError>;
+
+    async fn find_used(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshTokenDocument>, SessionError>;
+
+    async fn revoke_family(&self, family: &str) -> Result<(), SessionError>;
+}
+
+pub struct MongoRefreshTokenStore {
+    refresh_tokens: Collection<RefreshTokenDocument>,
+}
+
+impl MongoRefreshTokenStore {
+    pub fn new(refresh_tokens: Collection<RefreshTokenDocument>) -> Self {
+        MongoRefreshTokenStore { refresh_tokens }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenStore for MongoRefreshTokenStore {
+    async fn insert(&self, token: RefreshTokenDocument) -> Result<(), SessionError> {
+        self.refresh_tokens
+            .insert_one(token)
+            .await
+            .map_err(|e| SessionError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn consume(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshTokenDocument>, SessionError> {
+        self.refresh_tokens
+            .find_one_and_update(
+                doc! {
+                    "token_hash": token_hash,
+                    "used": false,
+                    "expires_at": {"$gt": DateTime::now()},
+                },
+                doc! {"$set": {"used": true}},
+            )
+            .await
+            .map_err(|e| SessionError::Database(e.to_string()))
+    }
+
+    async fn find_used(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshTokenDocument>, SessionError> {
+        self.refresh_tokens
+            .find_one(doc! {"token_hash": token_hash, "used": true})
+            .await
+            .map_err(|e| SessionError::Database(e.to_string()))
+    }
+
+    async fn revoke_family(&self, family: &str) -> Result<(), SessionError> {
+        self.refresh_tokens
+            .delete_many(doc! {"family": family})
+            .await
+            .map_err(|e| SessionError::Database(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryRefreshTokenStore {
+    tokens: RwLock<HashMap<String, RefreshTokenDocument>>,
+}
+
+#[async_trait]
+impl RefreshTokenStore for MemoryRefreshTokenStore {
+    async fn insert(&self, token: RefreshTokenDocument) -> Result<(), SessionError> {
+        self.tokens
+            .write()
+            .await
+            .insert(token.token_hash.clone(), token);
+        Ok(())
+    }
+
+    async fn consume(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshTokenDocument>, SessionError> {
+        let mut tokens = self.tokens.write().await;
+        match tokens.get_mut(token_hash) {
+            Some(token) if !token.used && token.expires_at > DateTime::now() => {
+                token.used = true;
+                Ok(Some(token.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn find_used(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshTokenDocument>, SessionError> {
+        Ok(self
+            .tokens
+            .read()
+            .await
+            .get(token_hash)
+            .filter(|token| token.used)
+            .cloned())
+    }
+
+    async fn revoke_family(&self, family: &str) -> Result<(), SessionError> {
+        self.tokens
+            .write()
+            .await
+            .retain(|_, token| token.family != family);
+        Ok(())
+    }
+}
+
+// Issues HS256 access tokens and rotating refresh tokens.
+pub struct SessionManager {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    refresh_tokens: Arc<dyn RefreshTokenStore>,
+}
+
+impl SessionManager {
+    pub fn new(secret: &[u8], refresh_tokens: Arc<dyn RefreshTokenStore>) -> Self {
+        SessionManager {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            refresh_tokens,
+        }
+    }
+
+    // JWT_SECRET signs access tokens. Without one, a random secret is used and
+    // every session ends when the server restarts.
+    pub fn from_env(
+        refresh_tokens: Collection<RefreshTokenDocument>,
+    ) -> Result<Self, SessionError> {
+        let secret = match env::var("JWT_SECRET") {
+            Ok(secret) if secret.len() >= MIN_SECRET_LENGTH => secret.into_bytes(),
+            Ok(_) => return Err(SessionError::WeakSecret(MIN_SECRET_LENGTH)),
+            Err(_) => random_bytes::<32>()?.to_vec(),
+        };
+        Ok(Self::new(
+            &secret,
+            Arc::new(MongoRefreshTokenStore::new(refresh_tokens)),
+        ))
+    }
+
+    pub fn access_token(&self, user_id: &str, email: &str) -> Result<String, SessionError> {
+        let iat = unix_time();
+        let claims = AccessClaims {
+            sub: user_id.to_string(),
+            email: email.to_string(),
+            iat,
+            exp: iat + ACCESS_TOKEN_TTL.as_secs(),
+        };
+        Ok(encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &self.encoding_key,
+        )?)
+    }
+
+    pub fn verify_access_token(&self, token: &str) -> Result<AccessClaims, SessionError> {
+        decode::<AccessClaims>(
+            token,
+            &self.decoding_key,
+            &Validation::new(Algorithm::HS256),
+        )
+        .map(|token| token.claims)
+        .map_err(|_| SessionError::InvalidToken)
+    }
+
+    pub fn step_up_token(&self, user_id: &str) -> Result<String, SessionError> {
+        let iat = unix_time();
+        let claims = StepUpClaims {
+            sub: user_id.to_string(),
+            purpose: String::from(STEP_UP_PURPOSE),
+            iat,
+            exp: iat + STEP_UP_TOKEN_TTL.as_secs(),
+        };
+        Ok(encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &self.encoding_key,
+        )?)
+    }
+
+    // Access tokens share the signing key, so the purpose tells them apart.
+    pub fn verify_step_up_token(&self, token: &str, user_id: &str) -> Result<(), SessionError> {
+        match decode::<StepUpClaims>(
+            token,
+            &self.decoding_key,
+            &Validation::new(Algorithm::HS256),
+        ) {
+            Ok(token) if token.claims.purpose == STEP_UP_PURPOSE && token.claims.sub == user_id => {
+                Ok(())
+            }
+            _ => Err(SessionError::InvalidToken),
+        }
+    }
+
+    // Starts a new refresh token family for `user_id`.
+    pub async fn refresh_token(&self, user_id: &str) -> Result<String, SessionError> {
+        let family = hex::encode(random_bytes::<16>()?);
+        self.issue_refresh_token(user_id, &family).await
+    }
+
+    // Consumes `token` and returns its user with the next token of its family.
+    pub async fn rotate_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<(String, String), SessionError> {
+        let token_hash = hash_token(token);
+        let consumed = self.refresh_tokens.consume(&token_hash).await?;
+        let Some(consumed) = consumed else {
+            return Err(self.reject_refresh_token(&token_hash).await);
+        };
+
+        let next = self
+            .issue_refresh_token(&consumed.user_id, &consumed.family)
+            .await?;
+        Ok((consumed.user_id, next))
+    }
+
+    // A token that was already consumed is revoked with its whole family.
+    async fn reject_refresh_token(&self, token_hash: &str) -> SessionError {
+        match self.refresh_tokens.find_used(token_hash).await {
+            Ok(Some(reused)) => match self.refresh_tokens.revoke_family(&reused.family).await {
+                Ok(()) => SessionError::TokenReused,
+                Err(e) => e,
+            },
+            Ok(None) => SessionError::InvalidToken,
+            Err(e) => e,
+        }
+    }
+
+    async fn issue_refresh_token(
+        &self,
+        user_id: &str,
+        family: &str,
+    ) -> Result<String, SessionError> {
+        let token = hex::encode(random_bytes::<32>()?);
+        let expires_at = DateTime::from_millis(
+            DateTime::now().timestamp_millis() + REFRESH_TOKEN_TTL.as_millis() as i64,
+        );
+        self.refresh_tokens
+            .insert(RefreshTokenDocument {
+                id: None,
+                token_hash: hash_token(&token),
+                user_id: user_id.to_string(),
+                family: family.to_string(),
+                used: false,
+                expires_at,
+            })
+            .await?;
+        Ok(token)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], SessionError> {
+    let mut bytes = [0u8; N];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|_| SessionError::RandomnessError)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sessions() -> SessionManager {
+        SessionManager::new(
+            &[7u8; MIN_SECRET_LENGTH],
+            Arc::new(MemoryRefreshTokenStore::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn rotation_issues_the_next_token() {
+        let sessions = sessions();
+        let first = sessions.refresh_token("user").await.unwrap();
+
+        let (user_id, second) = sessions.rotate_refresh_token(&first).await.unwrap();
+        assert_eq!(user_id, "user");
+        assert_ne!(first, second);
+
+        let (user_id, _) = sessions.rotate_refresh_token(&second).await.unwrap();
+        assert_eq!(user_id, "user");
+    }
+
+    #[tokio::test]
+    async fn reuse_revokes_the_whole_family() {
+        let sessions = sessions();
+        let first = sessions.refresh_token("user").await.unwrap();
+        let other = sessions.refresh_token("user").await.unwrap();
+        let (_, second) = sessions.rotate_refresh_token(&first).await.unwrap();
+
+        assert!(matches!(
+            sessions.rotate_refresh_token(&first).await,
+            Err(SessionError::TokenReused)
+        ));
+        // The token handed out after the stolen one is gone with its family.
+        assert!(matches!(
+            sessions.rotate_refresh_token(&second).await,
+            Err(SessionError::InvalidToken)
+        ));
+        // Other sessions of the same user are their own families.
+        assert!(sessions.rotate_refresh_token(&other).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unknown_and_expired_tokens_are_invalid() {
+        let store = Arc::new(MemoryRefreshTokenStore::default());
+        let sessions = SessionManager::new(&[7u8; MIN_SECRET_LENGTH], store.clone());
+        assert!(matches!(
+            sessions.rotate_refresh_token("unknown").await,
+            Err(SessionError::InvalidToken)
+        ));
+
+        store
+            .insert(RefreshTokenDocument {
+                id: None,
+                token_hash: hash_token("expired"),
+                user_id: String::from("user"),
+                family: String::from("family"),
+                used: false,
+                expires_at: DateTime::from_millis(DateTime::now().timestamp_millis() - 1000),
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            sessions.rotate_refresh_token("expired").await,
+            Err(SessionError::InvalidToken)
+        ));
+    }
+}
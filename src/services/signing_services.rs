@@ -0,0 +1,325 @@
+use num_bigint::BigUint;
+use secp256k1::{Message, PublicKey, Secp256k1, ecdsa::Signature as Secp256k1Signature};
+use std::collections::HashMap;
+
+use crate::errors::{key_errors::KeyShareError, signing_errors::SigningError};
+use crate::models::user_wallet_model::KeyShare;
+use crate::services::{
+    key_services::KeyServices,
+    paillier::{PaillierKeyPair, PaillierPublicKey, random_bits},
+};
+
+// Masks added by the MtA responder are drawn from [0, 2^MASK_BITS), large enough to
+// hide a product of two scalars and small enough never to wrap the Paillier modulus.
+const MASK_BITS: usize = 5 * 256;
+
+#[derive(Debug, Clone)]
+pub struct EcdsaSignature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub recovery_id: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignRoundOneMessage {
+    pub from: u32,
+    pub gamma_point: PublicKey,
+    pub encrypted_k: BigUint,
+    pub paillier_key: PaillierPublicKey,
+}
+
+#[derive(Debug, Clone)]
+pub struct MtaResponseMessage {
+    pub from: u32,
+    pub to: u32,
+    pub delta_ciphertext: BigUint,
+    pub sigma_ciphertext: BigUint,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignRoundTwoMessage {
+    pub from: u32,
+    pub delta: BigUint,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartialSignature {
+    pub from: u32,
+    pub s: BigUint,
+}
+
+// One share holder in a signing session. It only ever sees its own key share;
+// the cross terms k_i * gamma_j and k_i * w_j are computed with Paillier based
+// multiplicative-to-additive conversion (GG18 without the zero-knowledge proofs).
+pub struct SigningParty {
+    index: u32,
+    weighted_share: BigUint,
+    k: BigUint,
+    gamma: BigUint,
+    paillier: PaillierKeyPair,
+    delta_masks: HashMap<u32, BigUint>,
+    sigma_masks: HashMap<u32, BigUint>,
+    sigma: BigUint,
+}
+
+impl SigningParty {
+    pub fn new(share: &KeyShare, signers: &[u32]) -> Result<Self, SigningError> {
+        let order = KeyServices::curve_order();
+        let value = KeyServices::decode_scalar(&share.value)?;
+        let lambda = KeyServices::lagrange_coefficient(share.index, signers);
+        Ok(SigningParty {
+            index: share.index,
+            weighted_share: value * lambda % &order,
+            k: KeyServices::random_scalar()?,
+            gamma: KeyServices::random_scalar()?,
+            paillier: PaillierKeyPair::generate()?,
+            delta_masks: HashMap::new(),
+            sigma_masks: HashMap::new(),
+            sigma: BigUint::ZERO,
+        })
+    }
+
+    pub fn round_one(&self) -> Result<SignRoundOneMessage, SigningError> {
+        Ok(SignRoundOneMessage {
+            from: self.index,
+            gamma_point: KeyServices::scalar_base_mul(&self.gamma)?,
+            encrypted_k: self.paillier.public_key.encrypt(&self.k)?,
+            paillier_key: self.paillier.public_key.clone(),
+        })
+    }
+
+    pub fn mta_respond(
+        &mut self,
+        message: &SignRoundOneMessage,
+    ) -> Result<MtaResponseMessage, SigningError> {
+        let order = KeyServices::curve_order();
+        let key = &message.paillier_key;
+
+        let delta_mask = random_bits(MASK_BITS)?;
+        let delta_ciphertext = key.add(
+            &key.mul_plain(&message.encrypted_k, &self.gamma),
+            &key.encrypt(&delta_mask)?,
+        );
+        let sigma_mask = random_bits(MASK_BITS)?;
+        let sigma_ciphertext = key.add(
+            &key.mul_plain(&message.encrypted_k, &self.weighted_share),
+            &key.encrypt(&sigma_mask)?,
+        );
+
+        self.delta_masks
+            .insert(message.from, (&order - delta_mask % &order) % &order);
+        self.sigma_masks
+            .insert(message.from, (&order - sigma_mask % &order) % &order);
+
+        Ok(MtaResponseMessage {
+            from: self.index,
+            to: message.from,
+            delta_ciphertext,
+            sigma_ciphertext,
+        })
+    }
+
+    pub fn round_two(
+        &mut self,
+        responses: &[MtaResponseMessage],
+    ) -> Result<SignRoundTwoMessage, SigningError> {
+        let order = KeyServices::curve_order();
+        let mut delta = &self.k * &self.gamma % &order;
+        let mut sigma = &self.k * &self.weighted_share % &order;
+
+        for response in responses
+            .iter()
+            .filter(|response| response.to == self.index)
+        {
+            let alpha = self.paillier.decrypt(&response.delta_ciphertext) % &order;
+            let mu = self.paillier.decrypt(&response.sigma_ciphertext) % &order;
+            let (Some(beta), Some(nu)) = (
+                self.delta_masks.get(&response.from),
+                self.sigma_masks.get(&response.from),
+            ) else {
+                return Err(SigningError::SessionAborted(format!(
+                    "no MtA state for party {}",
+                    response.from
+                )));
+            };
+            delta = (delta + alpha + beta) % &order;
+            sigma = (sigma + mu + nu) % &order;
+        }
+
+        self.sigma = sigma;
+        Ok(SignRoundTwoMessage {
+            from: self.index,
+            delta,
+        })
+    }
+
+    pub fn partial_signature(&self, message_hash: &BigUint, r: &BigUint) -> PartialSignature {
+        let order = KeyServices::curve_order();
+        PartialSignature {
+            from: self.index,
+            s: (message_hash * &self.k + r * &self.sigma) % &order,
+        }
+    }
+}
+
+// Coordinates a signing session between `threshold` share holders. The
+// coordinator only relays messages and adds up public values; no party and no
+// step of the session ever holds the full private key.
+#[derive(Clone)]
+pub struct ThresholdSigner {
+    shares: Vec<KeyShare>,
+    threshold: u32,
+    public_key: PublicKey,
+}
+
+impl ThresholdSigner {
+    pub fn new(
+        shares: Vec<KeyShare>,
+        threshold: u32,
+        public_key: &str,
+    ) -> Result<Self, SigningError> {
+        let public_key = hex::decode(public_key)
+            .ok()
+            .and_then(|bytes| PublicKey::from_slice(&bytes).ok())
+            .ok_or(SigningError::InvalidPublicKey)?;
+        if shares.len() < threshold as usize {
+            return Err(KeyShareError::NotEnoughShares(threshold, shares.len()).into());
+        }
+        Ok(ThresholdSigner {
+            shares,
+            threshold,
+            public_key,
+        })
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn sign_hash(&self, hash: [u8; 32]) -> Result<EcdsaSignature, SigningError> {
+        let order = KeyServices::curve_order();
+        let shares = &self.shares[..self.threshold as usize];
+        let signers = KeyServices::share_indices(shares)?;
+        let mut parties = shares
+            .iter()
+            .map(|share| SigningParty::new(share, &signers))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let round_one = parties
+            .iter()
+            .map(SigningParty::round_one)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut responses = Vec::new();
+        for party in parties.iter_mut() {
+            let index = party.index;
+            for message in round_one.iter().filter(|message| message.from != index) {
+                responses.push(party.mta_respond(message)?);
+            }
+        }
+
+        let round_two = parties
+            .iter_mut()
+            .map(|party| party.round_two(&responses))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // R = (sum gamma_i * G) * (sum delta_i)^-1 = k^-1 * G
+        let delta = round_two.iter().fold(BigUint::ZERO, |acc, message| {
+            (acc + &message.delta) % &order
+        });
+        if delta == BigUint::ZERO {
+            return Err(SigningError::SessionAborted(String::from("delta is zero")));
+        }
+        let gamma_points = round_one
+            .iter()
+            .map(|message| &message.gamma_point)
+            .collect::<Vec<_>>();
+        let gamma = PublicKey::combine_keys(&gamma_points)
+            .map_err(|_| SigningError::SessionAborted(String::from("invalid gamma points")))?;
+        let r_point = KeyServices::point_mul(&gamma, &KeyServices::invert_scalar(&delta))?;
+        let r_bytes = r_point.serialize();
+        let r = BigUint::from_bytes_be(&r_bytes[1..]) % &order;
+        if r == BigUint::ZERO {
+            return Err(SigningError::SessionAborted(String::from("r is zero")));
+        }
+
+        let message_hash = BigUint::from_bytes_be(&hash) % &order;
+        let mut s = parties
+            .iter()
+            .map(|party| party.partial_signature(&message_hash, &r))
+            .fold(BigUint::ZERO, |acc, partial| (acc + partial.s) % &order);
+
+        let mut recovery_id = r_bytes[0] & 1;
+        if s > &order >> 1 {
+            s = &order - s;
+            recovery_id ^= 1;
+        }
+
+        let signature = EcdsaSignature {
+            r: KeyServices::scalar_bytes(&r),
+            s: KeyServices::scalar_bytes(&s),
+            recovery_id,
+        };
+        self.verify(&hash, &signature)?;
+        Ok(signature)
+    }
+
+    fn verify(&self, hash: &[u8; 32], signature: &EcdsaSignature) -> Result<(), SigningError> {
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&signature.r);
+        compact[32..].copy_from_slice(&signature.s);
+        let signature = Secp256k1Signature::from_compact(&compact)
+            .map_err(|_| SigningError::InvalidSignature)?;
+        Secp256k1::verification_only()
+            .verify_ecdsa(&Message::from_digest(*hash), &signature, &self.public_key)
+            .map_err(|_| SigningError::InvalidSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{chains_services::generate_chain_data, key_services::SharePolicy};
+    use ethers::{
+        types::{Address, Signature, U256},
+        utils::keccak256,
+    };
+
+    #[test]
+    fn threshold_signature_recovers_the_wallet_address() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+        let (address, public_key_hex) = generate_chain_data(&public_key);
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+
+        // Any two holders can sign, here the second and the third.
+        let signer =
+            ThresholdSigner::new(shares[1..].to_vec(), policy.threshold, &public_key_hex).unwrap();
+        let hash = keccak256(b"threshold signing test");
+        let signature = signer.sign_hash(hash).unwrap();
+
+        let recovered = Signature {
+            r: U256::from_big_endian(&signature.r),
+            s: U256::from_big_endian(&signature.s),
+            v: 27 + signature.recovery_id as u64,
+        }
+        .recover(hash)
+        .unwrap();
+        assert_eq!(recovered, address.parse::<Address>().unwrap());
+    }
+
+    #[test]
+    fn signer_needs_a_threshold_of_shares() {
+        let secret_key = KeyServices::generate_secret_key().unwrap();
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key);
+        let (_, public_key_hex) = generate_chain_data(&public_key);
+        let policy = SharePolicy::new(2, 3).unwrap();
+        let shares = KeyServices::split_secret_key(&secret_key, &policy).unwrap();
+
+        assert!(matches!(
+            ThresholdSigner::new(shares[..1].to_vec(), policy.threshold, &public_key_hex),
+            Err(SigningError::KeyShare(KeyShareError::NotEnoughShares(2, 1)))
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+use bcrypt::{DEFAULT_COST, hash};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{TryRngCore, rngs::OsRng};
+use sha1::Sha1;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::errors::two_factor_errors::TwoFactorError;
+
+// RFC 6238 defaults, the only parameters most authenticator apps support.
+const TOTP_ISSUER: &str = "MPC-Signer-Wallet";
+const TOTP_PERIOD: u64 = 30;
+const TOTP_DIGITS: u32 = 6;
+const SECRET_LENGTH: usize = 20;
+// Codes from one step either side are accepted to allow for clock drift.
+const ALLOWED_DRIFT: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+// A new base32 secret for an authenticator app.
+pub fn generate_secret() -> Result<String, TwoFactorError> {
+    let mut secret = [0u8; SECRET_LENGTH];
+    OsRng
+        .try_fill_bytes(&mut secret)
+        .map_err(|_| TwoFactorError::RandomnessError)?;
+    Ok(BASE32_NOPAD.encode(&secret))
+}
+
+// The key URI authenticator apps read from a QR code.
+pub fn otpauth_uri(account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(TOTP_ISSUER),
+        percent_encode(account),
+        secret,
+        percent_encode(TOTP_ISSUER),
+        TOTP_DIGITS,
+        TOTP_PERIOD,
+    )
+}
+
+// The code of `secret` for the time step `step`, as in RFC 4226.
+pub fn totp_code(secret: &str, step: u64) -> Result<u32, TwoFactorError> {
+    let key = BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|_| TwoFactorError::InvalidSecret)?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).map_err(|_| TwoFactorError::InvalidSecret)?;
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    Ok(binary % 10u32.pow(TOTP_DIGITS))
+}
+
+// The time step `code` was generated for, if it is valid now and newer than
+// `last_step`.
+pub fn verify_totp(
+    secret: &str,
+    code: &str,
+    last_step: i64,
+    unix_time: u64,
+) -> Result<Option<i64>, TwoFactorError> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let Ok(code) = code.parse::<u32>() else {
+        return Ok(None);
+    };
+
+    let current = (unix_time / TOTP_PERIOD) as i64;
+    for step in current - ALLOWED_DRIFT..=current + ALLOWED_DRIFT {
+        if step > last_step && step >= 0 && totp_code(secret, step as u64)? == code {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+// One-time recovery codes, returned in plain text once and stored as bcrypt
+// hashes like the password.
+pub fn recovery_codes() -> Result<(Vec<String>, Vec<String>), TwoFactorError> {
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    let mut hashes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let mut bytes = [0u8; 5];
+        OsRng
+            .try_fill_bytes(&mut bytes)
+            .map_err(|_| TwoFactorError::RandomnessError)?;
+        let code = BASE32_NOPAD.encode(&bytes).to_lowercase();
+        hashes.push(hash(&code, DEFAULT_COST)?);
+        codes.push(code);
+    }
+    Ok((codes, hashes))
+}
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The RFC 6238 SHA-1 seed, "12345678901234567890".
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn code_at(unix_time: u64) -> String {
+        format!(
+            "{:06}",
+            totp_code(RFC_SECRET, unix_time / TOTP_PERIOD).unwrap()
+        )
+    }
+
+    // RFC 6238 appendix B, truncated to six digits.
+    #[test]
+    fn codes_match_rfc_6238_vectors() {
+        let vectors = [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+            (20000000000, 353130),
+        ];
+        for (time, code) in vectors {
+            assert_eq!(totp_code(RFC_SECRET, time / TOTP_PERIOD).unwrap(), code);
+        }
+    }
+
+    #[test]
+    fn codes_within_the_drift_window_are_accepted() {
+        let now = 1111111111;
+        let step = (now / TOTP_PERIOD) as i64;
+        assert_eq!(
+            verify_totp(RFC_SECRET, &code_at(now), -1, now).unwrap(),
+            Some(step)
+        );
+        assert_eq!(
+            verify_totp(RFC_SECRET, &code_at(now - TOTP_PERIOD), -1, now).unwrap(),
+            Some(step - 1)
+        );
+        assert_eq!(
+            verify_totp(RFC_SECRET, &code_at(now - 2 * TOTP_PERIOD), -1, now).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn used_codes_are_rejected() {
+        let now = 1111111111;
+        let code = code_at(now);
+        let step = verify_totp(RFC_SECRET, &code, -1, now).unwrap().unwrap();
+
+        assert_eq!(verify_totp(RFC_SECRET, &code, step, now).unwrap(), None);
+        // Still inside the drift window half a minute later.
+        assert_eq!(
+            verify_totp(RFC_SECRET, &code, step, now + TOTP_PERIOD).unwrap(),
+            None
+        );
+        // A code older than the last accepted one is a replay too.
+        assert_eq!(
+            verify_totp(RFC_SECRET, &code_at(now - TOTP_PERIOD), step, now).unwrap(),
+            None
+        );
+        assert_eq!(
+            verify_totp(RFC_SECRET, &code_at(now + TOTP_PERIOD), step, now).unwrap(),
+            Some(step + 1)
+        );
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        let now = 1111111111;
+        let code = code_at(now);
+        for code in [&code[1..], "12345a", "+12345", "1234567", ""] {
+            assert_eq!(verify_totp(RFC_SECRET, code, -1, now).unwrap(), None);
+        }
+        assert!(matches!(
+            verify_totp("not base32!", &code, -1, now),
+            Err(TwoFactorError::InvalidSecret)
+        ));
+    }
+}
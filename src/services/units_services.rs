@@ -0,0 +1,101 @@
+use ethers::types::U256;
+
+use crate::errors::amount_errors::AmountError;
+
+// Converts a decimal string such as "1.5" into base units of a token with
+// `decimals` places. Unlike ethers' parse_units, excess precision is rejected
+// rather than silently truncated.
+pub fn parse_units(amount: &str, decimals: u32) -> Result<U256, AmountError> {
+    let amount = amount.trim();
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(AmountError::InvalidAmount(amount.to_string()));
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(AmountError::TooManyDecimals(decimals));
+    }
+    let scale = U256::from(10)
+        .checked_pow(U256::from(decimals))
+        .ok_or(AmountError::Overflow)?;
+    let whole = match whole {
+        "" => U256::zero(),
+        whole => U256::from_dec_str(whole).map_err(|_| AmountError::Overflow)?,
+    };
+    let fraction = match fraction {
+        "" => U256::zero(),
+        fraction => {
+            U256::from_dec_str(fraction).map_err(|_| AmountError::Overflow)?
+                * U256::from(10).pow(U256::from(decimals as usize - fraction.len()))
+        }
+    };
+    whole
+        .checked_mul(scale)
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or(AmountError::Overflow)
+}
+
+// The inverse of `parse_units`, without trailing zeros.
+pub fn format_units(value: U256, decimals: u32) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let digits = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{}.{}", whole, fraction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eighteen_decimals_round_trip() {
+        let wei = parse_units("1.5", 18).unwrap();
+
+        assert_eq!(wei, U256::from(1_500_000_000_000_000_000u64));
+        assert_eq!(format_units(wei, 18), "1.5");
+        assert_eq!(parse_units(".000000000000000001", 18).unwrap(), U256::one());
+        assert_eq!(format_units(U256::one(), 18), "0.000000000000000001");
+        assert_eq!(format_units(parse_units("42", 18).unwrap(), 18), "42");
+    }
+
+    #[test]
+    fn excess_precision_is_rejected() {
+        assert!(matches!(
+            parse_units("1.5", 0),
+            Err(AmountError::TooManyDecimals(0))
+        ));
+        assert_eq!(parse_units("1.50", 1).unwrap(), U256::from(15));
+        assert_eq!(parse_units("7.000", 0).unwrap(), U256::from(7));
+    }
+
+    #[test]
+    fn values_above_u256_max_are_rejected() {
+        let max = U256::MAX.to_string();
+        let above =
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+
+        assert_eq!(parse_units(&max, 0).unwrap(), U256::MAX);
+        assert!(matches!(parse_units(above, 0), Err(AmountError::Overflow)));
+        assert!(matches!(parse_units(&max, 1), Err(AmountError::Overflow)));
+    }
+
+    #[test]
+    fn malformed_amounts_are_rejected() {
+        for amount in ["", ".", "-1", "1e18", "1.2.3", "0x10", " "] {
+            assert!(
+                matches!(parse_units(amount, 18), Err(AmountError::InvalidAmount(_))),
+                "{:?} was accepted",
+                amount
+            );
+        }
+    }
+}
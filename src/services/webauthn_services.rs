@@ -0,0 +1,749 @@
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use mongodb::{
+    Collection,
+    bson::{DateTime, doc},
+};
+use p256::ecdsa::{Signature, VerifyingKey, signature::Verifier};
+use rand::{TryRngCore, rngs::OsRng};
+use serde::{Deserialize, Deserializer as _};
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, env, sync::Arc, time::Duration};
+use tokio::sync::RwLock;
+
+use crate::errors::two_factor_errors::TwoFactorError;
+use crate::models::{
+    user_wallet_model::PasskeyCredential,
+    webauthn_model::{WebauthnCeremony, WebauthnChallengeDocument},
+};
+
+pub const CEREMONY_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const CHALLENGE_LENGTH: usize = 32;
+
+// Authenticator data flags, WebAuthn §6.1.
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+// COSE key parameters of an ES256 key, RFC 9053.
+const COSE_KTY: i128 = 1;
+const COSE_ALG: i128 = 3;
+const COSE_CRV: i128 = -1;
+const COSE_X: i128 = -2;
+const COSE_Y: i128 = -3;
+const COSE_KTY_EC2: i128 = 2;
+pub const COSE_ALG_ES256: i128 = -7;
+const COSE_CRV_P256: i128 = 1;
+
+#[derive(Deserialize)]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+}
+
+// A credential from a verified registration, ready to be stored.
+#[derive(Debug)]
+pub struct RegisteredCredential {
+    pub credential_id: String,
+    pub public_key: String,
+    pub sign_count: u32,
+}
+
+// Authenticator data once the RP ID and user presence are checked.
+// `credential` is the id and COSE key of a newly attested credential.
+struct AuthenticatorData {
+    sign_count: u32,
+    credential: Option<(Vec<u8>, BTreeMap<Value, Value>)>,
+}
+
+// Where outstanding challenges live until the browser answers them.
+#[async_trait]
+pub trait ChallengeStore: Send + Sync {
+    async fn insert(&self, challenge: WebauthnChallengeDocument) -> Result<(), TwoFactorError>;
+
+    // Removes an unexpired challenge and reports whether there was one, so a
+    // challenge is answered at most once.
+    async fn consume(
+        &self,
+        user_id: &str,
+        ceremony: WebauthnCeremony,
+        challenge: &str,
+    ) -> Result<bool, TwoFactorError>;
+}
+
+pub struct MongoChallengeStore {
+    challenges: Collection<WebauthnChallengeDocument>,
+}
+
+impl MongoChallengeStore {
+    pub fn new(challenges: Collection<WebauthnChallengeDocument>) -> Self {
+        MongoChallengeStore { challenges }
+    }
+}
+
+#[async_trait]
+impl ChallengeStore for MongoChallengeStore {
+    async fn insert(&self, challenge: WebauthnChallengeDocument) -> Result<(), TwoFactorError> {
+        self.challenges
+            .insert_one(challenge)
+            .await
+            .map_err(|e| TwoFactorError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn consume(
+        &self,
+        user_id: &str,
+        ceremony: WebauthnCeremony,
+        challenge: &str,
+    ) -> Result<bool, TwoFactorError> {
+        let consumed = self
+            .challenges
+            .find_one_and_delete(doc! {
+                "user_id": user_id,
+                "ceremony": mongodb::bson::to_bson(&ceremony)
+                    .map_err(|e| TwoFactorError::Database(e.to_string()))?,
+                "challenge": challenge,
+                "expires_at": {"$gt": DateTime::now()},
+            })
+            .await
+            .map_err(|e| TwoFactorError::Database(e.to_string()))?;
+        Ok(consumed.is_some())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryChallengeStore {
+    challenges: RwLock<Vec<WebauthnChallengeDocument>>,
+}
+
+#[async_trait]
+impl ChallengeStore for MemoryChallengeStore {
+    async fn insert(&self, challenge: WebauthnChallengeDocument) -> Result<(), TwoFactorError> {
+        self.challenges.write().await.push(challenge);
+        Ok(())
+    }
+
+    async fn consume(
+        &self,
+        user_id: &str,
+        ceremony: WebauthnCeremony,
+        challenge: &str,
+    ) -> Result<bool, TwoFactorError> {
+        let mut challenges = self.challenges.write().await;
+        let Some(position) = challenges.iter().position(|stored| {
+            stored.user_id == user_id
+                && stored.ceremony == ceremony
+                && stored.challenge == challenge
+                && stored.expires_at > DateTime::now()
+        }) else {
+            return Ok(false);
+        };
+        challenges.remove(position);
+        Ok(true)
+    }
+}
+
+// Runs the registration and authentication ceremonies for passkeys. Only
+// ES256 credentials are accepted and attestation statements are not checked,
+// which is what `attestation: "none"` asks the browser for anyway.
+pub struct WebauthnManager {
+    rp_id: String,
+    rp_name: String,
+    origin: String,
+    challenges: Arc<dyn ChallengeStore>,
+}
+
+impl WebauthnManager {
+    pub fn new(
+        rp_id: &str,
+        rp_name: &str,
+        origin: &str,
+        challenges: Arc<dyn ChallengeStore>,
+    ) -> Result<Self, TwoFactorError> {
+        // Browsers only use an RP ID that is the origin's host or a parent of it.
+        let host = reqwest::Url::parse(origin)
+            .ok()
+            .and_then(|origin| origin.host_str().map(str::to_string))
+            .ok_or_else(|| TwoFactorError::Config(format!("invalid origin {}", origin)))?;
+        if host != rp_id && !host.ends_with(&format!(".{}", rp_id)) {
+            return Err(TwoFactorError::Config(format!(
+                "RP ID {} does not match origin {}",
+                rp_id, origin
+            )));
+        }
+        Ok(WebauthnManager {
+            rp_id: rp_id.to_string(),
+            rp_name: rp_name.to_string(),
+            origin: origin.trim_end_matches('/').to_string(),
+            challenges,
+        })
+    }
+
+    // WEBAUTHN_RP_ID and WEBAUTHN_ORIGIN must name the web app the passkeys
+    // are used from, they default to a local setup.
+    pub fn from_env(
+        challenges: Collection<WebauthnChallengeDocument>,
+    ) -> Result<Self, TwoFactorError> {
+        let rp_id = env::var("WEBAUTHN_RP_ID").unwrap_or_else(|_| String::from("localhost"));
+        let rp_name =
+            env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| String::from("MPC-Signer-Wallet"));
+        let origin =
+            env::var("WEBAUTHN_ORIGIN").unwrap_or_else(|_| String::from("http://localhost:9000"));
+        Self::new(
+            &rp_id,
+            &rp_name,
+            &origin,
+            Arc::new(MongoChallengeStore::new(challenges)),
+        )
+    }
+
+    pub fn rp_id(&self) -> &str {
+        &self.rp_id
+    }
+
+    pub fn rp_name(&self) -> &str {
+        &self.rp_name
+    }
+
+    // A new base64url challenge for `user_id`, valid for one ceremony.
+    pub async fn challenge(
+        &self,
+        user_id: &str,
+        ceremony: WebauthnCeremony,
+    ) -> Result<String, TwoFactorError> {
+        let mut challenge = [0u8; CHALLENGE_LENGTH];
+        OsRng
+            .try_fill_bytes(&mut challenge)
+            .map_err(|_| TwoFactorError::RandomnessError)?;
+        let challenge = URL_SAFE_NO_PAD.encode(challenge);
+        let expires_at = DateTime::from_millis(
+            DateTime::now().timestamp_millis() + CEREMONY_TIMEOUT.as_millis() as i64,
+        );
+        self.challenges
+            .insert(WebauthnChallengeDocument {
+                id: None,
+                user_id: user_id.to_string(),
+                ceremony,
+                challenge: challenge.clone(),
+                expires_at,
+            })
+            .await?;
+        Ok(challenge)
+    }
+
+    // Checks a `navigator.credentials.create()` response and returns the new
+    // credential. Both inputs are the raw bytes the browser returned.
+    pub async fn verify_registration(
+        &self,
+        user_id: &str,
+        client_data_json: &[u8],
+        attestation_object: &[u8],
+    ) -> Result<RegisteredCredential, TwoFactorError> {
+        self.verify_client_data(user_id, WebauthnCeremony::Registration, client_data_json)
+            .await?;
+
+        let attestation = match serde_cbor::from_slice::<Value>(attestation_object) {
+            Ok(Value::Map(attestation)) => attestation,
+            _ => return Err(TwoFactorError::MalformedResponse("attestation object")),
+        };
+        let Some(Value::Bytes(auth_data)) = attestation.get(&Value::Text(String::from("authData")))
+        else {
+            return Err(TwoFactorError::MalformedResponse("attestation object"));
+        };
+        let auth_data = self.parse_authenticator_data(auth_data)?;
+        let Some((credential_id, cose_key)) = auth_data.credential else {
+            return Err(TwoFactorError::MalformedResponse("attested credential"));
+        };
+
+        Ok(RegisteredCredential {
+            credential_id: URL_SAFE_NO_PAD.encode(credential_id),
+            public_key: hex::encode(cose_public_key(&cose_key)?),
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    // Checks a `navigator.credentials.get()` response signed by `credential`
+    // and returns the authenticator's new signature counter.
+    pub async fn verify_assertion(
+        &self,
+        user_id: &str,
+        credential: &PasskeyCredential,
+        client_data_json: &[u8],
+        authenticator_data: &[u8],
+        signature: &[u8],
+    ) -> Result<u32, TwoFactorError> {
+        self.verify_client_data(user_id, WebauthnCeremony::Authentication, client_data_json)
+            .await?;
+        let auth_data = self.parse_authenticator_data(authenticator_data)?;
+
+        let public_key = hex::decode(&credential.public_key)
+            .ok()
+            .and_then(|key| VerifyingKey::from_sec1_bytes(&key).ok())
+            .ok_or(TwoFactorError::MalformedResponse("stored public key"))?;
+        let signature = Signature::from_der(signature)
+            .map_err(|_| TwoFactorError::MalformedResponse("signature"))?;
+        let mut signed = authenticator_data.to_vec();
+        signed.extend_from_slice(&Sha256::digest(client_data_json));
+        public_key
+            .verify(&signed, &signature)
+            .map_err(|_| TwoFactorError::InvalidSignature)?;
+
+        // Authenticators without a counter always report 0. Any other counter
+        // has to move forward, or two copies of the key are in use.
+        let stored = credential.sign_count as u32;
+        if (stored != 0 || auth_data.sign_count != 0) && auth_data.sign_count <= stored {
+            return Err(TwoFactorError::CounterRegression(
+                stored,
+                auth_data.sign_count,
+            ));
+        }
+        Ok(auth_data.sign_count)
+    }
+
+    // The challenge is consumed before anything else is looked at, so a
+    // response is never checked twice.
+    async fn verify_client_data(
+        &self,
+        user_id: &str,
+        ceremony: WebauthnCeremony,
+        client_data_json: &[u8],
+    ) -> Result<(), TwoFactorError> {
+        let client_data = serde_json::from_slice::<CollectedClientData>(client_data_json)
+            .map_err(|_| TwoFactorError::MalformedResponse("client data"))?;
+        let consumed = self
+            .challenges
+            .consume(user_id, ceremony, &client_data.challenge)
+            .await?;
+        if !consumed {
+            return Err(TwoFactorError::InvalidChallenge);
+        }
+
+        let expected = match ceremony {
+            WebauthnCeremony::Registration => "webauthn.create",
+            WebauthnCeremony::Authentication => "webauthn.get",
+        };
+        if client_data.ceremony != expected {
+            return Err(TwoFactorError::MalformedResponse("client data type"));
+        }
+        if client_data.origin != self.origin {
+            return Err(TwoFactorError::WrongOrigin(client_data.origin));
+        }
+        Ok(())
+    }
+
+    // Layout from WebAuthn §6.1: RP ID hash, flags, counter, then the attested
+    // credential data when the AT flag is set.
+    fn parse_authenticator_data(&self, data: &[u8]) -> Result<AuthenticatorData, TwoFactorError> {
+        let (Some(rp_id_hash), Some(&flags), Some(sign_count)) =
+            (data.get(..32), data.get(32), data.get(33..37))
+        else {
+            return Err(TwoFactorError::MalformedResponse("authenticator data"));
+        };
+        if rp_id_hash != Sha256::digest(self.rp_id.as_bytes()).as_slice() {
+            return Err(TwoFactorError::WrongRelyingParty);
+        }
+        if flags & FLAG_USER_PRESENT == 0 {
+            return Err(TwoFactorError::UserNotPresent);
+        }
+        let sign_count =
+            u32::from_be_bytes([sign_count[0], sign_count[1], sign_count[2], sign_count[3]]);
+        if flags & FLAG_ATTESTED_CREDENTIAL == 0 {
+            return Ok(AuthenticatorData {
+                sign_count,
+                credential: None,
+            });
+        }
+
+        // 16 bytes of AAGUID, then the length of the credential id. The COSE
+        // key may be followed by extensions, so only one CBOR item is read.
+        let credential = data.get(53..55).and_then(|id_length| {
+            let id_end = 55 + u16::from_be_bytes([id_length[0], id_length[1]]) as usize;
+            let credential_id = data.get(55..id_end)?;
+            let mut deserializer = serde_cbor::Deserializer::from_slice(data.get(id_end..)?);
+            match Value::deserialize(&mut deserializer) {
+                Ok(Value::Map(cose_key)) => Some((credential_id.to_vec(), cose_key)),
+                _ => None,
+            }
+        });
+        match credential {
+            Some(credential) => Ok(AuthenticatorData {
+                sign_count,
+                credential: Some(credential),
+            }),
+            None => Err(TwoFactorError::MalformedResponse("attested credential")),
+        }
+    }
+}
+
+// The uncompressed SEC1 encoding of an ES256 COSE key.
+fn cose_public_key(cose_key: &BTreeMap<Value, Value>) -> Result<Vec<u8>, TwoFactorError> {
+    let integer = |key: i128| match cose_key.get(&Value::Integer(key)) {
+        Some(Value::Integer(value)) => Some(*value),
+        _ => None,
+    };
+    if integer(COSE_KTY) != Some(COSE_KTY_EC2)
+        || integer(COSE_ALG) != Some(COSE_ALG_ES256)
+        || integer(COSE_CRV) != Some(COSE_CRV_P256)
+    {
+        return Err(TwoFactorError::UnsupportedAlgorithm);
+    }
+    let coordinate = |key: i128| match cose_key.get(&Value::Integer(key)) {
+        Some(Value::Bytes(value)) if value.len() == 32 => Some(value.as_slice()),
+        _ => None,
+    };
+    let (Some(x), Some(y)) = (coordinate(COSE_X), coordinate(COSE_Y)) else {
+        return Err(TwoFactorError::MalformedResponse("credential public key"));
+    };
+    let public_key = [&[0x04], x, y].concat();
+    match VerifyingKey::from_sec1_bytes(&public_key) {
+        Ok(_) => Ok(public_key),
+        Err(_) => Err(TwoFactorError::MalformedResponse("credential public key")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{SigningKey, signature::Signer};
+
+    const RP_ID: &str = "wallet.example";
+    const ORIGIN: &str = "https://wallet.example";
+    const USER: &str = "user";
+
+    // A software authenticator holding one ES256 credential.
+    struct Authenticator {
+        key: SigningKey,
+        credential_id: Vec<u8>,
+        rp_id: String,
+        flags: u8,
+        sign_count: u32,
+    }
+
+    impl Authenticator {
+        fn new(seed: u8) -> Self {
+            Authenticator {
+                key: SigningKey::from_bytes(&[seed; 32].into()).unwrap(),
+                credential_id: vec![seed; 16],
+                rp_id: String::from(RP_ID),
+                flags: FLAG_USER_PRESENT,
+                sign_count: 0,
+            }
+        }
+
+        fn public_key(&self) -> Vec<u8> {
+            self.key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec()
+        }
+
+        fn authenticator_data(&self, attested: bool) -> Vec<u8> {
+            let flags = match attested {
+                true => self.flags | FLAG_ATTESTED_CREDENTIAL,
+                false => self.flags,
+            };
+            let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+            data.push(flags);
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if attested {
+                let public_key = self.public_key();
+                let cose_key = BTreeMap::from([
+                    (Value::Integer(COSE_KTY), Value::Integer(COSE_KTY_EC2)),
+                    (Value::Integer(COSE_ALG), Value::Integer(COSE_ALG_ES256)),
+                    (Value::Integer(COSE_CRV), Value::Integer(COSE_CRV_P256)),
+                    (
+                        Value::Integer(COSE_X),
+                        Value::Bytes(public_key[1..33].to_vec()),
+                    ),
+                    (
+                        Value::Integer(COSE_Y),
+                        Value::Bytes(public_key[33..].to_vec()),
+                    ),
+                ]);
+                data.extend_from_slice(&[0u8; 16]);
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                data.extend(serde_cbor::to_vec(&Value::Map(cose_key)).unwrap());
+            }
+            data
+        }
+
+        // `navigator.credentials.create()` with `attestation: "none"`.
+        fn register(&self, challenge: &str) -> (Vec<u8>, Vec<u8>) {
+            let attestation = BTreeMap::from([
+                (
+                    Value::Text(String::from("fmt")),
+                    Value::Text(String::from("none")),
+                ),
+                (
+                    Value::Text(String::from("attStmt")),
+                    Value::Map(BTreeMap::new()),
+                ),
+                (
+                    Value::Text(String::from("authData")),
+                    Value::Bytes(self.authenticator_data(true)),
+                ),
+            ]);
+            (
+                client_data("webauthn.create", challenge, ORIGIN),
+                serde_cbor::to_vec(&Value::Map(attestation)).unwrap(),
+            )
+        }
+
+        // `navigator.credentials.get()`, which moves the counter forward.
+        fn assert(&mut self, challenge: &str) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+            self.sign_count += 1;
+            let client_data_json = client_data("webauthn.get", challenge, ORIGIN);
+            let authenticator_data = self.authenticator_data(false);
+            let mut signed = authenticator_data.clone();
+            signed.extend_from_slice(&Sha256::digest(&client_data_json));
+            let signature: Signature = self.key.sign(&signed);
+            (
+                client_data_json,
+                authenticator_data,
+                signature.to_der().as_bytes().to_vec(),
+            )
+        }
+    }
+
+    fn client_data(ceremony: &str, challenge: &str, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": ceremony,
+            "challenge": challenge,
+            "origin": origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    fn manager() -> WebauthnManager {
+        WebauthnManager::new(
+            RP_ID,
+            "Wallet",
+            ORIGIN,
+            Arc::new(MemoryChallengeStore::default()),
+        )
+        .unwrap()
+    }
+
+    async fn register(
+        manager: &WebauthnManager,
+        authenticator: &Authenticator,
+    ) -> PasskeyCredential {
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Registration)
+            .await
+            .unwrap();
+        let (client_data_json, attestation_object) = authenticator.register(&challenge);
+        let registered = manager
+            .verify_registration(USER, &client_data_json, &attestation_object)
+            .await
+            .unwrap();
+        PasskeyCredential {
+            credential_id: registered.credential_id,
+            public_key: registered.public_key,
+            sign_count: registered.sign_count as i64,
+            name: String::from("test"),
+            created_at: DateTime::now(),
+        }
+    }
+
+    #[test]
+    fn rp_id_must_cover_the_origin() {
+        let store = || Arc::new(MemoryChallengeStore::default());
+        assert!(WebauthnManager::new("other.example", "Wallet", ORIGIN, store()).is_err());
+        assert!(WebauthnManager::new("llet.example", "Wallet", ORIGIN, store()).is_err());
+        // A parent domain of the origin is allowed.
+        assert!(WebauthnManager::new("example", "Wallet", ORIGIN, store()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn registration_returns_the_attested_key() {
+        let manager = manager();
+        let authenticator = Authenticator::new(1);
+        let credential = register(&manager, &authenticator).await;
+
+        assert_eq!(
+            credential.credential_id,
+            URL_SAFE_NO_PAD.encode(&authenticator.credential_id)
+        );
+        assert_eq!(
+            credential.public_key,
+            hex::encode(authenticator.public_key())
+        );
+        assert_eq!(credential.sign_count, 0);
+    }
+
+    #[tokio::test]
+    async fn registration_checks_the_ceremony() {
+        let manager = manager();
+        let mut authenticator = Authenticator::new(1);
+
+        // An authentication challenge cannot be used to register.
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Authentication)
+            .await
+            .unwrap();
+        let (client_data_json, attestation_object) = authenticator.register(&challenge);
+        assert!(matches!(
+            manager
+                .verify_registration(USER, &client_data_json, &attestation_object)
+                .await,
+            Err(TwoFactorError::InvalidChallenge)
+        ));
+
+        authenticator.rp_id = String::from("attacker.example");
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Registration)
+            .await
+            .unwrap();
+        let (client_data_json, attestation_object) = authenticator.register(&challenge);
+        assert!(matches!(
+            manager
+                .verify_registration(USER, &client_data_json, &attestation_object)
+                .await,
+            Err(TwoFactorError::WrongRelyingParty)
+        ));
+
+        authenticator.rp_id = String::from(RP_ID);
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Registration)
+            .await
+            .unwrap();
+        let (_, attestation_object) = authenticator.register(&challenge);
+        let client_data_json =
+            client_data("webauthn.create", &challenge, "https://attacker.example");
+        assert!(matches!(
+            manager
+                .verify_registration(USER, &client_data_json, &attestation_object)
+                .await,
+            Err(TwoFactorError::WrongOrigin(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn assertion_verifies_and_moves_the_counter() {
+        let manager = manager();
+        let mut authenticator = Authenticator::new(1);
+        let mut credential = register(&manager, &authenticator).await;
+
+        for expected in 1..=2 {
+            let challenge = manager
+                .challenge(USER, WebauthnCeremony::Authentication)
+                .await
+                .unwrap();
+            let (client_data_json, authenticator_data, signature) =
+                authenticator.assert(&challenge);
+            let sign_count = manager
+                .verify_assertion(
+                    USER,
+                    &credential,
+                    &client_data_json,
+                    &authenticator_data,
+                    &signature,
+                )
+                .await
+                .unwrap();
+            assert_eq!(sign_count, expected);
+            credential.sign_count = sign_count as i64;
+        }
+    }
+
+    #[tokio::test]
+    async fn assertions_are_accepted_once() {
+        let manager = manager();
+        let mut authenticator = Authenticator::new(1);
+        let credential = register(&manager, &authenticator).await;
+
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Authentication)
+            .await
+            .unwrap();
+        let (client_data_json, authenticator_data, signature) = authenticator.assert(&challenge);
+        for expected in [true, false] {
+            let verified = manager
+                .verify_assertion(
+                    USER,
+                    &credential,
+                    &client_data_json,
+                    &authenticator_data,
+                    &signature,
+                )
+                .await;
+            match expected {
+                true => assert!(verified.is_ok()),
+                false => assert!(matches!(verified, Err(TwoFactorError::InvalidChallenge))),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn assertion_rejects_other_keys_and_cloned_authenticators() {
+        let manager = manager();
+        let mut authenticator = Authenticator::new(1);
+        let mut credential = register(&manager, &authenticator).await;
+        credential.sign_count = 5;
+
+        // A counter at or below the stored one means a copy of the key.
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Authentication)
+            .await
+            .unwrap();
+        let (client_data_json, authenticator_data, signature) = authenticator.assert(&challenge);
+        assert!(matches!(
+            manager
+                .verify_assertion(
+                    USER,
+                    &credential,
+                    &client_data_json,
+                    &authenticator_data,
+                    &signature,
+                )
+                .await,
+            Err(TwoFactorError::CounterRegression(5, 1))
+        ));
+
+        let mut impostor = Authenticator::new(2);
+        impostor.sign_count = 10;
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Authentication)
+            .await
+            .unwrap();
+        let (client_data_json, authenticator_data, signature) = impostor.assert(&challenge);
+        assert!(matches!(
+            manager
+                .verify_assertion(
+                    USER,
+                    &credential,
+                    &client_data_json,
+                    &authenticator_data,
+                    &signature,
+                )
+                .await,
+            Err(TwoFactorError::InvalidSignature)
+        ));
+
+        authenticator.flags = 0;
+        authenticator.sign_count = 10;
+        let challenge = manager
+            .challenge(USER, WebauthnCeremony::Authentication)
+            .await
+            .unwrap();
+        let (client_data_json, authenticator_data, signature) = authenticator.assert(&challenge);
+        assert!(matches!(
+            manager
+                .verify_assertion(
+                    USER,
+                    &credential,
+                    &client_data_json,
+                    &authenticator_data,
+                    &signature,
+                )
+                .await,
+            Err(TwoFactorError::UserNotPresent)
+        ));
+    }
+}